@@ -0,0 +1,42 @@
+// Records the compiler version for the machine fingerprint in result files,
+// and the git commit, branch and dirty state the binary is built from so a
+// results file can be traced back to its code
+use std::process::Command;
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RUSTC_VERSION={}", version);
+    println!("cargo:rerun-if-env-changed=RUSTC");
+
+    // All empty outside a git checkout; the branch also on a detached HEAD
+    let commit = git(&["rev-parse", "HEAD"]);
+    println!("cargo:rustc-env=GIT_COMMIT={}", commit.clone().unwrap_or_default());
+    println!("cargo:rustc-env=GIT_BRANCH={}", git(&["symbolic-ref", "--short", "-q", "HEAD"]).unwrap_or_default());
+    // Dirty when tracked files differ from the commit; untracked files do not count
+    let dirty = commit.and_then(|_| git(&["status", "--porcelain", "--untracked-files=no"])).map(|changes| !changes.is_empty());
+    println!("cargo:rustc-env=GIT_DIRTY={}", dirty.map(|dirty| dirty.to_string()).unwrap_or_default());
+    // Rebuild when HEAD moves to another branch or commit, or the index
+    // changes; edits outside this package are only noticed once staged
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        for path in ["HEAD", "index", "refs/heads", "packed-refs"] {
+            println!("cargo:rerun-if-changed={}/{}", git_dir, path);
+        }
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_string())
+}
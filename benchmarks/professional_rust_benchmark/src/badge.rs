@@ -0,0 +1,70 @@
+// shields.io endpoint badges (--badges) for the headline benchmarks, the
+// ones registered with .badge(label): a small JSON file per benchmark that
+// https://img.shields.io/endpoint?url=... turns into e.g. "tasks/sec | 1.23M",
+// so the README can show the numbers of the latest published run. The color
+// says how far to trust it: red for errors or, with a --baseline, a
+// regression, yellow when the benchmark was too noisy, green otherwise.
+
+use serde_json::json;
+
+use crate::{BenchmarkResult, BenchmarkStatus};
+
+// Throughput badge for a timed result; None for other measurements, whose
+// label would not fit, and for results without a mean
+pub fn to_json(label: &str, result: &BenchmarkResult, baseline: &[(String, f64)], max_cv: f64, max_slowdown: f64) -> Option<String> {
+    if result.status != BenchmarkStatus::Completed || !result.is_timed() || result.stats.mean_ns <= 0.0 {
+        return None;
+    }
+    let regressed = result.slowdown(baseline).is_some_and(|slowdown| slowdown > max_slowdown);
+    let color = if result.error_count > 0 || regressed {
+        "red"
+    } else if result.stats.cv > max_cv {
+        "yellow"
+    } else {
+        "brightgreen"
+    };
+    let badge = json!({
+        "schemaVersion": 1,
+        "label": label,
+        "message": si_rate(1e9 / result.stats.mean_ns),
+        "color": color,
+    });
+    Some(badge.to_string())
+}
+
+// Three significant digits with a k/M/G suffix: 987, 45.6k, 1.23M
+pub fn si_rate(rate: f64) -> String {
+    let (scaled, suffix) = if rate >= 1e9 {
+        (rate / 1e9, "G")
+    } else if rate >= 1e6 {
+        (rate / 1e6, "M")
+    } else if rate >= 1e3 {
+        (rate / 1e3, "k")
+    } else {
+        (rate, "")
+    };
+    let decimals = if scaled >= 100.0 { 0 } else if scaled >= 10.0 { 1 } else { 2 };
+    format!("{:.*}{}", decimals, scaled, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::completed;
+
+    #[test]
+    fn rates_and_colors() {
+        assert_eq!([si_rate(987.0), si_rate(45_600.0), si_rate(1_234_000.0), si_rate(2.5e9)], ["987", "45.6k", "1.23M", "2.50G"]);
+
+        let result = completed("Spawn", 800..=800);
+        let badge: serde_json::Value = serde_json::from_str(&to_json("tasks/sec", &result, &[], 0.1, 0.05).unwrap()).unwrap();
+        assert_eq!(badge, json!({"schemaVersion": 1, "label": "tasks/sec", "message": "1.25M", "color": "brightgreen"}));
+        let color = |baseline: &[(String, f64)]| {
+            let badge: serde_json::Value = serde_json::from_str(&to_json("tasks/sec", &result, baseline, 0.1, 0.05).unwrap()).unwrap();
+            badge["color"].as_str().unwrap().to_string()
+        };
+        assert_eq!(color(&[("Spawn".to_string(), 700.0)]), "red");
+        assert_eq!(color(&[("Spawn".to_string(), 790.0)]), "brightgreen");
+        assert!(to_json("tasks/sec", &BenchmarkResult::skipped("Spawn", "no".to_string()), &[], 0.1, 0.05).is_none());
+    }
+}
@@ -0,0 +1,1438 @@
+// The benchmark bodies, each one the Rust counterpart of a FlowCoro
+// scenario, and the registry that names, tags and constrains them.
+
+use std::time::{Instant, SystemTime};
+use std::hash::BuildHasher;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use tokio::task::JoinSet;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite, LengthDelimitedCodec, LinesCodec};
+use futures::{SinkExt, StreamExt};
+use chrono::{DateTime, Local};
+
+use crate::runner::{spawn_task, BenchmarkRunner, StageRecorder};
+use crate::{
+    BenchError, BenchRng, BenchmarkRegistry, BenchmarkResult, EnvHook, Requirement, BUSY_POLL_US, FLOWCORO_SLA,
+};
+#[cfg(target_os = "linux")]
+use crate::{InstructionCounter, Measurement};
+
+async fn benchmark_task_creation_and_execution(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.try_run(name, || async {
+        let handle = tokio::spawn(async {
+            // 模拟任务执行中的一些计算
+            let mut sum = 0;
+            for i in 0..10 {
+                sum += i;
+            }
+            sum
+        });
+        handle.await?;
+        Ok(())
+    }).await
+}
+
+async fn benchmark_channel_ops(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.try_run(name, || async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        tx.send(42).await?;
+        rx.recv().await.ok_or_else(|| BenchError::new("channel closed"))?;
+        Ok(())
+    }).await
+}
+
+fn benchmark_simple_computation(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        let mut sum = 0;
+        for i in 0..100 {
+            sum += i;
+        }
+        sum
+    })
+}
+
+// 复杂任务基准测试 - 测试调度器处理复杂计算的能力
+fn benchmark_complex_computation(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        // 1. 矩阵运算 (3x3矩阵乘法)
+        let matrix_a = [1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7, 8.8, 9.9];
+        let matrix_b = [9.9, 8.8, 7.7, 6.6, 5.5, 4.4, 3.3, 2.2, 1.1];
+        let mut result_matrix = [0.0; 9];
+        
+        for i in 0..3 {
+            for j in 0..3 {
+                for k in 0..3 {
+                    result_matrix[i * 3 + j] += matrix_a[i * 3 + k] * matrix_b[k * 3 + j];
+                }
+            }
+        }
+        
+        // 2. 字符串处理和哈希计算
+        let data = "ComplexTaskBenchmark";
+        let mut hash: u64 = 0;
+        for c in data.chars() {
+            hash = hash.wrapping_mul(31).wrapping_add(c as u64);
+            hash ^= hash >> 16;
+        }
+        
+        // 3. 三角函数计算
+        let mut trig_sum = 0.0;
+        for i in 1..=50 {
+            let angle = i as f64 * 0.1;
+            trig_sum += angle.sin() * angle.cos() + (angle * 0.5).tan();
+        }
+        
+        // 4. 动态内存操作
+        let mut dynamic_data = Vec::with_capacity(100);
+        for i in 0..100 {
+            dynamic_data.push(i * i + (hash % 1000) as i32);
+        }
+        
+        // 5. 复杂条件分支和数据处理
+        let mut final_result = 0.0;
+        for (i, &val) in dynamic_data.iter().enumerate() {
+            if val % 3 == 0 {
+                final_result += (val as f64).sqrt();
+            } else if val % 5 == 0 {
+                final_result += (val as f64 + 1.0).ln();
+            } else {
+                final_result += val as f64 * 0.1;
+            }
+            let _ = i; // 防止编译器优化
+        }
+        
+        // 6. 合并所有计算结果
+        let mut total = 0.0;
+        for val in result_matrix.iter() {
+            total += val;
+        }
+        total += trig_sum + final_result + hash as f64;
+        
+        total.to_bits() // 返回结果防止编译器优化（f64没有实现Hash）
+    })
+}
+
+async fn benchmark_concurrent_tasks(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    let tasks = runner.task_recorder();
+    let mut result = runner.try_run_tracked(name, &tasks, || async {
+        let mut join_set = JoinSet::new();
+        
+        for _ in 0..10 {
+            spawn_task(&mut join_set, tasks.track(async {
+                tokio::time::sleep(tokio::time::Duration::from_micros(1)).await;
+            }));
+        }
+
+        while let Some(result) = join_set.join_next().await {
+            result?;
+        }
+        Ok(())
+    }).await;
+    result.workers = tasks.breakdown(&runner.stats_config);
+    result
+}
+
+// 持续派生：信号量限制在途任务数，每次迭代获取一个许可并派生一个任务，许可随任务
+// 结束释放。任务跨迭代存活，测得的是稳态下可持续的派生速率，而不是一次性派生全部
+// 任务时主要由内存分配决定的开销
+const SPAWN_IN_FLIGHT: usize = 256;
+
+async fn benchmark_bounded_spawn(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    let permits = Arc::new(tokio::sync::Semaphore::new(SPAWN_IN_FLIGHT));
+    let tasks = Rc::new(RefCell::new(JoinSet::new()));
+    let result = runner.try_run(name, || {
+        let permits = Arc::clone(&permits);
+        let tasks = Rc::clone(&tasks);
+        async move {
+            // Waits for a task to finish once SPAWN_IN_FLIGHT are running
+            let permit = permits.acquire_owned().await?;
+            let mut tasks = tasks.borrow_mut();
+            spawn_task(&mut tasks, async move {
+                tokio::task::yield_now().await;
+                drop(permit);
+            });
+            // Reap finished tasks so their results don't pile up either
+            while let Some(outcome) = tasks.try_join_next() {
+                outcome?;
+            }
+            Ok(())
+        }
+    }).await;
+    let mut remaining = tasks.take();
+    while remaining.join_next().await.is_some() {}
+    result
+}
+
+async fn benchmark_echo_server(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    
+    let result = runner.run(name, || async {
+        // Simulate network processing without server startup overhead
+        let data = [65u8; 20]; // Fill with 'A' characters
+        
+        // Simulate echo processing
+        let mut echo = Vec::with_capacity(data.len());
+        echo.extend_from_slice(&data);
+        
+        // Simulate checksum validation
+        let sum: u32 = echo.iter().map(|&b| b as u32).sum();
+        let _ = sum;
+    }).await;
+    
+    result
+}
+
+async fn benchmark_concurrent_echo_clients(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    const CLIENT_COUNT: usize = 100;  // 与FlowCoro和Go保持一致：100个并发任务
+    
+    let tasks = runner.task_recorder();
+    let mut result = runner.try_run_tracked(name, &tasks, || async {
+        let mut join_set = JoinSet::new();
+        
+        for _ in 0..CLIENT_COUNT {
+            spawn_task(&mut join_set, tasks.track(async {
+                // 模拟更多的网络处理工作（与FlowCoro和Go一致）
+                let mut work = 0;
+                for j in 0..1000 {  // 1000次循环，与FlowCoro和Go一致
+                    work += j * j;  // 更复杂的计算
+                }
+                
+                // 模拟网络延迟（与FlowCoro和Go的sleep对应）
+                tokio::time::sleep(tokio::time::Duration::from_micros(1)).await;
+                
+                let _ = work; // 防止编译器优化
+            }));
+        }
+        
+        while let Some(result) = join_set.join_next().await {
+            result?;
+        }
+        Ok(())
+    }).await;
+    result.workers = tasks.breakdown(&runner.stats_config);
+    
+    result
+}
+
+// The heap allocation is part of what is being measured, keep the Vec.
+#[allow(clippy::useless_vec)]
+fn benchmark_small_data_transfer(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        let mut data = vec![0u8; 64];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = (i % 256) as u8;
+        }
+        // Simulate checksum
+        let sum: usize = data.iter().map(|&b| b as usize).sum();
+        let _ = sum;
+    })
+}
+
+fn benchmark_medium_data_transfer(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        let mut data = vec![0u8; 4096];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = (i % 256) as u8;
+        }
+        // Simulate checksum
+        let sum: usize = data.iter().map(|&b| b as usize).sum();
+        let _ = sum;
+    })
+}
+
+fn benchmark_large_data_transfer(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        let mut data = vec![0u8; 65536];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = (i % 256) as u8;
+        }
+        // Simulate compression
+        let mut compressed_size = 0;
+        for i in (0..data.len()).step_by(64) {
+            if i > 0 && data[i] == data[i - 64] {
+                compressed_size += 1; // compression marker
+            } else {
+                compressed_size += 64; // raw data
+            }
+        }
+        let _ = compressed_size;
+    })
+}
+
+fn benchmark_memory_allocation(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        let mut data = vec![0u8; 1024];
+        // Use the data to prevent optimization
+        data[0] = 1;
+        data[1023] = 1;
+        let _ = data;
+    })
+}
+
+// 完整请求管线：解析 → 处理 → 序列化 → 写出，按阶段分别计时
+const PIPELINE_STAGES: &[&str] = &["parse", "handle", "serialize", "write"];
+const PIPELINE_REQUEST: &[u8] = b"POST /api/orders HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 36\r\n\r\n{\"item\":\"book\",\"qty\":3,\"price\":12.5}";
+
+async fn benchmark_http_pipeline(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    let stages = StageRecorder::new(PIPELINE_STAGES);
+    let mut result = runner.try_run(name, || async {
+        stages.begin();
+
+        let text = std::str::from_utf8(PIPELINE_REQUEST)?;
+        let (head, body) = text.split_once("\r\n\r\n").ok_or_else(|| BenchError::new("incomplete request"))?;
+        let mut lines = head.split("\r\n");
+        let request_line = lines.next().unwrap_or_default();
+        let headers: Vec<(&str, &str)> = lines.filter_map(|line| line.split_once(": ")).collect();
+        let content_length: usize = headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Content-Length"))
+            .and_then(|(_, value)| value.parse().ok())
+            .ok_or_else(|| BenchError::new("missing Content-Length"))?;
+        let body = body.get(..content_length).ok_or_else(|| BenchError::new("truncated body"))?;
+        stages.mark();
+
+        let order: serde_json::Value = serde_json::from_str(body)?;
+        let qty = order["qty"].as_u64().unwrap_or(0);
+        let price = order["price"].as_f64().unwrap_or(0.0);
+        let reply = serde_json::json!({
+            "request": request_line,
+            "item": order["item"],
+            "total": qty as f64 * price,
+        });
+        stages.mark();
+
+        let payload = serde_json::to_vec(&reply)?;
+        let mut response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", payload.len()).into_bytes();
+        response.extend_from_slice(&payload);
+        stages.mark();
+
+        let mut writer = BufWriter::new(tokio::io::sink());
+        writer.write_all(&response).await?;
+        writer.flush().await?;
+        stages.mark();
+
+        stages.commit();
+        Ok(response.len())
+    }).await;
+    result.stages = stages.attribution(result.iterations);
+    result
+}
+
+async fn benchmark_http_processing(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run(name, || async {
+        let request = "GET /api/data HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nHello, World!";
+        
+        // Simulate request parsing
+        let _ = request.len();
+        // Simulate response generation
+        let _ = response.len();
+    }).await
+}
+
+// 时间戳基准测试 - 每个请求都要打时间戳，这部分开销经常出现在profile中
+fn benchmark_instant_now(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        std::hint::black_box(Instant::now());
+    })
+}
+
+fn benchmark_system_time_now(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        std::hint::black_box(SystemTime::now());
+    })
+}
+
+// Cost of reading the instructions perf counter, i.e. of the read(2) that
+// --measure instructions adds around every iteration
+#[cfg(target_os = "linux")]
+fn benchmark_perf_counter_read(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    match InstructionCounter::new() {
+        Ok(counter) => runner.run_sync(name, || counter.read()),
+        Err(error) => BenchmarkResult::setup_failed(name, BenchError::new(error)),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn benchmark_perf_counter_read(_runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    BenchmarkResult::setup_failed(name, BenchError::new("perf counters require Linux"))
+}
+
+// Bare io_uring ring, only set up and entered; nothing is mapped or submitted
+#[cfg(target_os = "linux")]
+pub struct IoUring(std::os::fd::OwnedFd);
+
+#[cfg(target_os = "linux")]
+impl IoUring {
+    pub fn new(entries: u32) -> std::io::Result<Self> {
+        use std::os::fd::FromRawFd;
+        // struct io_uring_params, all zero: no flags, kernel fills the rest
+        let mut params = [0u32; 30];
+        // SAFETY: params is a writable io_uring_params-sized buffer
+        let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, entries, params.as_mut_ptr()) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: the kernel just returned this fd and nothing else owns it
+        Ok(Self(unsafe { std::os::fd::OwnedFd::from_raw_fd(fd as libc::c_int) }))
+    }
+
+    // io_uring_enter with nothing to submit or wait for: the fixed cost of
+    // entering the ring
+    fn enter_empty(&self) -> libc::c_long {
+        use std::os::fd::AsRawFd;
+        // SAFETY: no SQEs are submitted and no sigset is passed
+        unsafe { libc::syscall(libc::SYS_io_uring_enter, self.0.as_raw_fd(), 0u32, 0u32, 0u32, std::ptr::null::<libc::c_void>(), 0usize) }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn benchmark_io_uring_enter(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    match IoUring::new(1) {
+        Ok(ring) => runner.try_run_sync(name, || {
+            let ret = ring.enter_empty();
+            if ret < 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            Ok(ret)
+        }),
+        Err(error) => BenchmarkResult::setup_failed(name, error.into()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn benchmark_io_uring_enter(_runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    BenchmarkResult::setup_failed(name, BenchError::new("io_uring requires Linux"))
+}
+
+// 与rust_benchmark.rs中的get_current_time()相同的格式化方式
+fn format_current_time() -> String {
+    let local: DateTime<Local> = Local::now();
+    local.format("%H:%M:%S").to_string()
+}
+
+fn benchmark_timestamp_formatting(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        std::hint::black_box(format_current_time());
+    })
+}
+
+async fn benchmark_concurrent_timestamping(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    const TASK_COUNT: usize = 10;
+    const STAMPS_PER_TASK: usize = 100;
+
+    runner.try_run(name, || async {
+        let mut join_set = JoinSet::new();
+
+        for _ in 0..TASK_COUNT {
+            spawn_task(&mut join_set, async {
+                // 每个"请求"读取一次单调时钟、一次墙上时钟并格式化一次
+                for _ in 0..STAMPS_PER_TASK {
+                    let start = Instant::now();
+                    let wall = SystemTime::now();
+                    let formatted = format_current_time();
+                    std::hint::black_box((start.elapsed(), wall, formatted));
+                }
+            });
+        }
+
+        while let Some(result) = join_set.join_next().await {
+            result?;
+        }
+        Ok(())
+    }).await
+}
+
+// 哈希函数对比 - HashMap密集型负载的差异主要来自这里
+const HASH_SMALL_KEY: &[u8] = b"user:1000042";
+
+fn hash_payload_4kb() -> Vec<u8> {
+    (0..4096).map(|i| (i % 251) as u8).collect()
+}
+
+fn benchmark_hasher<S: BuildHasher>(runner: &BenchmarkRunner, name: &str, build_hasher: S, input: &[u8]) -> BenchmarkResult {
+    runner.run_sync(name, || build_hasher.hash_one(std::hint::black_box(input)))
+}
+
+// 并发HashMap对比 - 服务中最常见的共享状态访问模式
+trait ConcurrentMap: Send + Sync + 'static {
+    fn get(&self, key: u64) -> Option<u64>;
+    fn insert(&self, key: u64, value: u64);
+}
+
+impl ConcurrentMap for Mutex<HashMap<u64, u64>> {
+    fn get(&self, key: u64) -> Option<u64> {
+        self.lock().unwrap().get(&key).copied()
+    }
+
+    fn insert(&self, key: u64, value: u64) {
+        self.lock().unwrap().insert(key, value);
+    }
+}
+
+impl ConcurrentMap for RwLock<HashMap<u64, u64>> {
+    fn get(&self, key: u64) -> Option<u64> {
+        self.read().unwrap().get(&key).copied()
+    }
+
+    fn insert(&self, key: u64, value: u64) {
+        self.write().unwrap().insert(key, value);
+    }
+}
+
+impl ConcurrentMap for dashmap::DashMap<u64, u64> {
+    fn get(&self, key: u64) -> Option<u64> {
+        dashmap::DashMap::get(self, &key).map(|v| *v)
+    }
+
+    fn insert(&self, key: u64, value: u64) {
+        dashmap::DashMap::insert(self, key, value);
+    }
+}
+
+// 固定数量的 Mutex<HashMap> 分片，按key哈希选择分片
+struct ShardedMap {
+    shards: Vec<Mutex<HashMap<u64, u64>>>,
+    hasher: std::collections::hash_map::RandomState,
+}
+
+impl ShardedMap {
+    fn new(shard_count: usize) -> Self {
+        Self {
+            shards: (0..shard_count).map(|_| Mutex::new(HashMap::new())).collect(),
+            hasher: std::collections::hash_map::RandomState::new(),
+        }
+    }
+
+    fn shard(&self, key: u64) -> &Mutex<HashMap<u64, u64>> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        &self.shards[index]
+    }
+}
+
+impl ConcurrentMap for ShardedMap {
+    fn get(&self, key: u64) -> Option<u64> {
+        self.shard(key).lock().unwrap().get(&key).copied()
+    }
+
+    fn insert(&self, key: u64, value: u64) {
+        self.shard(key).lock().unwrap().insert(key, value);
+    }
+}
+
+const MAP_KEY_COUNT: u64 = 1024;
+const MAP_TASK_COUNT: u64 = 16;
+const MAP_OPS_PER_TASK: u64 = 100;
+
+async fn benchmark_concurrent_map<M: ConcurrentMap>(runner: &BenchmarkRunner, name: &str, map: M) -> BenchmarkResult {
+    for key in 0..MAP_KEY_COUNT {
+        map.insert(key, key);
+    }
+    let map = Arc::new(map);
+
+    runner.try_run_with_rng(name, |rng| {
+        let map = Arc::clone(&map);
+        let task_seeds: Vec<u64> = (0..MAP_TASK_COUNT).map(|_| rng.next_u64()).collect();
+        async move {
+            let mut join_set = JoinSet::new();
+
+            for task_seed in task_seeds {
+                let map = Arc::clone(&map);
+                spawn_task(&mut join_set, async move {
+                    let mut rng = BenchRng::new(task_seed);
+                    // 90% 读 / 10% 写，随机key
+                    for op in 0..MAP_OPS_PER_TASK {
+                        let key = rng.gen_range(MAP_KEY_COUNT);
+                        if rng.gen_range(10) == 0 {
+                            map.insert(key, op);
+                        } else {
+                            std::hint::black_box(map.get(key));
+                        }
+                    }
+                });
+            }
+
+            while let Some(result) = join_set.join_next().await {
+                result?;
+            }
+            Ok(())
+        }
+    }).await
+}
+
+// 账户转账 - 在持有一把锁时让出、再获取第二把锁，检查各种交错下总余额守恒。
+// --deterministic 下任务的轮询顺序由种子决定，不变量被破坏时该迭代记为错误，
+// 可以用同一个种子复现
+const TRANSFER_ACCOUNTS: u64 = 8;
+const TRANSFER_BALANCE: u64 = 1000;
+const TRANSFER_TASK_COUNT: u64 = 16;
+const TRANSFERS_PER_TASK: u64 = 20;
+
+async fn benchmark_account_transfers(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.try_run_with_rng(name, |rng| {
+        let task_seeds: Vec<u64> = (0..TRANSFER_TASK_COUNT).map(|_| rng.next_u64()).collect();
+        async move {
+            let accounts: Arc<Vec<tokio::sync::Mutex<u64>>> =
+                Arc::new((0..TRANSFER_ACCOUNTS).map(|_| tokio::sync::Mutex::new(TRANSFER_BALANCE)).collect());
+            let mut join_set = JoinSet::new();
+
+            for task_seed in task_seeds {
+                let accounts = Arc::clone(&accounts);
+                spawn_task(&mut join_set, async move {
+                    let mut rng = BenchRng::new(task_seed);
+                    for _ in 0..TRANSFERS_PER_TASK {
+                        let from = rng.gen_range(TRANSFER_ACCOUNTS);
+                        let to = (from + 1 + rng.gen_range(TRANSFER_ACCOUNTS - 1)) % TRANSFER_ACCOUNTS;
+                        let amount = rng.gen_range(100);
+                        // 按下标顺序加锁，方向相反的转账不会死锁
+                        let mut lower = accounts[from.min(to) as usize].lock().await;
+                        tokio::task::yield_now().await;
+                        let mut upper = accounts[from.max(to) as usize].lock().await;
+                        let (source, target) = if from < to { (&mut *lower, &mut *upper) } else { (&mut *upper, &mut *lower) };
+                        if *source >= amount {
+                            *source -= amount;
+                            *target += amount;
+                        }
+                    }
+                });
+            }
+
+            while let Some(result) = join_set.join_next().await {
+                result?;
+            }
+            let mut total = 0;
+            for account in accounts.iter() {
+                total += *account.lock().await;
+            }
+            if total != TRANSFER_ACCOUNTS * TRANSFER_BALANCE {
+                return Err(BenchError::new(format!("balances add up to {} after the transfers, not {}", total, TRANSFER_ACCOUNTS * TRANSFER_BALANCE)));
+            }
+            Ok(())
+        }
+    }).await
+}
+
+// 请求上下文传递 - task_local! 与 thread_local! 的热路径访问开销
+const CONTEXT_ACCESSES: u64 = 100;
+
+thread_local! {
+    static THREAD_REQUEST_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+tokio::task_local! {
+    static TASK_REQUEST_ID: u64;
+}
+
+fn benchmark_thread_local_access(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_sync(name, || {
+        for i in 0..CONTEXT_ACCESSES {
+            THREAD_REQUEST_ID.with(|id| id.set(id.get().wrapping_add(i)));
+            std::hint::black_box(THREAD_REQUEST_ID.with(Cell::get));
+        }
+    })
+}
+
+async fn benchmark_task_local_access(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run(name, || {
+        TASK_REQUEST_ID.scope(1000042, async {
+            for _ in 0..CONTEXT_ACCESSES {
+                std::hint::black_box(TASK_REQUEST_ID.get());
+            }
+        })
+    }).await
+}
+
+async fn benchmark_task_local_scope(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run(name, || {
+        TASK_REQUEST_ID.scope(1000042, async {
+            std::hint::black_box(TASK_REQUEST_ID.get());
+        })
+    }).await
+}
+
+// 上下文传递开销 - 请求上下文(Arc元数据 + tracing span)穿过10层中间件
+const MIDDLEWARE_DEPTH: usize = 10;
+
+struct RequestMetadata {
+    request_id: u64,
+    user: String,
+    headers: Vec<(String, String)>,
+}
+
+#[derive(Clone)]
+struct RequestContext {
+    metadata: Arc<RequestMetadata>,
+    span: tracing::Span,
+}
+
+type LayerFuture = Pin<Box<dyn Future<Output = u64> + Send>>;
+
+fn middleware_with_context(ctx: RequestContext, depth: usize) -> LayerFuture {
+    Box::pin(async move {
+        if depth == 0 {
+            return ctx.metadata.request_id + ctx.metadata.user.len() as u64 + ctx.metadata.headers.len() as u64;
+        }
+        // 每一层都克隆上下文并在自己的span中调用下一层
+        let span = tracing::debug_span!(parent: &ctx.span, "middleware", depth);
+        let next = ctx.clone();
+        tracing::Instrument::instrument(middleware_with_context(next, depth - 1), span).await
+    })
+}
+
+fn middleware_without_context(request_id: u64, depth: usize) -> LayerFuture {
+    Box::pin(async move {
+        if depth == 0 {
+            return request_id;
+        }
+        middleware_without_context(request_id, depth - 1).await
+    })
+}
+
+async fn benchmark_context_propagation(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_with_rng(name, |rng| {
+        let metadata = Arc::new(RequestMetadata {
+            request_id: rng.next_u64(),
+            user: "user:1000042".to_string(),
+            headers: vec![
+                ("x-request-id".to_string(), "abc123".to_string()),
+                ("x-tenant".to_string(), "flowcoro".to_string()),
+            ],
+        });
+        async move {
+            let ctx = RequestContext {
+                span: tracing::info_span!("request", id = metadata.request_id),
+                metadata,
+            };
+            std::hint::black_box(middleware_with_context(ctx, MIDDLEWARE_DEPTH).await);
+        }
+    }).await
+}
+
+// 基线: 相同深度的调用链，但不携带上下文
+async fn benchmark_call_chain_baseline(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_with_rng(name, |rng| {
+        let request_id = rng.next_u64();
+        async move {
+            std::hint::black_box(middleware_without_context(request_id, MIDDLEWARE_DEPTH).await);
+        }
+    }).await
+}
+
+// 缓冲 vs 非缓冲套接字IO - 长度前缀回显负载
+// 每个网络基准测试都有两种传输方式: 回环TCP(经过内核协议栈)和内存duplex管道，
+// 两者的差值即内核网络栈的开销
+const SOCKET_MESSAGES: usize = 16;
+const SOCKET_PAYLOAD_SIZE: usize = 64;
+const DUPLEX_CAPACITY: usize = 64 * 1024;
+
+type TransportStream = tokio_util::either::Either<TcpStream, tokio::io::DuplexStream>;
+
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    Loopback,
+    InMemory,
+}
+
+// Nagle is disabled on both ends so the comparison measures syscall and
+// copy costs instead of delayed-ACK stalls on the unbuffered write pattern.
+// Busy polling is only enabled through EnvHook::BusyPoll.
+fn configure_socket(stream: &TcpStream) -> std::io::Result<()> {
+    stream.set_nodelay(true)?;
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+        let busy_poll_us = BUSY_POLL_US.load(std::sync::atomic::Ordering::Relaxed) as libc::c_int;
+        if busy_poll_us > 0 {
+            // SAFETY: the fd belongs to `stream` and the option value is a c_int
+            let rc = unsafe {
+                libc::setsockopt(stream.as_raw_fd(), libc::SOL_SOCKET, libc::SO_BUSY_POLL,
+                    (&busy_poll_us as *const libc::c_int).cast(), std::mem::size_of::<libc::c_int>() as libc::socklen_t)
+            };
+            if rc != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn connect_loopback(addr: std::net::SocketAddr) -> Result<TcpStream, BenchError> {
+    let stream = TcpStream::connect(addr).await?;
+    configure_socket(&stream)?;
+    Ok(stream)
+}
+
+// Starts a peer serving `handler` over the chosen transport and returns the
+// connected client stream. The caller aborts the returned handle once the
+// benchmark is done.
+async fn connect_peer<H, Fut>(transport: Transport, handler: H) -> Result<(TransportStream, tokio::task::JoinHandle<()>), BenchError>
+where
+    H: Fn(TransportStream) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    match transport {
+        Transport::Loopback => {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+            let server = tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    let _ = configure_socket(&socket);
+                    tokio::spawn(handler(tokio_util::either::Either::Left(socket)));
+                }
+            });
+            match connect_loopback(addr).await {
+                Ok(stream) => Ok((tokio_util::either::Either::Left(stream), server)),
+                Err(error) => {
+                    server.abort();
+                    Err(error)
+                }
+            }
+        }
+        Transport::InMemory => {
+            let (client, server_end) = tokio::io::duplex(DUPLEX_CAPACITY);
+            let server = tokio::spawn(handler(tokio_util::either::Either::Right(server_end)));
+            Ok((tokio_util::either::Either::Right(client), server))
+        }
+    }
+}
+
+async fn echo_handler(stream: TransportStream) {
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+}
+
+async fn benchmark_echo_unbuffered(runner: &BenchmarkRunner, name: &str, transport: Transport) -> BenchmarkResult {
+    let (stream, server) = match connect_peer(transport, echo_handler).await {
+        Ok(peer) => peer,
+        Err(error) => return BenchmarkResult::setup_failed(name, error),
+    };
+    let stream = Arc::new(tokio::sync::Mutex::new(stream));
+    let payload = [b'A'; SOCKET_PAYLOAD_SIZE];
+
+    let result = runner.try_run(name, || {
+        let stream = Arc::clone(&stream);
+        async move {
+            let mut stream = stream.lock().await;
+            let mut reply = [0u8; SOCKET_PAYLOAD_SIZE];
+            // 每条消息: 头和负载各一次write，各一次read
+            for _ in 0..SOCKET_MESSAGES {
+                stream.write_all(&(payload.len() as u32).to_be_bytes()).await?;
+                stream.write_all(&payload).await?;
+                let mut header = [0u8; 4];
+                stream.read_exact(&mut header).await?;
+                let len = u32::from_be_bytes(header) as usize;
+                stream.read_exact(&mut reply[..len]).await?;
+            }
+            Ok(())
+        }
+    }).await;
+
+    server.abort();
+    result
+}
+
+struct BufferedConnection {
+    reader: BufReader<tokio::io::ReadHalf<TransportStream>>,
+    writer: BufWriter<tokio::io::WriteHalf<TransportStream>>,
+}
+
+impl BufferedConnection {
+    fn new(stream: TransportStream) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self {
+            reader: BufReader::new(reader),
+            writer: BufWriter::new(writer),
+        }
+    }
+}
+
+async fn benchmark_echo_buffered(runner: &BenchmarkRunner, name: &str, transport: Transport) -> BenchmarkResult {
+    let (stream, server) = match connect_peer(transport, echo_handler).await {
+        Ok(peer) => peer,
+        Err(error) => return BenchmarkResult::setup_failed(name, error),
+    };
+    let connection = Arc::new(tokio::sync::Mutex::new(BufferedConnection::new(stream)));
+    let payload = [b'A'; SOCKET_PAYLOAD_SIZE];
+
+    let result = runner.try_run(name, || {
+        let connection = Arc::clone(&connection);
+        async move {
+            let mut connection = connection.lock().await;
+            let connection = &mut *connection;
+            let mut reply = [0u8; SOCKET_PAYLOAD_SIZE];
+            // 每条消息: 帧在用户态缓冲区中拼好，一次flush；读取由BufReader合并
+            for _ in 0..SOCKET_MESSAGES {
+                connection.writer.write_all(&(payload.len() as u32).to_be_bytes()).await?;
+                connection.writer.write_all(&payload).await?;
+                connection.writer.flush().await?;
+                let mut header = [0u8; 4];
+                connection.reader.read_exact(&mut header).await?;
+                let len = u32::from_be_bytes(header) as usize;
+                connection.reader.read_exact(&mut reply[..len]).await?;
+            }
+            Ok(())
+        }
+    }).await;
+
+    server.abort();
+    result
+}
+
+// HTTP/1.1 keep-alive请求/响应往返
+const HTTP_REQUEST: &[u8] = b"GET /api/data HTTP/1.1\r\nHost: localhost\r\n\r\n";
+const HTTP_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nHello, World!";
+
+// Reads header lines up to the blank line; returns the Content-Length (0 if
+// absent) or None on EOF.
+async fn read_http_head<R>(reader: &mut R) -> Result<Option<usize>, BenchError>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+{
+    let mut content_length = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        if line == "\r\n" {
+            return Ok(Some(content_length));
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse().map_err(|_| BenchError::new("invalid Content-Length"))?;
+        }
+    }
+}
+
+async fn http_handler(stream: TransportStream) {
+    let mut connection = BufferedConnection::new(stream);
+    while let Ok(Some(_)) = read_http_head(&mut connection.reader).await {
+        if connection.writer.write_all(HTTP_RESPONSE).await.is_err() || connection.writer.flush().await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn benchmark_http_roundtrip(runner: &BenchmarkRunner, name: &str, transport: Transport) -> BenchmarkResult {
+    let (stream, server) = match connect_peer(transport, http_handler).await {
+        Ok(peer) => peer,
+        Err(error) => return BenchmarkResult::setup_failed(name, error),
+    };
+    let connection = Arc::new(tokio::sync::Mutex::new(BufferedConnection::new(stream)));
+
+    let result = runner.try_run(name, || {
+        let connection = Arc::clone(&connection);
+        async move { http_request(&mut *connection.lock().await).await }
+    }).await;
+
+    server.abort();
+    result
+}
+
+// One keep-alive request/response exchange on an established connection
+async fn http_request(connection: &mut BufferedConnection) -> Result<(), BenchError> {
+    connection.writer.write_all(HTTP_REQUEST).await?;
+    connection.writer.flush().await?;
+    let content_length = read_http_head(&mut connection.reader)
+        .await?
+        .ok_or_else(|| BenchError::new("connection closed by server"))?;
+    let mut body = vec![0u8; content_length];
+    connection.reader.read_exact(&mut body).await?;
+    std::hint::black_box(body);
+    Ok(())
+}
+
+// Suite-level fixtures, set up once before any benchmark runs and shared by
+// every benchmark that needs them, so their setup is never timed or repeated.
+#[derive(Default)]
+pub struct SuiteFixtures {
+    http_pool: Option<WarmPool>,
+}
+
+impl SuiteFixtures {
+    pub async fn establish(http_pool_size: Option<usize>) -> Result<Self, BenchError> {
+        let http_pool = match http_pool_size {
+            Some(size) => Some(WarmPool::establish(size).await?),
+            None => None,
+        };
+        Ok(Self { http_pool })
+    }
+}
+
+// Keep-alive HTTP connections to a loopback server, each already past its
+// first request (handshake, slow start, buffer allocation) when handed out.
+// Connections are checked out round-robin.
+struct WarmPool {
+    connections: Vec<tokio::sync::Mutex<BufferedConnection>>,
+    next: std::sync::atomic::AtomicUsize,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl WarmPool {
+    async fn establish(size: usize) -> Result<Self, BenchError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let _ = configure_socket(&socket);
+                tokio::spawn(http_handler(tokio_util::either::Either::Left(socket)));
+            }
+        });
+        let mut pool = Self { connections: Vec::with_capacity(size), next: Default::default(), server };
+        for _ in 0..size {
+            let stream = connect_loopback(addr).await?;
+            let mut connection = BufferedConnection::new(tokio_util::either::Either::Left(stream));
+            http_request(&mut connection).await?;
+            pool.connections.push(tokio::sync::Mutex::new(connection));
+        }
+        Ok(pool)
+    }
+
+    async fn checkout(&self) -> tokio::sync::MutexGuard<'_, BufferedConnection> {
+        let index = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % self.connections.len();
+        self.connections[index].lock().await
+    }
+}
+
+impl Drop for WarmPool {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+fn warm_pool(runner: &BenchmarkRunner) -> Result<&WarmPool, BenchError> {
+    match &runner.fixtures.http_pool {
+        Some(pool) if !pool.connections.is_empty() => Ok(pool),
+        _ => Err(BenchError::new("warm HTTP pool fixture was not established")),
+    }
+}
+
+// One request on the next pooled connection
+async fn benchmark_pooled_http_request(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    let pool = match warm_pool(runner) {
+        Ok(pool) => pool,
+        Err(error) => return BenchmarkResult::setup_failed(name, error),
+    };
+    runner.try_run(name, || async { http_request(&mut *pool.checkout().await).await }).await
+}
+
+// One request on every pooled connection concurrently
+async fn benchmark_pooled_http_fanout(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    let pool = match warm_pool(runner) {
+        Ok(pool) => pool,
+        Err(error) => return BenchmarkResult::setup_failed(name, error),
+    };
+    runner.try_run(name, || async {
+        let requests = pool.connections.iter().map(|connection| async move {
+            http_request(&mut *connection.lock().await).await
+        });
+        futures::future::try_join_all(requests).await?;
+        Ok(())
+    }).await
+}
+
+// 编解码/分帧层 - 每个协议实现都要付出的开销，通过内存duplex流测量
+const CODEC_FRAMES: usize = 16;
+
+struct CodecPipe<C> {
+    writer: FramedWrite<tokio::io::DuplexStream, C>,
+    reader: FramedRead<tokio::io::DuplexStream, C>,
+}
+
+// Encodes all frames into one end of the duplex, flushes once, then decodes
+// them from the other end.
+async fn benchmark_codec<C, I>(runner: &BenchmarkRunner, name: &str, codec: C, frames: Vec<I>) -> BenchmarkResult
+where
+    C: Encoder<I> + Decoder + Clone,
+    <C as Encoder<I>>::Error: std::error::Error,
+    <C as Decoder>::Error: std::error::Error,
+    I: Clone,
+{
+    let (client, server) = tokio::io::duplex(DUPLEX_CAPACITY);
+    let pipe = Arc::new(tokio::sync::Mutex::new(CodecPipe {
+        writer: FramedWrite::new(client, codec.clone()),
+        reader: FramedRead::new(server, codec),
+    }));
+    let frames = Arc::new(frames);
+
+    runner.try_run(name, || {
+        let pipe = Arc::clone(&pipe);
+        let frames = Arc::clone(&frames);
+        async move {
+            let mut pipe = pipe.lock().await;
+            for frame in frames.iter() {
+                pipe.writer.feed(frame.clone()).await?;
+            }
+            pipe.writer.flush().await?;
+            for _ in 0..frames.len() {
+                let frame = pipe.reader.next().await.ok_or_else(|| BenchError::new("duplex stream closed"))??;
+                std::hint::black_box(frame);
+            }
+            Ok(())
+        }
+    }).await
+}
+
+async fn benchmark_length_delimited_codec(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    let frames = (0..CODEC_FRAMES)
+        .map(|i| bytes::Bytes::from(vec![(i % 256) as u8; 256]))
+        .collect();
+    benchmark_codec(runner, name, LengthDelimitedCodec::new(), frames).await
+}
+
+async fn benchmark_lines_codec(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    let frames = (0..CODEC_FRAMES)
+        .map(|i| Arc::<str>::from(format!("GET /api/data/{:04} HTTP/1.1 host=localhost accept=application/json", i)))
+        .collect();
+    benchmark_codec(runner, name, LinesCodec::new(), frames).await
+}
+
+// 定时器密集型负载 - 在暂停的tokio时钟下运行，只测量时间轮本身的算法开销
+const TIMER_COUNT: usize = 1000;
+
+async fn benchmark_timer_wheel_sleeps(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.try_run_with_rng(name, |rng| {
+        let delays: Vec<u64> = (0..TIMER_COUNT).map(|_| 1 + rng.gen_range(1000)).collect();
+        async move {
+            let mut join_set = JoinSet::new();
+            for delay_ms in delays {
+                spawn_task(&mut join_set, tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)));
+            }
+            while let Some(result) = join_set.join_next().await {
+                result?;
+            }
+            Ok(())
+        }
+    }).await
+}
+
+async fn benchmark_timer_cancellation(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run_with_rng(name, |rng| {
+        let delays: Vec<u64> = (0..TIMER_COUNT).map(|_| 1 + rng.gen_range(60_000)).collect();
+        async move {
+            // 注册后立即取消，典型的超时保护模式(请求在超时前完成)
+            let mut sleeps: Vec<_> = delays
+                .into_iter()
+                .map(|delay_ms| Box::pin(tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms))))
+                .collect();
+            for sleep in sleeps.iter_mut() {
+                let _ = futures::poll!(sleep.as_mut());
+            }
+            drop(sleeps);
+        }
+    }).await
+}
+
+async fn benchmark_interval_ticks(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    runner.run(name, || async {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(1));
+        for _ in 0..TIMER_COUNT {
+            interval.tick().await;
+        }
+    }).await
+}
+
+// 请求处理架构的开销：同一个处理函数分别以直接调用、经mpsc队列交给常驻工作任务、
+// 每个请求派生一个任务三种方式执行，差值即排队或派生本身的成本，与运行时选择无关
+const DISPATCH_REQUEST: &[u8] = b"GET /api/items/42 HTTP/1.1\r\nHost: localhost\r\n\r\n";
+const DISPATCH_QUEUE_DEPTH: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum Dispatch {
+    Direct,
+    Queue,
+    Spawn,
+}
+
+async fn handle_request(request: bytes::Bytes) -> Result<usize, BenchError> {
+    let text = std::str::from_utf8(&request)?;
+    let path = text.split(' ').nth(1).ok_or_else(|| BenchError::new("malformed request line"))?;
+    let id: u64 = path.rsplit('/').next().unwrap_or_default().parse()?;
+    let body = format!("{{\"id\":{},\"name\":\"item-{}\"}}", id, id);
+    let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+    Ok(response.len())
+}
+
+type DispatchJob = (bytes::Bytes, tokio::sync::oneshot::Sender<Result<usize, BenchError>>);
+
+async fn benchmark_request_dispatch(runner: &BenchmarkRunner, name: &str, dispatch: Dispatch) -> BenchmarkResult {
+    let request = bytes::Bytes::from_static(DISPATCH_REQUEST);
+    match dispatch {
+        Dispatch::Direct => runner.try_run(name, || handle_request(request.clone())).await,
+        Dispatch::Queue => {
+            let (sender, mut receiver) = tokio::sync::mpsc::channel::<DispatchJob>(DISPATCH_QUEUE_DEPTH);
+            let worker = tokio::spawn(async move {
+                while let Some((request, reply)) = receiver.recv().await {
+                    let _ = reply.send(handle_request(request).await);
+                }
+            });
+            let result = runner.try_run(name, || {
+                let (sender, request) = (sender.clone(), request.clone());
+                async move {
+                    let (reply, response) = tokio::sync::oneshot::channel();
+                    sender.send((request, reply)).await.map_err(|_| BenchError::new("worker stopped"))?;
+                    response.await?
+                }
+            }).await;
+            drop(sender);
+            let _ = worker.await;
+            result
+        }
+        Dispatch::Spawn => runner.try_run(name, || {
+            let request = request.clone();
+            async move { tokio::spawn(handle_request(request)).await? }
+        }).await,
+    }
+}
+
+// 作用域取消时的清理开销：先构建一棵全部挂起的任务树（每个任务持有缓冲区，
+// 部分叶子持有socket并阻塞在读上），再整体取消，计时直到所有资源都被释放
+const TEARDOWN_SUBTREES: usize = 10;
+const TEARDOWN_LEAVES: usize = 100;
+const TEARDOWN_BUFFER: usize = 4096;
+// Every n-th leaf waits on a socket instead of a bare pending future
+const TEARDOWN_SOCKET_EVERY: usize = 10;
+const TEARDOWN_STAGES: &[&str] = &["build", "teardown"];
+
+#[cfg(unix)]
+async fn benchmark_scope_teardown(runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::UnixStream;
+
+    let stages = StageRecorder::new(TEARDOWN_STAGES);
+    let mut result = runner.try_run(name, || async {
+        stages.begin();
+        // Held by every task; the tree is gone once only this handle is left
+        let live = Arc::new(AtomicUsize::new(0));
+        let mut scope = JoinSet::new();
+        for _ in 0..TEARDOWN_SUBTREES {
+            let mut leaves = JoinSet::new();
+            for leaf in 0..TEARDOWN_LEAVES {
+                let live = Arc::clone(&live);
+                let buffer = vec![0u8; TEARDOWN_BUFFER];
+                let socket = if leaf % TEARDOWN_SOCKET_EVERY == 0 { Some(UnixStream::pair()?) } else { None };
+                spawn_task(&mut leaves, async move {
+                    live.fetch_add(1, Ordering::Relaxed);
+                    let _buffer = buffer;
+                    match socket {
+                        // Nothing is ever written, so the read stays pending
+                        Some((mut reader, _writer)) => {
+                            let mut byte = [0u8; 1];
+                            let _ = reader.read(&mut byte).await;
+                        }
+                        None => std::future::pending::<()>().await,
+                    }
+                });
+            }
+            let live = Arc::clone(&live);
+            let buffer = vec![0u8; TEARDOWN_BUFFER];
+            spawn_task(&mut scope, async move {
+                live.fetch_add(1, Ordering::Relaxed);
+                let _buffer = buffer;
+                let _leaves = leaves;
+                std::future::pending::<()>().await
+            });
+        }
+        // Tear down a tree of tasks that have all been polled, not one of
+        // futures that never started
+        let tasks = TEARDOWN_SUBTREES * (TEARDOWN_LEAVES + 1);
+        while live.load(Ordering::Relaxed) < tasks {
+            tokio::task::yield_now().await;
+        }
+        stages.mark();
+
+        // Aborting a subtree drops its JoinSet, which aborts its leaves
+        drop(scope);
+        while Arc::strong_count(&live) > 1 {
+            tokio::task::yield_now().await;
+        }
+        stages.mark();
+
+        stages.commit();
+        Ok(tasks)
+    }).await;
+    result.stages = stages.attribution(result.iterations);
+    result
+}
+
+#[cfg(not(unix))]
+async fn benchmark_scope_teardown(_runner: &BenchmarkRunner, name: &str) -> BenchmarkResult {
+    BenchmarkResult::setup_failed(name, BenchError::new("socket pairs require a Unix platform"))
+}
+
+pub fn register_benchmarks() -> BenchmarkRegistry {
+    let mut registry = BenchmarkRegistry::default();
+
+    // Core Rust benchmarks
+    registry.add_async("Task Creation & Execution", &["core"], benchmark_task_creation_and_execution)
+        .detailed()
+        .also_known_as("Task Creation")
+        .also_known_as("Coroutine Create & Execute")
+        .also_known_as("Goroutine Creation & Execution")
+        .param("workload", "Task Creation")
+        .sla(&FLOWCORO_SLA)
+        .badge("tasks/sec");
+    registry.add_async("Task Creation [current-thread]", &["core"], benchmark_task_creation_and_execution)
+        .current_thread()
+        .variant_of("Task Creation & Execution")
+        .param("workload", "Task Creation");
+    registry.add_async("Channel Operations", &["core"], benchmark_channel_ops)
+        .param("workload", "Channel Operations")
+        .sla(&FLOWCORO_SLA)
+        .badge("channel ops/sec");
+    registry.add_async("Channel Ops [current-thread]", &["core"], benchmark_channel_ops)
+        .current_thread()
+        .variant_of("Channel Operations")
+        .param("workload", "Channel Operations");
+    registry.add_sync("Simple Computation", &["core", "cpu"], benchmark_simple_computation)
+        .also_known_as("Simple Computation (baseline)");
+
+    // 复杂任务基准测试 - 测试调度器能力
+    registry.add_sync("Complex Computation Task", &["cpu"], benchmark_complex_computation);
+
+    // Concurrency benchmarks
+    registry.add_async("Concurrent Tasks (10)", &["concurrency"], benchmark_concurrent_tasks)
+        .also_known_as("Concurrent Goroutines (10)")
+        .param("workload", "Concurrent Tasks (10)")
+        .sla(&FLOWCORO_SLA);
+    registry.add_async("Bounded Spawn (256 in flight)", &["core", "concurrency"], benchmark_bounded_spawn);
+    registry.add_async("Scope Teardown (1010 pending)", &["concurrency", "memory"], benchmark_scope_teardown)
+        .with_env(EnvHook::NoFileLimit(4096));
+    registry.add_async("Concurrent Tasks [current-thread]", &["concurrency"], benchmark_concurrent_tasks)
+        .current_thread()
+        .variant_of("Concurrent Tasks (10)")
+        .param("workload", "Concurrent Tasks (10)");
+    registry.add_async("Concurrent Tasks [1 worker]", &["concurrency"], benchmark_concurrent_tasks)
+        .worker_threads(1)
+        .variant_of("Concurrent Tasks (10)")
+        .param("workload", "Concurrent Tasks (10)");
+
+    // Memory benchmarks
+    registry.add_sync("Memory Allocation (1KB)", &["memory"], benchmark_memory_allocation);
+
+    // Network and IO simulation benchmarks
+    registry.add_async("Echo Server Throughput", &["network"], benchmark_echo_server)
+        .detailed()
+        .also_known_as("Echo Server Simulation")
+        .sla(&FLOWCORO_SLA)
+        .badge("echo round trips/sec");
+    registry.add_async("Concurrent Echo Clients", &["network", "concurrency"], benchmark_concurrent_echo_clients);
+    registry.add_async("HTTP Request Processing", &["network"], benchmark_http_processing)
+        .detailed()
+        .sla(&FLOWCORO_SLA)
+        .badge("HTTP requests/sec");
+    registry.add_async("HTTP Pipeline (staged)", &["network", "stages"], benchmark_http_pipeline);
+
+    // Real I/O, each workload over loopback TCP and over an in-memory duplex
+    for (transport, label, tag) in [(Transport::Loopback, "tcp", "loopback"), (Transport::InMemory, "mem", "in-memory")] {
+        registry.add_async(&format!("Echo Raw 16x64B [{}]", label), &["network", "io", tag], async move |runner, name| {
+            benchmark_echo_unbuffered(runner, name, transport).await
+        }).param("workload", "Echo Raw 16x64B").param("transport", label);
+        registry.add_async(&format!("Echo Buffered 16x64B [{}]", label), &["network", "io", tag], async move |runner, name| {
+            benchmark_echo_buffered(runner, name, transport).await
+        }).param("workload", "Echo Buffered 16x64B").param("transport", label);
+        registry.add_async(&format!("HTTP Roundtrip [{}]", label), &["network", "io", tag], async move |runner, name| {
+            benchmark_http_roundtrip(runner, name, transport).await
+        }).param("workload", "HTTP Roundtrip").param("transport", label);
+    }
+
+    for (dispatch, label) in [(Dispatch::Direct, "direct"), (Dispatch::Queue, "queue"), (Dispatch::Spawn, "spawn")] {
+        registry.add_async(&format!("Request Handling [{}]", label), &["concurrency", "dispatch"], async move |runner, name| {
+            benchmark_request_dispatch(runner, name, dispatch).await
+        }).param("workload", "Request Handling").param("dispatch", label).sla(&FLOWCORO_SLA);
+    }
+
+    // Client benchmarks over the suite's warm connection pool
+    registry.add_async("HTTP Pooled Request", &["network", "io", "loopback", "pooled"], benchmark_pooled_http_request);
+    registry.add_async("HTTP Pooled Fan-out", &["network", "io", "loopback", "pooled"], benchmark_pooled_http_fanout);
+
+    // Framing benchmarks
+    registry.add_async("Codec Length-Delim (16x256B)", &["codec", "io"], benchmark_length_delimited_codec);
+    registry.add_async("Codec Lines (16x~70B)", &["codec", "io"], benchmark_lines_codec);
+
+    // Data transfer benchmarks
+    registry.add_sync("Small Data Transfer (64B)", &["data"], benchmark_small_data_transfer)
+        .detailed()
+        .also_known_as("Data Transfer (64B)")
+        .param("workload", "Data Transfer")
+        .param("size", "64B");
+    registry.add_sync("Medium Data Transfer (4KB)", &["data"], benchmark_medium_data_transfer)
+        .detailed()
+        .also_known_as("Data Transfer (4KB)")
+        .param("workload", "Data Transfer")
+        .param("size", "4KB");
+    registry.add_sync("Large Data Transfer (64KB)", &["data"], benchmark_large_data_transfer)
+        .detailed()
+        .param("workload", "Data Transfer")
+        .param("size", "64KB");
+
+    // Clock and timestamp benchmarks
+    registry.add_sync("Clock Read (Instant::now)", &["clock"], benchmark_instant_now);
+    registry.add_sync("Clock Read (SystemTime::now)", &["clock"], benchmark_system_time_now);
+    registry.add_sync("Timestamp Formatting (chrono)", &["clock"], benchmark_timestamp_formatting);
+    registry.add_sync("Perf Counter Read", &["clock"], benchmark_perf_counter_read)
+        .requires(Requirement::Linux)
+        .requires(Requirement::PerfCounters);
+    registry.add_sync("io_uring_enter (empty)", &["syscall"], benchmark_io_uring_enter)
+        .requires(Requirement::Linux)
+        .requires(Requirement::IoUring);
+    registry.add_async("Concurrent Timestamping (10)", &["clock", "concurrency"], benchmark_concurrent_timestamping);
+
+    // Timer benchmarks (simulated time)
+    registry.add_async("Timer 1k Random Sleeps [sim]", &["timer"], benchmark_timer_wheel_sleeps).simulated_time();
+    registry.add_async("Timer 1k Register+Cancel [sim]", &["timer"], benchmark_timer_cancellation).simulated_time();
+    registry.add_async("Interval 1k Ticks [sim]", &["timer"], benchmark_interval_ticks).simulated_time();
+
+    // Hashing benchmarks
+    for (label, input) in [("Small Key", HASH_SMALL_KEY.to_vec()), ("4KB", hash_payload_4kb())] {
+        let input: Arc<[u8]> = input.into();
+
+        let sip_input = Arc::clone(&input);
+        registry.add_sync(&format!("Hash SipHash ({})", label), &["hashing"], move |runner, name| {
+            benchmark_hasher(runner, name, std::collections::hash_map::RandomState::new(), &sip_input)
+        }).param("hasher", "SipHash").param("input", label);
+        let fx_input = Arc::clone(&input);
+        registry.add_sync(&format!("Hash FxHash ({})", label), &["hashing"], move |runner, name| {
+            benchmark_hasher(runner, name, rustc_hash::FxBuildHasher, &fx_input)
+        }).param("hasher", "FxHash").param("input", label);
+        let ahash_input = Arc::clone(&input);
+        registry.add_sync(&format!("Hash aHash ({})", label), &["hashing"], move |runner, name| {
+            benchmark_hasher(runner, name, ahash::RandomState::new(), &ahash_input)
+        }).param("hasher", "aHash").param("input", label);
+        registry.add_sync(&format!("Hash xxHash3 ({})", label), &["hashing"], move |runner, name| {
+            benchmark_hasher(runner, name, xxhash_rust::xxh3::Xxh3Builder::new(), &input)
+        }).param("hasher", "xxHash3").param("input", label);
+    }
+
+    // Request context benchmarks
+    registry.add_sync("Thread-Local Access (x100)", &["context"], benchmark_thread_local_access);
+    registry.add_async("Task-Local Access (x100)", &["context"], benchmark_task_local_access);
+    registry.add_async("Task-Local Scope Enter", &["context"], benchmark_task_local_scope);
+    registry.add_async("Context Propagation (10 deep)", &["context"], benchmark_context_propagation).without_tracing();
+    registry.add_async("Call Chain Baseline (10 deep)", &["context"], benchmark_call_chain_baseline).without_tracing();
+
+    // Shared-state map benchmarks
+    registry.add_async("Map Mutex<HashMap> (90/10)", &["map", "concurrency"], async |runner, name| {
+        benchmark_concurrent_map(runner, name, Mutex::new(HashMap::new())).await
+    }).requires(Requirement::MinCores(2))
+        .param("map", "Mutex<HashMap>");
+    registry.add_async("Map RwLock<HashMap> (90/10)", &["map", "concurrency"], async |runner, name| {
+        benchmark_concurrent_map(runner, name, RwLock::new(HashMap::new())).await
+    }).requires(Requirement::MinCores(2))
+        .param("map", "RwLock<HashMap>");
+    registry.add_async("Map DashMap (90/10)", &["map", "concurrency"], async |runner, name| {
+        benchmark_concurrent_map(runner, name, dashmap::DashMap::new()).await
+    }).requires(Requirement::MinCores(2))
+        .param("map", "DashMap");
+    registry.add_async("Map Sharded (16, 90/10)", &["map", "concurrency"], async |runner, name| {
+        benchmark_concurrent_map(runner, name, ShardedMap::new(16)).await
+    }).requires(Requirement::MinCores(2))
+        .param("map", "Sharded (16)");
+    registry.add_async("Account Transfers (16 tasks)", &["concurrency", "invariant"], benchmark_account_transfers);
+
+    registry
+}
@@ -0,0 +1,285 @@
+// Static charts of a run (--charts), to paste into a write-up or attach to a
+// pull request: per benchmark the latency distribution (<name>.svg/.png), as
+// the log-scaled histogram printed by --histogram with the median and p99
+// marked, and one bar chart of the throughput of every timed benchmark
+// across the suite (throughput.svg/.png), on a log scale since the scenarios
+// differ by orders of magnitude. Each chart is drawn by plotters twice, once
+// on its SVG backend and once on its bitmap backend.
+
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+
+use crate::badge::si_rate;
+use crate::{format_value_plain, BenchmarkResult, BenchmarkStatus};
+
+const WIDTH: u32 = 800;
+const DISTRIBUTION_HEIGHT: u32 = 420;
+// Upper bound on the histogram's columns; buckets are halved per octave
+// until they fit
+const MAX_BUCKETS: usize = 48;
+const BAR_HEIGHT: u32 = 24;
+const LABEL_WIDTH: u32 = 280;
+const GREY: RGBColor = RGBColor(0x55, 0x55, 0x55);
+const BLUE: RGBColor = RGBColor(0x46, 0x82, 0xb4);
+const GREEN: RGBColor = RGBColor(0x2e, 0x8b, 0x57);
+const RED: RGBColor = RGBColor(0xcd, 0x5c, 0x5c);
+
+// A chart plotters can draw on any backend
+trait Chart {
+    fn height(&self) -> u32;
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>>;
+}
+
+// Writes <stem>.svg and <stem>.png
+fn save(chart: &impl Chart, stem: &Path) -> Result<(), String> {
+    let size = (WIDTH, chart.height());
+    let svg = stem.with_extension("svg");
+    let root = SVGBackend::new(&svg, size).into_drawing_area();
+    chart.draw(&root).and_then(|()| root.present()).map_err(|e| format!("{}: {}", svg.display(), e))?;
+    let png = stem.with_extension("png");
+    let root = BitMapBackend::new(&png, size).into_drawing_area();
+    chart.draw(&root).and_then(|()| root.present()).map_err(|e| format!("{}: {}", png.display(), e))
+}
+
+// Title and a grey line under it; returns the area below
+fn titled<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, title: &str, subtitle: &str) -> Result<DrawingArea<DB, Shift>, DrawingAreaErrorKind<DB::ErrorType>> {
+    root.fill(&WHITE)?;
+    let area = root.titled(title, ("sans-serif", 18))?;
+    area.titled(subtitle, ("sans-serif", 12).into_font().color(&GREY))
+}
+
+struct Distribution<'a> {
+    result: &'a BenchmarkResult,
+    buckets: Vec<crate::stats::LogBucket>,
+}
+
+impl Chart for Distribution<'_> {
+    fn height(&self) -> u32 {
+        DISTRIBUTION_HEIGHT
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let stats = &self.result.stats;
+        let unit = &stats.unit;
+        let buckets = &self.buckets;
+        let total = stats.histogram.len().max(1) as f64;
+        let share = |count: u64| count as f64 / total * 100.0;
+        let fullest = buckets.iter().map(|bucket| share(bucket.count)).fold(0.0, f64::max).max(f64::MIN_POSITIVE);
+
+        let subtitle = format!("{} samples, mean {}, median {}, p99 {}", stats.histogram.len(),
+            format_value_plain(stats.mean_ns, unit), format_value_plain(stats.median_ns, unit), format_value_plain(stats.p99_ns, unit));
+        let area = titled(root, &format!("{}: distribution", self.result.name), &subtitle)?;
+        let columns = buckets.len() as f64;
+        let mut chart = ChartBuilder::on(&area)
+            .margin(10)
+            .margin_right(30)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d(0.0..columns, 0.0..fullest * 1.05)?;
+        // Columns are evenly spaced on the log scale; label them with the
+        // bucket bounds
+        let bound = |x: &f64| {
+            let i = x.round() as usize;
+            buckets.get(i).map_or_else(|| buckets.last().map(|bucket| bucket.upper).unwrap_or(0.0), |bucket| bucket.lower)
+        };
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .light_line_style(WHITE)
+            .x_labels(8)
+            .x_label_formatter(&|x| format_value_plain(bound(x), unit))
+            .y_label_formatter(&|share| format!("{:.1}%", share))
+            .x_desc(format!("{} (log scale)", if self.result.is_timed() { "time per iteration" } else { unit.as_str() }))
+            .draw()?;
+        chart.draw_series(buckets.iter().enumerate().map(|(i, bucket)| {
+            Rectangle::new([(i as f64 + 0.05, 0.0), (i as f64 + 0.95, share(bucket.count))], BLUE.filled())
+        }))?;
+
+        // Labels at different heights, as the two are often a column apart
+        for (label, value, color, height) in [("median", stats.median_ns, GREEN, 1.0), ("p99", stats.p99_ns, RED, 0.9)] {
+            let Some(i) = buckets.iter().position(|bucket| (bucket.lower..bucket.upper).contains(&value)) else {
+                continue;
+            };
+            let bucket = &buckets[i];
+            // Where in the bucket, on the same log scale as the columns
+            let within = if bucket.lower > 0.0 { (value / bucket.lower).ln() / (bucket.upper / bucket.lower).ln() } else { value / bucket.upper };
+            let x = i as f64 + within;
+            chart.draw_series(DashedLineSeries::new([(x, 0.0), (x, fullest * 1.05)], 4, 3, color.stroke_width(2)))?;
+            chart.draw_series([Text::new(label, (x + 0.2, fullest * height), ("sans-serif", 11).into_font().color(&color))])?;
+        }
+        Ok(())
+    }
+}
+
+// None for results without samples
+fn distribution(result: &BenchmarkResult) -> Option<Distribution<'_>> {
+    if result.status != BenchmarkStatus::Completed || result.stats.histogram.is_empty() {
+        return None;
+    }
+    let mut per_octave = 8;
+    let mut buckets = result.stats.histogram.log_buckets(per_octave);
+    while buckets.len() > MAX_BUCKETS && per_octave > 1 {
+        per_octave /= 2;
+        buckets = result.stats.histogram.log_buckets(per_octave);
+    }
+    Some(Distribution { result, buckets })
+}
+
+struct Throughput<'a> {
+    // Top to bottom, iterations per second
+    rates: Vec<(&'a BenchmarkResult, f64)>,
+}
+
+impl Chart for Throughput<'_> {
+    fn height(&self) -> u32 {
+        120 + self.rates.len() as u32 * BAR_HEIGHT
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let rates = &self.rates;
+        let lowest = rates.iter().map(|&(_, rate)| rate).fold(f64::INFINITY, f64::min);
+        let highest = rates.iter().map(|&(_, rate)| rate).fold(0.0, f64::max);
+        // Whole decades, at least one, with room for the value past the bar
+        let first = 10f64.powf(lowest.log10().floor());
+        let last = 10f64.powf(highest.log10().ceil().max(first.log10() + 1.0) + 0.3);
+
+        let subtitle = format!("{} timed benchmarks, iterations per second (log scale)", rates.len());
+        let area = titled(root, "Throughput", &subtitle)?;
+        let rows = rates.len() as f64;
+        let mut chart = ChartBuilder::on(&area)
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(LABEL_WIDTH)
+            .build_cartesian_2d((first..last).log_scale(), 0.0..rows)?;
+        chart.configure_mesh()
+            .disable_y_mesh()
+            .y_labels(0)
+            .light_line_style(WHITE)
+            .x_label_formatter(&|rate| format!("{}/s", si_rate(*rate)))
+            .draw()?;
+        let name_style = ("sans-serif", 12).into_font().into_text_style(&area).pos(Pos::new(HPos::Right, VPos::Center));
+        for (i, (result, rate)) in rates.iter().enumerate() {
+            // The first result on top
+            let row = rows - 1.0 - i as f64;
+            let color = if result.error_count > 0 { RED } else { BLUE };
+            chart.draw_series([Rectangle::new([(first, row + 0.15), (*rate, row + 0.85)], color.filled())])?;
+            chart.draw_series([
+                EmptyElement::at((first, row + 0.5)) + Text::new(fit_label(&result.name), (-8, 0), name_style.clone()),
+                EmptyElement::at((*rate, row + 0.5)) + Text::new(format!("{}/s", si_rate(*rate)), (4, -6), ("sans-serif", 11)),
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+// None when no benchmark was timed
+fn throughput(results: &[BenchmarkResult]) -> Option<Throughput<'_>> {
+    let rates: Vec<(&BenchmarkResult, f64)> = results.iter()
+        .filter(|result| result.status == BenchmarkStatus::Completed && result.is_timed() && result.stats.mean_ns > 0.0)
+        .map(|result| (result, 1e9 / result.stats.mean_ns))
+        .collect();
+    (!rates.is_empty()).then_some(Throughput { rates })
+}
+
+// The distribution chart of a result as <stem>.svg and <stem>.png; false when
+// it has no samples
+pub fn write_distribution(result: &BenchmarkResult, stem: &Path) -> Result<bool, String> {
+    distribution(result).map(|chart| save(&chart, stem)).transpose().map(|saved| saved.is_some())
+}
+
+// The suite's throughput chart as <stem>.svg and <stem>.png; false when no
+// benchmark was timed
+pub fn write_throughput(results: &[BenchmarkResult], stem: &Path) -> Result<bool, String> {
+    throughput(results).map(|chart| save(&chart, stem)).transpose().map(|saved| saved.is_some())
+}
+
+// Benchmark names cut to what fits the label column at 12px
+fn fit_label(name: &str) -> String {
+    let fits = (LABEL_WIDTH / 7) as usize;
+    if name.chars().count() <= fits {
+        name.to_string()
+    } else {
+        format!("{}..", name.chars().take(fits - 2).collect::<String>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BenchmarkStats, StatsConfig};
+
+    fn result(name: &str, samples: &[f64]) -> BenchmarkResult {
+        let mut result = BenchmarkResult::new(name.to_string());
+        result.stats = BenchmarkStats::with_precision(3);
+        for &sample in samples {
+            result.stats.record(sample);
+        }
+        result.stats.calculate(&StatsConfig::default());
+        result.iterations = samples.len();
+        result
+    }
+
+    // Charts in a directory of their own, removed when dropped
+    struct Dir(std::path::PathBuf);
+
+    impl Dir {
+        fn new(test: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("charts-{}-{}", test, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        // The SVG's text, after checking the PNG is one
+        fn read(&self, stem: &str) -> String {
+            let png = std::fs::read(self.0.join(stem).with_extension("png")).unwrap();
+            assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+            std::fs::read_to_string(self.0.join(stem).with_extension("svg")).unwrap()
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn one_sample() {
+        let dir = Dir::new("one");
+        let result = result("spawn <one> & \"only\"", &[1500.0]);
+        assert!(write_distribution(&result, &dir.0.join("spawn")).unwrap());
+        let svg = dir.read("spawn");
+        assert!(svg.contains("1 samples"), "{}", svg);
+        assert!(svg.contains("spawn &lt;one&gt; &amp;"), "{}", svg);
+        assert!(write_throughput(std::slice::from_ref(&result), &dir.0.join("throughput")).unwrap());
+        assert!(dir.read("throughput").contains("667k/s"));
+    }
+
+    #[test]
+    fn zero_samples() {
+        let dir = Dir::new("zero");
+        let empty = result("empty", &[]);
+        assert!(!write_distribution(&empty, &dir.0.join("empty")).unwrap());
+        assert!(!dir.0.join("empty.svg").exists());
+        assert!(!write_throughput(std::slice::from_ref(&empty), &dir.0.join("throughput")).unwrap());
+        // Left out of the suite's chart rather than drawn as a zero-width bar
+        assert!(write_throughput(&[empty, result("one", &[1500.0])], &dir.0.join("throughput")).unwrap());
+        let svg = dir.read("throughput");
+        assert!(svg.contains("1 timed benchmarks"));
+        assert!(!svg.contains(">empty<"));
+    }
+
+    #[test]
+    fn identical_samples_and_errors() {
+        let dir = Dir::new("flat");
+        let mut result = result("flat", &[2000.0; 50]);
+        result.error_count = 3;
+        assert!(write_distribution(&result, &dir.0.join("flat")).unwrap());
+        assert!(!dir.read("flat").contains("NaN"));
+        let skipped = crate::BenchmarkResult::skipped("skipped", "no network".to_string());
+        assert!(write_throughput(&[result, skipped], &dir.0.join("throughput")).unwrap());
+        assert!(dir.read("throughput").to_uppercase().contains("#CD5C5C"));
+    }
+}
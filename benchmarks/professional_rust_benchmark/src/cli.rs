@@ -0,0 +1,864 @@
+// Command-line options: the usage text, parsing of the arguments into
+// CliOptions and the checks of combinations that make no sense, which end
+// the program with the usage text (usage_error) before anything runs.
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::logging::LogFormat;
+use crate::report::{ReportFormat, ReportTemplate};
+use crate::stats::OutlierMethod;
+use crate::{grafana, profile, stats};
+use crate::{measurement_by_name, BenchmarkDef, DisplayUnit, Labels, Measurement, NumberStyle, StatsConfig, COUNTING_ALLOCATOR, DEFAULT_SEED};
+
+const USAGE: &str = "Usage: professional_rust_benchmark [selftest | compare <old> <new> | grafana <source>] [options]
+
+Commands:
+  selftest           Instead of the suite, measure spin loops of known duration
+                     (1µs, 100µs, 10ms) and check the harness reports them
+                     within tolerance; exits non-zero on failure
+  compare <old> <new>
+                     Instead of the suite, diff two results files (any of the
+                     three harnesses): each benchmark's change of the mean with
+                     its 95% confidence interval, whether it is significant
+                     (Welch's t-test) and beyond --max-slowdown, and an overall
+                     verdict; exits non-zero when a benchmark regressed
+  grafana <source>   Instead of the suite, print a Grafana dashboard to import,
+                     one panel per benchmark category (--filter and --tag
+                     select the benchmarks) over what --pushgateway
+                     (prometheus) or --influx (influx) publish
+
+Options:
+  --seed <u64>       Seed for randomized workloads (default: 42)
+  --filter <text>    Only run benchmarks whose name contains <text> (repeatable)
+  --tag <tag>        Only run benchmarks carrying <tag> (repeatable)
+  --dry-run          List the selected benchmarks and their configuration, then exit
+  --budget <dur>     Total time budget for the suite (e.g. 90s, 10m). Measurement
+                     time is apportioned by each benchmark's noise in the
+                     previous run (see --history); benchmarks that would start
+                     after the budget is exhausted are reported as skipped
+  --history <file>   Results of a previous run used to plan --budget
+                     (default: the JSON --output)
+  --output <path>    Where the results go (default: rust_benchmark_results.json)
+  --format <list>    Formats to write, comma-separated or repeated: json (the
+                     results file the other options read), csv, md or html
+                     (tables of the headline numbers). Default: by --output's
+                     extension, else json. With several, each goes to --output
+                     with the format's extension
+  --template <file>[=<out>]
+                     Also render the results through a Handlebars template
+                     ({{#each results}}{{name}}: {{display.mean}}{{/each}})
+                     into <out>, or onto stdout; repeatable. Values are not
+                     HTML-escaped; the html, json and csv helpers escape one
+                     ({{html name}})
+  --stdout-tsv       Instead of the report, print only a header line and one
+                     tab-separated line per benchmark (the CSV's columns, raw
+                     numbers) on stdout, for awk, sort or datamash; files are
+                     still written and status still goes to stderr
+  --append-history <path>
+                     Also keep this run, with its time and the git commit the
+                     binary was built from: appended as one JSON line to a
+                     file, or as <time>-<commit>.json into a directory (an
+                     existing one, or a path ending in /)
+  --stream <file>    Also write each benchmark's result as one line of JSON as
+                     soon as it completes, so an interrupted run keeps what it
+                     measured; the file is truncated when the run starts
+  --xlsx <file>      Also write an Excel workbook: sheets Summary (the CSV's
+                     columns), Samples (each benchmark's distribution) and
+                     System Info
+  --sqlite <file.db> Also add this run to an SQLite database (tables runs,
+                     benchmarks and distribution, created on first use);
+                     needs a build with --features sqlite
+  --percentiles <list>
+                     Comma-separated percentiles to report (default:
+                     50,90,99,99.9,99.99)
+  --min-tail-samples <n>
+                     Samples that must lie beyond a percentile for it to be
+                     reported, e.g. 100k for p99.99 at 10 (default: 10);
+                     rarer percentiles are marked n/a
+  --outliers <method>
+                     Outlier detection: tukey (IQR fences, default) or mad
+                     (modified z-score)
+  --exclude-outliers Compute mean and std dev without outliers (both sets of
+                     numbers are still reported)
+  --winsorize <percent>
+                     Clamp the lowest and highest <percent> of each
+                     benchmark's samples before computing mean and std dev
+                     (the raw figures are still reported)
+  --trim <percent>   Leave the first and last <percent> of each benchmark's
+                     samples out of its statistics (ramp-up and wind-down)
+  --tdigest <compression>
+                     Also summarize each benchmark's samples in a t-digest of
+                     about <compression> centroids (e.g. 100) and store it in
+                     the results file instead of the histogram: a few KB per
+                     benchmark however long the run, mergeable with --merge
+  --min-of-batches <size>
+                     Also report the minimum of every <size> consecutive
+                     samples, a low-noise estimate for CPU-bound benchmarks
+  --anomaly-z <z>    Flag samples whose modified z-score (distance from the
+                     median in units of 1.4826 MAD) exceeds <z> as anomalies,
+                     counted per benchmark (default: 10)
+  --max-cv <percent> Warn about results whose coefficient of variation
+                     (std dev / mean) exceeds this (default: 10)
+  --resolve <percent>
+                     Difference between two runs' means the samples should
+                     resolve at 95% confidence (default: 2); benchmarks that
+                     stopped with fewer samples than that needs are listed
+  -q, --quiet        Only log warnings and errors to stderr (files written and
+                     other status messages are left out); the report on
+                     stdout is unchanged
+  -v, --verbose      Also log each benchmark as it starts and finishes; -vv
+                     adds how each one is run (runtime, clock)
+  --log-format <format>
+                     Log lines on stderr as text (default) or json, one object
+                     per line with timestamp, level, target, fields (the
+                     message among them) and spans; RUST_LOG, if set,
+                     overrides -q/-v
+  --no-color         Plain summary rows; by default, on a terminal and without
+                     $NO_COLOR, rows with errors or regressed against
+                     --baseline are red and noisy ones (--max-cv) yellow
+  --histogram        Print each detailed result's distribution as a text
+                     histogram of log-scaled buckets, marking the median and
+                     p99
+  --sparkline        Print a sparkline per benchmark of its samples over the
+                     run (batch means, or per-sample with --timestamps), to
+                     spot drift and spikes at a glance
+  --units <unit>     Unit for times in reports: auto (default; scaled per
+                     value, e.g. 1.53 µs, 2.10 ms) or a fixed ns, us, ms or s
+                     for diffing reports
+  --thousands <sep>  Group digits in the terminal report: , _ ' space, or .
+                     with a decimal comma (1.234.567,8). Files keep plain
+                     numbers
+  --precision <digits>
+                     Significant digits kept by the sample histograms, 1-5
+                     (default: 3, i.e. values within 0.1%)
+  --warmup <dur>     Untimed warmup per benchmark (default: 100ms; 0 disables)
+  --interleave <n>   Split each benchmark into <n> short blocks and run them
+                     round-robin across benchmarks, merging the samples
+  --deterministic <u64>
+                     Run every benchmark on a single-threaded runtime with a paused
+                     clock and a seeded task polling order (reproducible CI runs;
+                     loopback network benchmarks remain timing dependent)
+  --measure <names>  What each sample measures: wall (default), cpu (thread CPU
+                     time), tsc (calibrated rdtsc, x86_64 only), allocs,
+                     alloc-bytes, instructions, io-syscalls or io-bytes (the
+                     last three Linux only). A comma-separated list records
+                     the rest alongside the first, e.g. wall,allocs
+  --clock <name>     Same as --measure, restricted to clocks (wall, cpu, tsc)
+  --regression       After the regular samples, time 30 batches of growing size
+                     and report the slope of a linear fit as the per-iteration
+                     cost, free of per-batch overhead such as clock reads
+  --task-latency     Also record the latency of every task the concurrent
+                     benchmarks spawn, overall and per tokio worker (Task
+                     Latency per Worker); costs two clock reads and a locked
+                     push per task inside the timed iterations, so keep it out
+                     of runs compared against FlowCoro or Go
+  --timestamps       Store a CLOCK_MONOTONIC timestamp with every sample in the
+                     results file (timeline field), to correlate spikes with
+                     external events
+  --raw-samples <file.csv>
+                     Also keep every sample and write them (benchmark,
+                     index, CLOCK_MONOTONIC time, value) to a CSV file; the
+                     results file stays statistics-only unless --timestamps
+                     is given too
+  --reservoir <n>    Keep only a uniform random sample of at most <n> of the
+                     samples stored by --timestamps or --raw-samples, for long
+                     soak runs (the statistics still cover every sample)
+  --memory-interval <dur>
+                     Sample RSS and heap bytes in use every <dur> (e.g. 1ms)
+                     while measuring and report peak and mean occupancy
+  --outlier-log <multiple>
+                     Log every sample above <multiple> times the median with
+                     its index, time, RSS, heap and runtime queue depth
+  --progress         Show a live progress line on stderr while measuring
+  --profile <dir>    Sample call stacks with pprof (up to 999 per CPU second)
+                     while each benchmark runs and write <dir>/<name>.svg, a
+                     flamegraph, and <name>.folded, folded stacks for inferno
+                     or speedscope (needs a build with --features profiling;
+                     the sampling perturbs the numbers a little, so profile in
+                     a separate run)
+  --profile-filter <text>
+                     Only profile benchmarks whose name contains <text>
+                     (repeatable)
+  --shuffle          Run the selected benchmarks in random order (seed from the clock)
+  --shuffle-seed <u64>
+                     Run in the order given by this seed (implies --shuffle)
+  --pool-size <n>    Connections in the warm HTTP pool shared by the pooled
+                     client benchmarks (default: 8)
+  --influx <target>  Also export InfluxDB line protocol, to a file, to stdout
+                     after the report (-) or to an http(s):// write endpoint
+                     (token from $INFLUX_TOKEN)
+  --pushgateway <url>
+                     Also push mean, p99 and throughput per benchmark to a
+                     Prometheus pushgateway (e.g. http://localhost:9091), as
+                     the group job/professional_rust_benchmark/run_id/<id>/
+                     commit/<git commit>
+  --run-id <id>      Run id for --pushgateway (default: the start time in Unix
+                     seconds)
+  --baseline <file>  Compare against an earlier results file (this harness, Go or
+                     FlowCoro C++) and report each speedup and their geometric
+                     mean as the overall figure
+  --junit <file.xml> Also write the results as JUnit XML, one test case per
+                     benchmark; with --baseline, benchmarks slower than the
+                     baseline by more than --max-slowdown are failures
+  --max-slowdown <percent>
+                     Slowdown of the mean against --baseline that counts as
+                     a regression: a red summary row and a --junit failure
+                     (default: 5)
+  --hgrm <dir>       Also write each benchmark's percentile spectrum to
+                     <dir>/<name>.hgrm in HdrHistogram's text format (times
+                     in microseconds), for hdr-plot, PerfPlotter and the like
+  --criterion <dir>  Also write each timed benchmark in criterion's layout,
+                     <dir>/<name>/new/estimates.json and so on (e.g.
+                     target/criterion), for critcmp and cargo-criterion
+  --criterion-baseline <name>
+                     Baseline the --criterion results are also saved as,
+                     like cargo bench -- --save-baseline (default: base)
+  --badges <dir>     Also write shields.io endpoint badges (e.g. tasks/sec |
+                     1.23M) for the headline benchmarks, one <name>.json each
+  --charts <dir>     Also draw charts, each as SVG and PNG: each benchmark's
+                     latency distribution (<name>.svg, <name>.png) and the
+                     throughput of the suite (throughput.svg, throughput.png)
+  --gbench-json <file>
+                     Also write the timed benchmarks in Google Benchmark's JSON
+                     format (benchmarks[].real_time, cpu_time, iterations), as
+                     FlowCoro's C++ benchmarks do; cpu_time is only measured
+                     with --measure including cpu
+  --compare <file>   Instead of running, print the means of several results
+                     files (this harness, Go or FlowCoro C++) side by side,
+                     with each one's change against the first (repeatable;
+                     --filter and --tag select the rows)
+  --cross-language <file>
+                     Instead of running, print one report over the results
+                     files of this harness, Go and FlowCoro C++: scenarios
+                     matched by name and alias, each mean with its ratio to the
+                     fastest, and the winner (repeatable; --filter and --tag
+                     select the rows)
+  --merge <file>     Instead of running, combine the histograms of results files
+                     from several workers or processes (repeatable)
+  --subtract <file>  With --merge, remove an earlier cumulative snapshot from the
+                     combined histograms (repeatable)
+  --force            Combine results files even if their machine fingerprints
+                     (CPU, cores, OS, rustc, allocator, build profile) differ
+  --pivot <rows>,<columns>
+                     Also print the mean as a table with one parameter's values
+                     as rows and another's as columns (e.g. workload,transport
+                     or hasher,input; runtime is always a parameter), one
+                     table per combination of the remaining parameters
+  --pivot-file <file>
+                     Also write the --pivot tables to <file>, as CSV or as an
+                     HTML page depending on its extension (repeatable)
+  --label <key=value>
+                     Annotate the results file with what this run measured,
+                     e.g. branch=foo or config=jemalloc (repeatable); shown in
+                     reports, baseline comparisons and as InfluxDB tags";
+
+const DEFAULT_OUTPUT: &str = "rust_benchmark_results.json";
+const DEFAULT_MAX_SLOWDOWN: f64 = 0.05;
+
+pub struct CliOptions {
+    pub seed: u64,
+    pub display_unit: DisplayUnit,
+    pub number_style: NumberStyle,
+    pub color: bool,
+    pub histogram: bool,
+    pub sparkline: bool,
+    pub stdout_tsv: bool,
+    // -1 with -q, 1 and 2 with -v and -vv
+    pub verbosity: i8,
+    pub log_format: LogFormat,
+    pub filters: Vec<String>,
+    pub tags: Vec<String>,
+    pub dry_run: bool,
+    pub budget: Option<std::time::Duration>,
+    pub history: String,
+    pub output: String,
+    // Empty until parse() settles on the formats
+    pub formats: Vec<ReportFormat>,
+    pub templates: Vec<ReportTemplate>,
+    pub append_history: Option<String>,
+    pub stream: Option<String>,
+    pub sqlite: Option<String>,
+    pub xlsx: Option<String>,
+    pub warmup: Option<std::time::Duration>,
+    pub stats_config: StatsConfig,
+    pub interleave_rounds: Option<usize>,
+    pub shuffle_seed: Option<u64>,
+    pub progress: bool,
+    pub regression: bool,
+    pub task_latency: bool,
+    pub memory_interval: Option<std::time::Duration>,
+    pub timestamps: bool,
+    pub raw_samples: Option<String>,
+    pub reservoir: Option<usize>,
+    pub outlier_multiple: Option<f64>,
+    pub profile_dir: Option<String>,
+    pub profile_filters: Vec<String>,
+    pub selftest: bool,
+    pub deterministic_seed: Option<u64>,
+    // The first one is the primary measurement, the rest are extras
+    pub measurements: Vec<Arc<dyn Measurement>>,
+    pub pool_size: usize,
+    pub influx: Option<String>,
+    pub pushgateway: Option<String>,
+    pub run_id: Option<String>,
+    pub hgrm_dir: Option<String>,
+    pub criterion_dir: Option<String>,
+    pub gbench_json: Option<String>,
+    pub badges_dir: Option<String>,
+    pub charts_dir: Option<String>,
+    pub criterion_baseline: String,
+    pub merge_files: Vec<String>,
+    pub compare_files: Vec<String>,
+    // The two files of the compare command, old then new
+    pub diff_files: Option<(String, String)>,
+    pub grafana: Option<grafana::Datasource>,
+    pub cross_language_files: Vec<String>,
+    pub subtract_files: Vec<String>,
+    pub force: bool,
+    pub baseline: Option<String>,
+    pub junit: Option<String>,
+    pub max_slowdown: f64,
+    pub labels: Labels,
+    pub pivot: Option<(String, String)>,
+    pub pivot_files: Vec<String>,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        CliOptions {
+            seed: DEFAULT_SEED,
+            display_unit: DisplayUnit::Auto,
+            number_style: NumberStyle::default(),
+            color: true,
+            histogram: false,
+            sparkline: false,
+            stdout_tsv: false,
+            verbosity: 0,
+            log_format: LogFormat::Text,
+            filters: Vec::new(),
+            tags: Vec::new(),
+            dry_run: false,
+            budget: None,
+            history: DEFAULT_OUTPUT.to_string(),
+            output: DEFAULT_OUTPUT.to_string(),
+            formats: Vec::new(),
+            templates: Vec::new(),
+            append_history: None,
+            stream: None,
+            sqlite: None,
+            xlsx: None,
+            warmup: None,
+            stats_config: StatsConfig::default(),
+            interleave_rounds: None,
+            shuffle_seed: None,
+            progress: false,
+            regression: false,
+            task_latency: false,
+            memory_interval: None,
+            timestamps: false,
+            raw_samples: None,
+            reservoir: None,
+            outlier_multiple: None,
+            profile_dir: None,
+            profile_filters: Vec::new(),
+            selftest: false,
+            deterministic_seed: None,
+            measurements: Vec::new(),
+            pool_size: 8,
+            influx: None,
+            pushgateway: None,
+            run_id: None,
+            hgrm_dir: None,
+            criterion_dir: None,
+            gbench_json: None,
+            badges_dir: None,
+            charts_dir: None,
+            criterion_baseline: "base".to_string(),
+            merge_files: Vec::new(),
+            compare_files: Vec::new(),
+            diff_files: None,
+            grafana: None,
+            cross_language_files: Vec::new(),
+            subtract_files: Vec::new(),
+            force: false,
+            baseline: None,
+            junit: None,
+            max_slowdown: DEFAULT_MAX_SLOWDOWN,
+            labels: Labels::new(),
+            pivot: None,
+            pivot_files: Vec::new(),
+        }
+    }
+}
+
+impl CliOptions {
+    pub fn parse() -> Self {
+        let mut options = CliOptions::default();
+        let mut args = std::env::args().skip(1);
+        let mut history_given = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    options.seed = match args.next().and_then(|v| v.parse().ok()) {
+                        Some(seed) => seed,
+                        None => usage_error("--seed requires an unsigned integer"),
+                    };
+                }
+                "--filter" => match args.next() {
+                    Some(filter) => options.filters.push(filter.to_lowercase()),
+                    None => usage_error("--filter requires a value"),
+                },
+                "--tag" => match args.next() {
+                    Some(tag) => options.tags.push(tag),
+                    None => usage_error("--tag requires a value"),
+                },
+                "--dry-run" => options.dry_run = true,
+                "--budget" => {
+                    options.budget = match args.next().as_deref().and_then(parse_duration) {
+                        Some(budget) => Some(budget),
+                        None => usage_error("--budget requires a duration such as 500ms, 90s or 10m"),
+                    };
+                }
+                "--history" => match args.next() {
+                    Some(path) => {
+                        options.history = path;
+                        history_given = true;
+                    }
+                    None => usage_error("--history requires a results file"),
+                },
+                "--output" => match args.next() {
+                    Some(path) if !path.starts_with("--") => options.output = path,
+                    _ => usage_error("--output requires a file path"),
+                },
+                "--format" => {
+                    for name in args.next().unwrap_or_default().split(',') {
+                        match ReportFormat::by_name(name.trim()) {
+                            Some(format) if !options.formats.contains(&format) => options.formats.push(format),
+                            Some(_) => {}
+                            None => usage_error("--format requires json, csv, md or html (comma-separated or repeated)"),
+                        }
+                    }
+                }
+                "--template" => match args.next() {
+                    Some(spec) if !spec.starts_with("--") => match ReportTemplate::load(&spec) {
+                        Ok(template) => options.templates.push(template),
+                        Err(message) => {
+                            eprintln!("Error: {}", message);
+                            std::process::exit(2);
+                        }
+                    },
+                    _ => usage_error("--template requires a template file"),
+                },
+                "--append-history" => match args.next() {
+                    Some(path) if !path.starts_with("--") => options.append_history = Some(path),
+                    _ => usage_error("--append-history requires a file or directory"),
+                },
+                "--stream" => match args.next() {
+                    Some(path) if !path.starts_with("--") => options.stream = Some(path),
+                    _ => usage_error("--stream requires a file"),
+                },
+                "--xlsx" => match args.next() {
+                    Some(path) if !path.starts_with("--") => options.xlsx = Some(path),
+                    _ => usage_error("--xlsx requires a file"),
+                },
+                "--sqlite" => match args.next() {
+                    Some(path) if !path.starts_with("--") => options.sqlite = Some(path),
+                    _ => usage_error("--sqlite requires a database file"),
+                },
+                "--warmup" => {
+                    options.warmup = match args.next().as_deref().and_then(parse_duration) {
+                        Some(warmup) => Some(warmup),
+                        None => usage_error("--warmup requires a duration such as 500ms or 2s"),
+                    };
+                }
+                "--percentiles" => {
+                    let list = args.next().unwrap_or_default();
+                    options.stats_config.percentiles = list.split(',').map(|p| match p.trim().parse::<f64>() {
+                        Ok(percentile) if percentile > 0.0 && percentile <= 100.0 => percentile,
+                        _ => usage_error("--percentiles requires a comma-separated list of numbers in (0, 100]"),
+                    }).collect();
+                }
+                "--min-tail-samples" => {
+                    options.stats_config.min_tail_samples = match args.next().and_then(|v| v.parse::<u64>().ok()) {
+                        Some(samples) if samples > 0 => samples,
+                        _ => usage_error("--min-tail-samples requires a positive number of samples"),
+                    };
+                }
+                "--outliers" => {
+                    options.stats_config.outlier_method = match args.next().as_deref().and_then(OutlierMethod::by_name) {
+                        Some(method) => method,
+                        None => usage_error("--outliers requires tukey or mad"),
+                    };
+                }
+                "--exclude-outliers" => options.stats_config.exclude_outliers = true,
+                "--winsorize" => {
+                    options.stats_config.winsorize = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(percent) if percent > 0.0 && percent < 50.0 => Some(percent / 100.0),
+                        _ => usage_error("--winsorize requires a percentage above 0 and below 50"),
+                    };
+                }
+                "--trim" => {
+                    options.stats_config.trim = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(percent) if (0.0..50.0).contains(&percent) => percent / 100.0,
+                        _ => usage_error("--trim requires a percentage from 0 to below 50"),
+                    };
+                }
+                "--tdigest" => {
+                    options.stats_config.digest_compression = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(compression) if compression >= 10.0 => Some(compression),
+                        _ => usage_error("--tdigest requires a compression of at least 10, e.g. 100"),
+                    };
+                }
+                "--min-of-batches" => {
+                    options.stats_config.batch_size = match args.next().and_then(|v| v.parse::<usize>().ok()) {
+                        Some(size) if size >= 2 => Some(size),
+                        _ => usage_error("--min-of-batches requires a batch size of at least 2, e.g. 10"),
+                    };
+                }
+                "--anomaly-z" => {
+                    options.stats_config.anomaly_z = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(z) if z > 0.0 => z,
+                        _ => usage_error("--anomaly-z requires a positive modified z-score, e.g. 10"),
+                    };
+                }
+                "--max-cv" => {
+                    options.stats_config.max_cv = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(percent) if percent > 0.0 => percent / 100.0,
+                        _ => usage_error("--max-cv requires a positive percentage"),
+                    };
+                }
+                "--resolve" => {
+                    options.stats_config.resolve = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(percent) if percent > 0.0 => percent / 100.0,
+                        _ => usage_error("--resolve requires a positive percentage"),
+                    };
+                }
+                "--no-color" => options.color = false,
+                "--histogram" => options.histogram = true,
+                "--sparkline" => options.sparkline = true,
+                "--stdout-tsv" => options.stdout_tsv = true,
+                "-q" | "--quiet" if options.verbosity <= 0 => options.verbosity = -1,
+                "-v" | "--verbose" if options.verbosity >= 0 => options.verbosity = options.verbosity.saturating_add(1),
+                "-vv" if options.verbosity >= 0 => options.verbosity = options.verbosity.saturating_add(2),
+                "-q" | "--quiet" | "-v" | "--verbose" | "-vv" => usage_error("-q and -v cannot be combined"),
+                "--log-format" => {
+                    options.log_format = match args.next().as_deref().and_then(LogFormat::by_name) {
+                        Some(format) => format,
+                        None => usage_error("--log-format requires text or json"),
+                    };
+                }
+                "--units" => {
+                    options.display_unit = match args.next().and_then(|v| DisplayUnit::parse(&v)) {
+                        Some(unit) => unit,
+                        None => usage_error("--units requires auto, ns, us, ms or s"),
+                    };
+                }
+                "--thousands" => {
+                    options.number_style = match args.next().and_then(|v| NumberStyle::parse(&v)) {
+                        Some(style) => style,
+                        None => usage_error("--thousands requires one of , _ ' . space none"),
+                    };
+                }
+                "--precision" => {
+                    options.stats_config.precision = match args.next().and_then(|v| v.parse().ok()) {
+                        Some(digits) if (1..=stats::MAX_SIGNIFICANT_DIGITS).contains(&digits) => digits,
+                        _ => usage_error("--precision requires 1 to 5 significant digits"),
+                    };
+                }
+                "--interleave" => {
+                    options.interleave_rounds = match args.next().and_then(|v| v.parse().ok()) {
+                        Some(rounds) if rounds > 0 => Some(rounds),
+                        _ => usage_error("--interleave requires a positive number of rounds"),
+                    };
+                }
+                "--progress" => options.progress = true,
+                "--profile" => match args.next() {
+                    Some(dir) if !dir.starts_with("--") && profile::SUPPORTED => options.profile_dir = Some(dir),
+                    Some(_) if !profile::SUPPORTED => usage_error("--profile needs a Unix build with --features profiling"),
+                    _ => usage_error("--profile requires a directory"),
+                },
+                "--profile-filter" => match args.next() {
+                    Some(filter) => options.profile_filters.push(filter.to_lowercase()),
+                    None => usage_error("--profile-filter requires a value"),
+                },
+                "--regression" => options.regression = true,
+                "--task-latency" => options.task_latency = true,
+                "--timestamps" => options.timestamps = true,
+                "--reservoir" => {
+                    options.reservoir = match args.next().and_then(|v| v.parse::<usize>().ok()) {
+                        Some(capacity) if capacity > 0 => Some(capacity),
+                        _ => usage_error("--reservoir requires a positive number of samples"),
+                    };
+                }
+                "--raw-samples" => {
+                    options.raw_samples = match args.next() {
+                        Some(path) if !path.starts_with("--") => Some(path),
+                        _ => usage_error("--raw-samples requires a file name"),
+                    };
+                }
+                "--outlier-log" => {
+                    options.outlier_multiple = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(multiple) if multiple > 1.0 => Some(multiple),
+                        _ => usage_error("--outlier-log requires a multiple of the median above 1, e.g. 10"),
+                    };
+                }
+                "--memory-interval" => {
+                    options.memory_interval = match args.next().as_deref().and_then(parse_duration) {
+                        Some(_) if !COUNTING_ALLOCATOR => usage_error("--memory-interval reads the heap through the counting allocator; build with the counting-allocator feature"),
+                        Some(interval) if !interval.is_zero() => Some(interval),
+                        _ => usage_error("--memory-interval requires a duration such as 1ms"),
+                    };
+                }
+                "--measure" => {
+                    let names = args.next().unwrap_or_default();
+                    options.measurements = names.split(',').map(|name| match measurement_by_name(name.trim()) {
+                        Ok(measurement) => measurement,
+                        Err(message) => usage_error(&message),
+                    }).collect();
+                }
+                "--clock" => {
+                    let name = args.next().unwrap_or_default();
+                    options.measurements = match measurement_by_name(&name) {
+                        Ok(measurement) if measurement.unit() != "ns" => {
+                            usage_error(&format!("'{}' is not a clock, use --measure", name))
+                        }
+                        Ok(measurement) => vec![measurement],
+                        Err(message) => usage_error(&message),
+                    };
+                }
+                "--deterministic" => {
+                    options.deterministic_seed = match args.next().and_then(|v| v.parse().ok()) {
+                        Some(seed) => Some(seed),
+                        None => usage_error("--deterministic requires an unsigned integer seed"),
+                    };
+                }
+                "--shuffle" => {
+                    if options.shuffle_seed.is_none() {
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                        options.shuffle_seed = Some(now.as_nanos() as u64);
+                    }
+                }
+                "--shuffle-seed" => {
+                    options.shuffle_seed = match args.next().and_then(|v| v.parse().ok()) {
+                        Some(seed) => Some(seed),
+                        None => usage_error("--shuffle-seed requires an unsigned integer"),
+                    };
+                }
+                "--pool-size" => {
+                    options.pool_size = match args.next().and_then(|v| v.parse().ok()) {
+                        Some(size) if size > 0 => size,
+                        _ => usage_error("--pool-size requires a positive number of connections"),
+                    };
+                }
+                "--influx" => match args.next() {
+                    Some(target) => options.influx = Some(target),
+                    None => usage_error("--influx requires a file path, - or an http(s):// URL"),
+                },
+                "--pushgateway" => match args.next() {
+                    Some(url) if url.starts_with("http://") || url.starts_with("https://") => options.pushgateway = Some(url),
+                    _ => usage_error("--pushgateway requires an http(s):// URL"),
+                },
+                "--run-id" => match args.next() {
+                    Some(id) if !id.is_empty() && !id.contains('/') && !id.starts_with("--") => options.run_id = Some(id),
+                    _ => usage_error("--run-id requires an identifier without '/'"),
+                },
+                "--hgrm" => match args.next() {
+                    Some(dir) => options.hgrm_dir = Some(dir),
+                    None => usage_error("--hgrm requires a directory"),
+                },
+                "--criterion" => match args.next() {
+                    Some(dir) => options.criterion_dir = Some(dir),
+                    None => usage_error("--criterion requires a directory, e.g. target/criterion"),
+                },
+                "--badges" => match args.next() {
+                    Some(dir) if !dir.starts_with("--") => options.badges_dir = Some(dir),
+                    _ => usage_error("--badges requires a directory"),
+                },
+                "--charts" => match args.next() {
+                    Some(dir) if !dir.starts_with("--") => options.charts_dir = Some(dir),
+                    _ => usage_error("--charts requires a directory"),
+                },
+                "--gbench-json" => match args.next() {
+                    Some(path) => options.gbench_json = Some(path),
+                    None => usage_error("--gbench-json requires a file path"),
+                },
+                "--criterion-baseline" => match args.next() {
+                    Some(name) if !name.is_empty() && name != "new" && !name.contains(['/', '\\']) => options.criterion_baseline = name,
+                    _ => usage_error("--criterion-baseline requires a baseline name other than 'new'"),
+                },
+                "--compare" => match args.next() {
+                    Some(path) => options.compare_files.push(path),
+                    None => usage_error("--compare requires a results file"),
+                },
+                "--cross-language" => match args.next() {
+                    Some(path) => options.cross_language_files.push(path),
+                    None => usage_error("--cross-language requires a results file"),
+                },
+                "--merge" => match args.next() {
+                    Some(path) => options.merge_files.push(path),
+                    None => usage_error("--merge requires a results file"),
+                },
+                "--subtract" => match args.next() {
+                    Some(path) => options.subtract_files.push(path),
+                    None => usage_error("--subtract requires a results file"),
+                },
+                "--force" => options.force = true,
+                "--pivot" => match args.next().as_deref().and_then(|dims| dims.split_once(',')) {
+                    Some((rows, columns)) if !rows.trim().is_empty() && !columns.trim().is_empty() => {
+                        options.pivot = Some((rows.trim().to_string(), columns.trim().to_string()));
+                    }
+                    _ => usage_error("--pivot requires <rows>,<columns>, e.g. workload,transport"),
+                },
+                "--pivot-file" => match args.next() {
+                    Some(path) if path.ends_with(".csv") || path.ends_with(".html") => options.pivot_files.push(path),
+                    _ => usage_error("--pivot-file requires a .csv or .html file"),
+                },
+                "--label" => match args.next().as_deref().and_then(|label| label.split_once('=')) {
+                    Some((key, value)) if !key.trim().is_empty() => {
+                        options.labels.insert(key.trim().to_string(), value.trim().to_string());
+                    }
+                    _ => usage_error("--label requires key=value"),
+                },
+                "--baseline" => match args.next() {
+                    Some(path) => options.baseline = Some(path),
+                    None => usage_error("--baseline requires a results file"),
+                },
+                "--junit" => match args.next() {
+                    Some(path) => options.junit = Some(path),
+                    None => usage_error("--junit requires a file path"),
+                },
+                "--max-slowdown" => {
+                    options.max_slowdown = match args.next().and_then(|v| v.parse::<f64>().ok()) {
+                        Some(percent) if percent >= 0.0 => percent / 100.0,
+                        _ => usage_error("--max-slowdown requires a percentage, e.g. 5"),
+                    };
+                }
+                "selftest" => options.selftest = true,
+                "grafana" => match args.next().as_deref().and_then(grafana::Datasource::by_name) {
+                    Some(source) => options.grafana = Some(source),
+                    None => usage_error("grafana requires a data source: prometheus or influx"),
+                },
+                "compare" => match (args.next(), args.next()) {
+                    (Some(old), Some(new)) if !old.starts_with("--") && !new.starts_with("--") => options.diff_files = Some((old, new)),
+                    _ => usage_error("compare requires two results files, old then new"),
+                },
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                other => usage_error(&format!("unknown argument: {}", other)),
+            }
+        }
+
+        if options.compare_files.len() == 1 {
+            usage_error("--compare needs at least two results files");
+        }
+        if options.cross_language_files.len() == 1 {
+            usage_error("--cross-language needs at least two results files");
+        }
+        let modes = [&options.compare_files, &options.cross_language_files, &options.merge_files];
+        if modes.iter().filter(|files| !files.is_empty()).count() + options.diff_files.is_some() as usize > 1 {
+            usage_error("compare, --compare, --cross-language and --merge are separate modes; use one");
+        }
+        if !options.subtract_files.is_empty() && options.merge_files.is_empty() {
+            usage_error("--subtract only applies together with --merge");
+        }
+        if options.reservoir.is_some() && !options.timestamps && options.raw_samples.is_none() {
+            usage_error("--reservoir only applies together with --timestamps or --raw-samples");
+        }
+        if options.stats_config.winsorize.is_some() && options.stats_config.exclude_outliers {
+            usage_error("--winsorize and --exclude-outliers are alternatives; use one");
+        }
+        if !options.pivot_files.is_empty() && options.pivot.is_none() {
+            usage_error("--pivot-file only applies together with --pivot");
+        }
+        if options.sqlite.is_some() && !cfg!(feature = "sqlite") {
+            usage_error("--sqlite needs the sqlite feature; build with --features sqlite");
+        }
+        if options.stdout_tsv {
+            if options.influx.as_deref() == Some("-") {
+                usage_error("--stdout-tsv leaves stdout to the TSV; send --influx to a file instead");
+            }
+            if options.templates.iter().any(|template| template.output.is_none()) {
+                usage_error("--stdout-tsv leaves stdout to the TSV; give each --template an output file (<file>=<out>)");
+            }
+            if options.pivot.is_some() {
+                usage_error("--stdout-tsv prints no report, so --pivot has nowhere to go");
+            }
+        }
+        if options.formats.is_empty() {
+            let extension = std::path::Path::new(&options.output).extension().and_then(|e| e.to_str()).unwrap_or_default();
+            options.formats.push(ReportFormat::by_name(extension).unwrap_or(ReportFormat::Json));
+        }
+        // The next run plans its budget from the results this one writes
+        if !history_given {
+            if let Some((_, path)) = options.output_paths().into_iter().find(|(format, _)| *format == ReportFormat::Json) {
+                options.history = path;
+            }
+        }
+        options
+    }
+
+    // Where each --format goes: --output itself for a single format, else
+    // --output with each format's extension
+    pub fn output_paths(&self) -> Vec<(ReportFormat, String)> {
+        if let [format] = self.formats[..] {
+            return vec![(format, self.output.clone())];
+        }
+        self.formats
+            .iter()
+            .map(|&format| (format, std::path::Path::new(&self.output).with_extension(format.extension()).display().to_string()))
+            .collect()
+    }
+
+    // A benchmark is selected when it matches any --filter and any --tag
+    // (an empty list matches everything).
+    pub fn selects(&self, def: &BenchmarkDef) -> bool {
+        let name = def.name.to_lowercase();
+        let name_ok = self.filters.is_empty() || self.filters.iter().any(|f| name.contains(f.as_str()));
+        let tag_ok = self.tags.is_empty() || self.tags.iter().any(|t| def.has_tag(t));
+        name_ok && tag_ok
+    }
+}
+
+// Parses "500ms", "90s", "10m", "1h"; a bare number is taken as seconds.
+fn parse_duration(text: &str) -> Option<std::time::Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let seconds = match unit {
+        "us" => value / 1_000_000.0,
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return None,
+    };
+    std::time::Duration::try_from_secs_f64(seconds).ok()
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn durations_with_and_without_units() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration(" 90 "), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1.5m"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("250us"), Some(Duration::from_micros(250)));
+        assert_eq!(parse_duration("10d"), None);
+        assert_eq!(parse_duration("ms"), None);
+    }
+}
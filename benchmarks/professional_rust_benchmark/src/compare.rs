@@ -0,0 +1,454 @@
+// Results set against other results files: this run against --baseline,
+// several files side by side (--compare), two files tested for significant
+// changes (the compare command) and the Rust, Go and FlowCoro C++ harnesses
+// against each other (--cross-language). Every reader takes the means in ns
+// from the raw JSON, as the other harnesses' files are not BenchmarkSuites,
+// and resolves names through the registry so renamed benchmarks line up.
+
+use crate::cli::CliOptions;
+use crate::{format_labels, format_value, paint, stats, BenchmarkRegistry, BenchmarkResult, BenchmarkStatus, Highlight, Labels};
+
+// Mean per benchmark name from a results file of this harness, the Go
+// harness (same layout) or the FlowCoro C++ one (means at the top level of
+// each result), plus the file's labels if it has any. Results in another
+// unit than ns are left out.
+async fn load_baseline_means(path: &str) -> Result<(Vec<(String, f64)>, Labels), String> {
+    let suite = load_results_value(path).await?;
+    let labels = serde_json::from_value(suite["labels"].clone()).unwrap_or_default();
+    Ok((means_in(&suite, path)?, labels))
+}
+
+async fn load_results_value(path: &str) -> Result<serde_json::Value, String> {
+    let json_data = tokio::fs::read_to_string(path).await.map_err(|e| format!("cannot read {}: {}", path, e))?;
+    serde_json::from_str(&json_data).map_err(|e| format!("cannot parse {}: {}", path, e))
+}
+
+// Means in ns of a results file of this harness, the Go one (stats nested
+// like ours) or FlowCoro's C++ one (stats on the result itself)
+fn means_in(suite: &serde_json::Value, path: &str) -> Result<Vec<(String, f64)>, String> {
+    let results = suite["results"].as_array().ok_or_else(|| format!("{} has no results array", path))?;
+    Ok(results
+        .iter()
+        .filter_map(|result| {
+            let name = result["name"].as_str()?;
+            let stats = if result["stats"].is_object() { &result["stats"] } else { result };
+            let unit = stats["unit"].as_str().unwrap_or("ns");
+            let mean = stats["mean_ns"].as_f64()?;
+            (unit == "ns" && mean > 0.0).then(|| (name.to_string(), mean))
+        })
+        .collect())
+}
+
+// Which harness wrote a results file, told apart by the version each records
+fn harness_of(suite: &serde_json::Value) -> &'static str {
+    if suite["benchmark_info"]["flowcoro_version"].is_string() {
+        "FlowCoro C++"
+    } else if suite["system_info"]["go_version"].is_string() {
+        "Go"
+    } else if suite["system_info"]["rust_version"].is_string() {
+        "Rust"
+    } else {
+        "unknown"
+    }
+}
+
+// Speedup of each benchmark over the baseline run and their geometric mean,
+// the single "overall" figure for cross-commit or cross-language comparisons
+fn print_baseline_summary(results: &[BenchmarkResult], labels: &Labels, baseline_path: &str, baseline: &[(String, f64)], baseline_labels: &Labels) {
+    let pairs: Vec<(&BenchmarkResult, f64)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed && r.is_timed() && r.stats.mean_ns > 0.0)
+        .filter_map(|r| baseline.iter().find(|(name, _)| *name == r.name).map(|(_, mean)| (r, *mean)))
+        .collect();
+
+    println!("\n=== Overall vs Baseline ({}) ===", baseline_path);
+    if !labels.is_empty() || !baseline_labels.is_empty() {
+        println!("Baseline labels: {}", format_labels(baseline_labels));
+        println!("Current labels:  {}", format_labels(labels));
+    }
+    if pairs.is_empty() {
+        println!("No benchmark of this run appears in the baseline.");
+        return;
+    }
+    println!("{:<30} {:>15} {:>15} {:>10}", "Benchmark Name", "Baseline", "Current", "Speedup");
+    println!("------------------------------------------------------------------------");
+    let mut log_sum = 0.0;
+    for (result, baseline_mean) in &pairs {
+        let speedup = baseline_mean / result.stats.mean_ns;
+        log_sum += speedup.ln();
+        println!("{:<30} {:>15} {:>15} {:>9.2}x",
+            result.name, format_value(*baseline_mean, "ns"), format_value(result.stats.mean_ns, "ns"), speedup);
+    }
+    let geomean = (log_sum / pairs.len() as f64).exp();
+    let direction = if geomean >= 1.0 { "faster" } else { "slower" };
+    let factor = if geomean >= 1.0 { geomean } else { 1.0 / geomean };
+    println!("------------------------------------------------------------------------");
+    println!("Overall: {:.2}x {} than baseline (geometric mean of {} benchmarks)", factor, direction, pairs.len());
+    let unmatched = results.iter().filter(|r| r.status == BenchmarkStatus::Completed).count() - pairs.len();
+    if unmatched > 0 {
+        println!("{} completed benchmarks have no baseline counterpart and are not included", unmatched);
+    }
+}
+
+// Names from another results file that no registered benchmark answers to,
+// most likely benchmarks renamed since that file was written
+pub fn warn_unknown_names<'a>(registry: &BenchmarkRegistry, source: &str, names: impl IntoIterator<Item = &'a str>) {
+    let mut unknown: Vec<&str> = names.into_iter().filter(|name| registry.resolve(name).is_none()).collect();
+    unknown.sort_unstable();
+    unknown.dedup();
+    if !unknown.is_empty() {
+        tracing::warn!(path = %source, benchmarks = %unknown.join(", "),
+            "results for benchmarks that are not registered (if one was renamed, register its old name with .also_known_as)");
+    }
+}
+
+pub async fn print_baseline(results: &[BenchmarkResult], labels: &Labels, registry: &BenchmarkRegistry, options: &CliOptions) {
+    let Some(path) = &options.baseline else {
+        return;
+    };
+    match load_baseline_means(path).await {
+        Ok((baseline, baseline_labels)) => {
+            warn_unknown_names(registry, path, baseline.iter().map(|(name, _)| name.as_str()));
+            let baseline = resolve_baseline_names(registry, baseline);
+            print_baseline_summary(results, labels, path, &baseline, &baseline_labels);
+        }
+        Err(e) => tracing::error!(error = %e, "cannot load baseline"),
+    }
+}
+
+// Baseline entries under the current names of the benchmarks they belong
+// to, dropping ones no registered benchmark answers to
+fn resolve_baseline_names(registry: &BenchmarkRegistry, baseline: Vec<(String, f64)>) -> Vec<(String, f64)> {
+    baseline
+        .into_iter()
+        .filter_map(|(name, mean)| registry.resolve(&name).map(|def| (def.name.clone(), mean)))
+        .collect()
+}
+
+// The --baseline means for flagging regressions, empty without a baseline;
+// print_baseline reports a file that cannot be loaded
+pub async fn load_baseline(registry: &BenchmarkRegistry, options: &CliOptions) -> Vec<(String, f64)> {
+    let Some(path) = &options.baseline else {
+        return Vec::new();
+    };
+    load_baseline_means(path).await.map_or_else(|_| Vec::new(), |(baseline, _)| resolve_baseline_names(registry, baseline))
+}
+// Means of the same benchmarks across results files (--compare), e.g. from
+// different commits or runtimes: one column per file with the change
+// against the first, and the geometric mean of those changes at the bottom.
+// Names are resolved through the registry so renamed benchmarks line up;
+// ones it does not know (another harness's) are kept as they are.
+struct ComparedFile {
+    path: String,
+    means: Vec<(String, f64)>,
+    labels: Labels,
+}
+
+pub async fn print_comparison(registry: &BenchmarkRegistry, options: &CliOptions) -> Result<(), String> {
+    let mut files: Vec<ComparedFile> = Vec::new();
+    for path in &options.compare_files {
+        let (means, labels) = load_baseline_means(path).await?;
+        let means = means
+            .into_iter()
+            .filter(|(name, _)| registry.resolve(name).map_or(options.filters.is_empty() && options.tags.is_empty(), |def| options.selects(def)))
+            .map(|(name, mean)| (registry.resolve(&name).map_or(name, |def| def.name.clone()), mean))
+            .collect();
+        files.push(ComparedFile { path: path.clone(), means, labels });
+    }
+    let mut names: Vec<&str> = Vec::new();
+    for file in &files {
+        for (name, _) in &file.means {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+    }
+    let mean_in = |file: usize, name: &str| files[file].means.iter().find(|(n, _)| n == name).map(|(_, mean)| *mean);
+    let column = |path: &str| std::path::Path::new(path).file_name().map_or(path.to_string(), |file| file.to_string_lossy().into_owned());
+
+    println!("\n=== Comparison (changes against {}) ===", column(&files[0].path));
+    for file in files.iter().filter(|file| !file.labels.is_empty()) {
+        println!("{}: {}", column(&file.path), format_labels(&file.labels));
+    }
+    if names.is_empty() {
+        println!("None of the files has a result in ns for the selected benchmarks.");
+        return Ok(());
+    }
+    print!("{:<30}", "Benchmark Name");
+    for file in &files {
+        print!(" {:>24}", column(&file.path));
+    }
+    println!();
+    println!("{}", "-".repeat(30 + 25 * files.len()));
+    let mut log_sums = vec![(0.0, 0usize); files.len()];
+    for name in &names {
+        print!("{:<30}", name);
+        let first = mean_in(0, name);
+        for (file, log_sum) in log_sums.iter_mut().enumerate() {
+            let cell = match (first, mean_in(file, name)) {
+                (_, None) => "-".to_string(),
+                (Some(first), Some(mean)) if file > 0 => {
+                    log_sum.0 += (mean / first).ln();
+                    log_sum.1 += 1;
+                    format!("{} ({:+.1}%)", format_value(mean, "ns"), (mean / first - 1.0) * 100.0)
+                }
+                (_, Some(mean)) => format_value(mean, "ns"),
+            };
+            print!(" {:>24}", cell);
+        }
+        println!();
+    }
+    println!("{}", "-".repeat(30 + 25 * files.len()));
+    print!("{:<30} {:>24}", "Geometric mean", "");
+    for &(log_sum, count) in &log_sums[1..] {
+        let cell = if count == 0 { "-".to_string() } else { format!("{:+.1}% ({} shared)", ((log_sum / count as f64).exp() - 1.0) * 100.0, count) };
+        print!(" {:>24}", cell);
+    }
+    println!();
+    Ok(())
+}
+
+// Mean, standard deviation and sample count of one benchmark in a results
+// file, enough for a t-test between two files
+struct SampleSummary {
+    name: String,
+    mean: f64,
+    stddev: f64,
+    count: u64,
+}
+
+fn summaries_in(suite: &serde_json::Value, path: &str) -> Result<Vec<SampleSummary>, String> {
+    let results = suite["results"].as_array().ok_or_else(|| format!("{} has no results array", path))?;
+    Ok(results
+        .iter()
+        .filter_map(|result| {
+            let name = result["name"].as_str()?;
+            let stats = if result["stats"].is_object() { &result["stats"] } else { result };
+            let mean = stats["mean_ns"].as_f64()?;
+            (stats["unit"].as_str().unwrap_or("ns") == "ns" && mean > 0.0).then(|| SampleSummary {
+                name: name.to_string(),
+                mean,
+                stddev: stats["stddev_ns"].as_f64().unwrap_or(0.0),
+                // Files from before samples_used was recorded count all of them
+                count: stats["samples_used"].as_u64().filter(|&used| used > 0).or_else(|| result["iterations"].as_u64()).unwrap_or(0),
+            })
+        })
+        .collect())
+}
+
+// How one benchmark's mean moved between the two files of `compare`
+enum DiffVerdict {
+    Regressed,
+    Improved,
+    // Significant, but within --max-slowdown either way
+    Minor,
+    Unchanged,
+    // Too few samples for a test
+    Unknown,
+}
+
+impl DiffVerdict {
+    fn label(&self) -> &'static str {
+        match self {
+            DiffVerdict::Regressed => "REGRESSED",
+            DiffVerdict::Improved => "improved",
+            DiffVerdict::Minor => "minor",
+            DiffVerdict::Unchanged => "~",
+            DiffVerdict::Unknown => "?",
+        }
+    }
+}
+
+// The compare command: a per-benchmark diff of two results files. A change
+// counts when Welch's t-test finds it significant at 95% and it exceeds
+// --max-slowdown; returns whether any benchmark regressed.
+pub async fn print_diff(registry: &BenchmarkRegistry, options: &CliOptions, old_path: &str, new_path: &str) -> Result<bool, String> {
+    let load = |suite: &serde_json::Value, path: &str| -> Result<Vec<SampleSummary>, String> {
+        Ok(summaries_in(suite, path)?
+            .into_iter()
+            .filter(|summary| registry.resolve(&summary.name).map_or(options.filters.is_empty() && options.tags.is_empty(), |def| options.selects(def)))
+            .map(|summary| SampleSummary { name: registry.resolve(&summary.name).map_or(summary.name.clone(), |def| def.name.clone()), ..summary })
+            .collect())
+    };
+    let old = load(&load_results_value(old_path).await?, old_path)?;
+    let new = load(&load_results_value(new_path).await?, new_path)?;
+
+    println!("\n=== Diff: {} -> {} ===", old_path, new_path);
+    println!("{:<30} {:>12} {:>12} {:>9} {:>9}  Verdict", "Benchmark Name", "Old", "New", "Change", "±95%");
+    println!("{}", "-".repeat(94));
+    let (mut regressed, mut improved, mut unchanged) = (0, 0, 0);
+    let mut log_sum = 0.0;
+    for after in &new {
+        let Some(before) = old.iter().find(|summary| summary.name == after.name) else {
+            println!("{:<30} {:>12} {:>12} {:>9} {:>9}  new", after.name, "-", format_value(after.mean, "ns"), "", "");
+            continue;
+        };
+        let change = after.mean / before.mean - 1.0;
+        log_sum += (after.mean / before.mean).ln();
+        let test = stats::welch_t_test((before.mean, before.stddev, before.count), (after.mean, after.stddev, after.count));
+        let (margin, verdict) = match test {
+            None => (None, DiffVerdict::Unknown),
+            Some(test) => {
+                let margin = stats::t_critical_95(test.df.min(u64::MAX as f64) as u64) * test.se / before.mean;
+                let verdict = if test.p_value() >= 0.05 {
+                    DiffVerdict::Unchanged
+                } else if change > options.max_slowdown {
+                    DiffVerdict::Regressed
+                } else if change < -options.max_slowdown {
+                    DiffVerdict::Improved
+                } else {
+                    DiffVerdict::Minor
+                };
+                (Some(margin), verdict)
+            }
+        };
+        match verdict {
+            DiffVerdict::Regressed => regressed += 1,
+            DiffVerdict::Improved => improved += 1,
+            _ => unchanged += 1,
+        }
+        let line = format!("{:<30} {:>12} {:>12} {:>9} {:>9}  {}", after.name, format_value(before.mean, "ns"), format_value(after.mean, "ns"),
+            format!("{:+.1}%", change * 100.0), margin.map_or("-".to_string(), |margin| format!("±{:.1}%", margin * 100.0)), verdict.label());
+        match verdict {
+            DiffVerdict::Regressed => println!("{}", paint(&line, Highlight::Red)),
+            _ => println!("{}", line),
+        }
+    }
+    for before in old.iter().filter(|before| !new.iter().any(|after| after.name == before.name)) {
+        println!("{:<30} {:>12} {:>12} {:>9} {:>9}  removed", before.name, format_value(before.mean, "ns"), "-", "", "");
+    }
+    println!("{}", "-".repeat(94));
+    let compared = regressed + improved + unchanged;
+    if compared == 0 {
+        println!("The files have no benchmark in common.");
+        return Ok(false);
+    }
+    let geomean = ((log_sum / compared as f64).exp() - 1.0) * 100.0;
+    let verdict = match (regressed, improved) {
+        (0, 0) => "no significant change",
+        (0, _) => "improved",
+        _ => "REGRESSED",
+    };
+    println!("Verdict: {} ({} regressed, {} improved, {} within noise or ±{:.1}%; geometric mean {:+.1}%)",
+        verdict, regressed, improved, unchanged, options.max_slowdown * 100.0, geomean);
+    Ok(regressed > 0)
+}
+
+// One report over the results files of the Rust, Go and FlowCoro C++
+// harnesses (--cross-language): scenarios aligned through the registry's
+// aliases, each harness's mean with its ratio to the fastest, and the winner.
+// Scenarios only one file has are left out.
+pub async fn print_cross_language(registry: &BenchmarkRegistry, options: &CliOptions) -> Result<(), String> {
+    let mut columns: Vec<(String, Vec<(String, f64)>)> = Vec::new();
+    for path in &options.cross_language_files {
+        let suite = load_results_value(path).await?;
+        let means = means_in(&suite, path)?
+            .into_iter()
+            .filter(|(name, _)| registry.resolve(name).map_or(options.filters.is_empty() && options.tags.is_empty(), |def| options.selects(def)))
+            .map(|(name, mean)| (registry.resolve(&name).map_or(name, |def| def.name.clone()), mean))
+            .collect();
+        columns.push((harness_of(&suite).to_string(), means));
+    }
+    // Files of the same harness are told apart by their file name
+    let headings: Vec<String> = columns.iter().zip(&options.cross_language_files).map(|((harness, _), path)| {
+        if columns.iter().filter(|(other, _)| other == harness).count() > 1 {
+            let file = std::path::Path::new(path).file_name().map_or(path.clone(), |file| file.to_string_lossy().into_owned());
+            format!("{} ({})", harness, file)
+        } else {
+            harness.clone()
+        }
+    }).collect();
+
+    let mut scenarios: Vec<&str> = Vec::new();
+    for (_, means) in &columns {
+        for (name, _) in means {
+            let files = columns.iter().filter(|(_, means)| means.iter().any(|(n, _)| n == name)).count();
+            if files > 1 && !scenarios.contains(&name.as_str()) {
+                scenarios.push(name);
+            }
+        }
+    }
+
+    println!("\n=== Cross-Language Report (mean, ratio to the fastest) ===");
+    for (heading, path) in headings.iter().zip(&options.cross_language_files) {
+        println!("{}: {}", heading, path);
+    }
+    if scenarios.is_empty() {
+        println!("The files have no scenario in common.");
+        return Ok(());
+    }
+    let width = headings.iter().map(|heading| heading.len()).max().unwrap_or(0).max(22);
+    print!("{:<30}", "Scenario");
+    for heading in &headings {
+        print!(" {:>width$}", heading, width = width);
+    }
+    println!("  Winner");
+    let rule = "-".repeat(30 + (width + 1) * headings.len() + 8 + headings.iter().map(|h| h.len()).max().unwrap_or(0));
+    println!("{}", rule);
+    let mut wins = vec![0usize; headings.len()];
+    for name in &scenarios {
+        let row: Vec<Option<f64>> = columns.iter()
+            .map(|(_, means)| means.iter().find(|(n, _)| n == name).map(|(_, mean)| *mean))
+            .collect();
+        let (winner, best) = row.iter().enumerate()
+            .filter_map(|(column, mean)| mean.map(|mean| (column, mean)))
+            .fold((0, f64::INFINITY), |best, candidate| if candidate.1 < best.1 { candidate } else { best });
+        wins[winner] += 1;
+        print!("{:<30}", name);
+        for mean in &row {
+            let cell = match mean {
+                Some(mean) => format!("{} ({:.2}x)", format_value(*mean, "ns"), mean / best),
+                None => "-".to_string(),
+            };
+            print!(" {:>width$}", cell, width = width);
+        }
+        println!("  {}", headings[winner]);
+    }
+    println!("{}", rule);
+    print!("{:<30}", "Wins");
+    for count in &wins {
+        print!(" {:>width$}", count, width = width);
+    }
+    println!();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Each harness's layout reads the same: this one and Go's nest the stats,
+    // FlowCoro's C++ one keeps them on the result; results in another unit
+    // than ns are left out
+    #[test]
+    fn means_from_every_harness() {
+        let rust = json!({
+            "system_info": {"rust_version": "1.87"},
+            "results": [
+                {"name": "spawn", "stats": {"mean_ns": 120.0, "unit": "ns"}},
+                {"name": "allocs", "stats": {"mean_ns": 3.0, "unit": "allocs"}},
+            ],
+        });
+        let go = json!({"system_info": {"go_version": "go1.22"}, "results": [{"name": "spawn", "stats": {"mean_ns": 90.0}}]});
+        let cpp = json!({"benchmark_info": {"flowcoro_version": "2.3"}, "results": [{"name": "spawn", "mean_ns": 60.0}]});
+        assert_eq!(means_in(&rust, "rust.json").unwrap(), vec![("spawn".to_string(), 120.0)]);
+        assert_eq!(means_in(&go, "go.json").unwrap(), vec![("spawn".to_string(), 90.0)]);
+        assert_eq!(means_in(&cpp, "cpp.json").unwrap(), vec![("spawn".to_string(), 60.0)]);
+        assert_eq!([harness_of(&rust), harness_of(&go), harness_of(&cpp), harness_of(&json!({}))], ["Rust", "Go", "FlowCoro C++", "unknown"]);
+        assert!(means_in(&json!({}), "empty.json").is_err());
+    }
+
+    // The t-test counts the samples the statistics were computed from, and
+    // files from before samples_used was recorded fall back to iterations
+    #[test]
+    fn summaries_count_samples_used() {
+        let suite = json!({"results": [
+            {"name": "trimmed", "iterations": 100, "stats": {"mean_ns": 10.0, "stddev_ns": 1.0, "samples_used": 81}},
+            {"name": "old", "iterations": 100, "stats": {"mean_ns": 10.0, "stddev_ns": 1.0}},
+        ]});
+        let summaries = summaries_in(&suite, "results.json").unwrap();
+        let counts: Vec<(&str, u64)> = summaries.iter().map(|summary| (summary.name.as_str(), summary.count)).collect();
+        assert_eq!(counts, [("trimmed", 81), ("old", 100)]);
+    }
+}
@@ -0,0 +1,174 @@
+// Results in criterion's on-disk layout (--criterion), so critcmp and
+// cargo-criterion tooling read them like a `cargo bench` run: per benchmark
+// a directory with benchmark.json, estimates.json, sample.json and
+// tukey.json. Criterion bootstraps its confidence intervals; here the
+// mean's comes from Student's t and the other estimates' standard errors
+// from their normal-theory approximations. sample.json holds one entry per
+// histogram bucket, its count as the iterations and count x midpoint as the
+// time, which gives the same distribution as the individual samples.
+
+use serde::Serialize;
+
+use crate::stats::{OutlierFences, OutlierMethod};
+use crate::BenchmarkResult;
+
+// Criterion's limit on directory names
+const MAX_DIRECTORY_NAME_LEN: usize = 64;
+const CONFIDENCE_LEVEL: f64 = 0.95;
+const Z_95: f64 = 1.959964;
+
+#[derive(Serialize)]
+struct BenchmarkId<'a> {
+    group_id: &'a str,
+    function_id: Option<&'a str>,
+    value_str: Option<&'a str>,
+    throughput: Option<()>,
+    full_id: &'a str,
+    directory_name: &'a str,
+}
+
+#[derive(Serialize)]
+struct ConfidenceInterval {
+    confidence_level: f64,
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+#[derive(Serialize)]
+struct Estimate {
+    confidence_interval: ConfidenceInterval,
+    point_estimate: f64,
+    standard_error: f64,
+}
+
+impl Estimate {
+    fn new(point_estimate: f64, standard_error: f64, half_width: f64) -> Self {
+        Self {
+            confidence_interval: ConfidenceInterval {
+                confidence_level: CONFIDENCE_LEVEL,
+                lower_bound: point_estimate - half_width,
+                upper_bound: point_estimate + half_width,
+            },
+            point_estimate,
+            standard_error,
+        }
+    }
+
+    fn normal(point_estimate: f64, standard_error: f64) -> Self {
+        Self::new(point_estimate, standard_error, Z_95 * standard_error)
+    }
+}
+
+#[derive(Serialize)]
+struct Estimates {
+    mean: Estimate,
+    median: Estimate,
+    median_abs_dev: Estimate,
+    slope: Option<Estimate>,
+    std_dev: Estimate,
+}
+
+#[derive(Serialize)]
+struct SavedSample {
+    sampling_mode: &'static str,
+    iters: Vec<f64>,
+    times: Vec<f64>,
+}
+
+// As criterion names a benchmark's directory: characters that are not safe
+// in file names replaced by '_', cut to 64 bytes
+pub fn directory_name(name: &str) -> String {
+    let mut safe = name.replace(['?', '"', '/', '\\', '*', '<', '>', ':', '|', '^'], "_");
+    if safe.len() > MAX_DIRECTORY_NAME_LEN {
+        let mut end = MAX_DIRECTORY_NAME_LEN;
+        while !safe.is_char_boundary(end) {
+            end -= 1;
+        }
+        safe.truncate(end);
+    }
+    safe
+}
+
+// (file name, contents) of the files criterion keeps per run of a timed
+// benchmark
+pub fn files(result: &BenchmarkResult) -> Result<Vec<(&'static str, String)>, serde_json::Error> {
+    let stats = &result.stats;
+    let directory = directory_name(&result.name);
+    let id = BenchmarkId {
+        group_id: &result.name,
+        function_id: None,
+        value_str: None,
+        throughput: None,
+        full_id: &result.name,
+        directory_name: &directory,
+    };
+
+    let n = stats.samples_used.max(2) as f64;
+    // Criterion reports the MAD scaled to estimate the standard deviation
+    let mad = 1.4826 * stats.mad_ns;
+    let estimates = Estimates {
+        mean: Estimate::new(stats.mean_ns, stats.sem_ns, stats.margin_ns),
+        median: Estimate::normal(stats.median_ns, 1.2533 * stats.sem_ns),
+        median_abs_dev: Estimate::normal(mad, 1.1664 * mad / n.sqrt()),
+        slope: result.regression.as_ref().map(|regression| {
+            let points: Vec<(f64, f64)> = regression.batches.iter().map(|b| (b.iterations as f64, b.total)).collect();
+            Estimate::normal(regression.slope, slope_standard_error(&points, regression.slope, regression.intercept))
+        }),
+        std_dev: Estimate::normal(stats.stddev_ns, stats.stddev_ns / (2.0 * (n - 1.0)).sqrt()),
+    };
+
+    let (iters, times) = stats.histogram.midpoints().map(|(value, count)| (count as f64, value * count as f64)).unzip();
+    let sample = SavedSample { sampling_mode: "Flat", iters, times };
+
+    // Criterion always uses Tukey's fences, whatever --outliers says
+    let fences = OutlierFences::new(&stats.histogram, OutlierMethod::Tukey);
+
+    Ok(vec![
+        ("benchmark.json", serde_json::to_string(&id)?),
+        ("estimates.json", serde_json::to_string(&estimates)?),
+        ("sample.json", serde_json::to_string(&sample)?),
+        ("tukey.json", serde_json::to_string(&fences.bounds())?),
+    ])
+}
+
+// Standard error of the slope of a least squares fit through `points`
+fn slope_standard_error(points: &[(f64, f64)], slope: f64, intercept: f64) -> f64 {
+    if points.len() < 3 {
+        return 0.0;
+    }
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / points.len() as f64;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sse: f64 = points.iter().map(|(x, y)| (y - intercept - slope * x).powi(2)).sum();
+    if sxx == 0.0 {
+        return 0.0;
+    }
+    (sse / (points.len() - 2) as f64 / sxx).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::completed;
+
+    #[test]
+    fn files_in_criterion_layout() {
+        assert_eq!(directory_name("Echo (TCP/loopback)"), "Echo (TCP_loopback)");
+        let result = completed("Spawn", 1..=100);
+        let files = files(&result).unwrap();
+        let names: Vec<&str> = files.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["benchmark.json", "estimates.json", "sample.json", "tukey.json"]);
+        let json = |name: &str| -> serde_json::Value { serde_json::from_str(&files.iter().find(|(n, _)| *n == name).unwrap().1).unwrap() };
+        assert_eq!(json("benchmark.json")["full_id"], "Spawn");
+        let estimates = json("estimates.json");
+        assert!((estimates["mean"]["point_estimate"].as_f64().unwrap() - 50.5).abs() < 0.1);
+        assert!(estimates["mean"]["confidence_interval"]["lower_bound"].as_f64().unwrap() < 50.5);
+        assert!(estimates["slope"].is_null());
+        // Iterations add up to the sample count, times to the total
+        let sample = json("sample.json");
+        let iterations: f64 = sample["iters"].as_array().unwrap().iter().map(|v| v.as_f64().unwrap()).sum();
+        let times: f64 = sample["times"].as_array().unwrap().iter().map(|v| v.as_f64().unwrap()).sum();
+        assert_eq!(iterations, 100.0);
+        assert!((times - 5050.0).abs() < 5.0, "{}", times);
+        assert_eq!(json("tukey.json").as_array().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,69 @@
+// What a results file was measured on. Numbers from machines (or builds) that
+// differ here are not comparable, so combining them needs --force.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MachineFingerprint {
+    pub cpu_model: String,
+    pub num_cpus: usize,
+    pub os: String,
+    pub arch: String,
+    pub rustc: String,
+    pub allocator: String,
+    // release or debug
+    pub profile: String,
+}
+
+// The global allocator installed in main.rs: the counting wrapper adds a
+// relaxed load to every allocation, so builds with and without it differ
+const ALLOCATOR: &str = if crate::COUNTING_ALLOCATOR { "system (counting wrapper)" } else { "system" };
+
+impl MachineFingerprint {
+    pub fn current() -> Self {
+        Self {
+            cpu_model: cpu_model(),
+            num_cpus: num_cpus::get(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            rustc: env!("RUSTC_VERSION").to_string(),
+            allocator: ALLOCATOR.to_string(),
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" }.to_string(),
+        }
+    }
+
+    // Fields that differ, as "what: ours vs theirs"
+    pub fn differences(&self, other: &Self) -> Vec<String> {
+        let mut differences = Vec::new();
+        let mut compare = |what: &str, ours: &str, theirs: &str| {
+            if ours != theirs {
+                differences.push(format!("{}: {} vs {}", what, ours, theirs));
+            }
+        };
+        compare("CPU", &self.cpu_model, &other.cpu_model);
+        compare("cores", &self.num_cpus.to_string(), &other.num_cpus.to_string());
+        compare("OS", &self.os, &other.os);
+        compare("arch", &self.arch, &other.arch);
+        compare("rustc", &self.rustc, &other.rustc);
+        compare("allocator", &self.allocator, &other.allocator);
+        compare("profile", &self.profile, &other.profile);
+        differences
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_model() -> String {
+    std::fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|cpuinfo| {
+            cpuinfo
+                .lines()
+                .find_map(|line| line.strip_prefix("model name").map(|rest| rest.trim_start_matches([' ', '\t', ':']).to_string()))
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_model() -> String {
+    "unknown".to_string()
+}
@@ -0,0 +1,193 @@
+// Google Benchmark's JSON output (--gbench-json), the format FlowCoro's C++
+// benchmarks write with --benchmark_format=json, so one comparison script
+// (compare.py or anything reading benchmarks[].real_time) handles both
+// sides. Each timed benchmark is a single "iteration" run; real_time is the
+// wall-clock mean and cpu_time the thread CPU mean, which is only measured
+// with --measure including cpu and otherwise repeats real_time. Labels go
+// into the context, where --benchmark_context puts them.
+
+use serde::Serialize;
+
+use crate::{BenchmarkResult, BenchmarkStatus, Labels};
+
+#[derive(Serialize)]
+pub struct Context {
+    date: String,
+    host_name: String,
+    executable: String,
+    num_cpus: usize,
+    mhz_per_cpu: u64,
+    cpu_scaling_enabled: bool,
+    caches: Vec<()>,
+    load_avg: Vec<f64>,
+    library_build_type: &'static str,
+    #[serde(flatten)]
+    labels: Labels,
+}
+
+impl Context {
+    pub fn current(labels: &Labels) -> Self {
+        Self {
+            date: chrono::Local::now().to_rfc3339(),
+            host_name: host_name(),
+            executable: std::env::args().next().unwrap_or_default(),
+            num_cpus: num_cpus::get(),
+            mhz_per_cpu: mhz_per_cpu(),
+            cpu_scaling_enabled: cpu_scaling_enabled(),
+            caches: Vec::new(),
+            load_avg: load_avg(),
+            library_build_type: if cfg!(debug_assertions) { "debug" } else { "release" },
+            labels: labels.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Run<'a> {
+    name: &'a str,
+    family_index: usize,
+    per_family_instance_index: usize,
+    run_name: &'a str,
+    run_type: &'static str,
+    repetitions: usize,
+    repetition_index: usize,
+    threads: usize,
+    iterations: usize,
+    real_time: f64,
+    cpu_time: f64,
+    time_unit: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    items_per_second: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    error_occurred: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_message: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Output<'a> {
+    context: Context,
+    benchmarks: Vec<Run<'a>>,
+}
+
+// Timed benchmarks only: Google Benchmark has no runs measured in anything
+// but time. Skipped ones are reported as errors, as a benchmark calling
+// state.SkipWithError() would be.
+pub fn to_json(results: &[BenchmarkResult], context: Context) -> serde_json::Result<String> {
+    let benchmarks = results
+        .iter()
+        .filter(|r| r.is_timed())
+        .enumerate()
+        .map(|(family_index, result)| {
+            let mean_of = |measurement: &str| if result.measurement == measurement {
+                Some(result.stats.mean_ns)
+            } else {
+                result.metrics.iter().find(|m| m.name == measurement).map(|m| m.stats.mean_ns)
+            };
+            let real_time = mean_of("wall").unwrap_or(result.stats.mean_ns);
+            let error_message = match &result.status {
+                BenchmarkStatus::Skipped(reason) => Some(reason.as_str()),
+                BenchmarkStatus::Completed => None,
+            };
+            Run {
+                name: &result.name,
+                family_index,
+                per_family_instance_index: 0,
+                run_name: &result.name,
+                run_type: "iteration",
+                repetitions: 1,
+                repetition_index: 0,
+                threads: 1,
+                iterations: result.iterations,
+                real_time,
+                cpu_time: mean_of("cpu").unwrap_or(real_time),
+                time_unit: "ns",
+                items_per_second: (real_time > 0.0 && error_message.is_none()).then(|| 1e9 / real_time),
+                error_occurred: error_message.is_some(),
+                error_message,
+            }
+        })
+        .collect();
+    serde_json::to_string_pretty(&Output { context, benchmarks })
+}
+
+#[cfg(target_os = "linux")]
+fn host_name() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname").map(|name| name.trim().to_string()).unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn host_name() -> String {
+    std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")).unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+fn mhz_per_cpu() -> u64 {
+    std::fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|cpuinfo| {
+            cpuinfo.lines().find_map(|line| {
+                let value = line.strip_prefix("cpu MHz")?.trim_start_matches([' ', '\t', ':']);
+                value.parse::<f64>().ok()
+            })
+        })
+        .map_or(0, |mhz| mhz.round() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mhz_per_cpu() -> u64 {
+    0
+}
+
+// As Google Benchmark decides it: a governor other than "performance"
+#[cfg(target_os = "linux")]
+fn cpu_scaling_enabled() -> bool {
+    std::fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")
+        .is_ok_and(|governor| governor.trim() != "performance")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_scaling_enabled() -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn load_avg() -> Vec<f64> {
+    std::fs::read_to_string("/proc/loadavg")
+        .map(|loadavg| loadavg.split_whitespace().take(3).filter_map(|value| value.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn load_avg() -> Vec<f64> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::completed;
+
+    // Timed results only, skipped ones as errors, labels in the context
+    #[test]
+    fn runs_in_google_benchmark_layout() {
+        let mut allocs = completed("Alloc", 2..=2);
+        allocs.stats.unit = "allocs".to_string();
+        let results = [completed("Spawn", 500..=500), allocs, BenchmarkResult::skipped("Perf", "no perf".to_string())];
+        let labels = Labels::from([("branch".to_string(), "main".to_string())]);
+        let json: serde_json::Value = serde_json::from_str(&to_json(&results, Context::current(&labels)).unwrap()).unwrap();
+        assert_eq!(json["context"]["branch"], "main");
+        let runs = json["benchmarks"].as_array().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0]["name"], "Spawn");
+        assert_eq!(runs[0]["real_time"], 500.0);
+        assert_eq!(runs[0]["cpu_time"], 500.0);
+        assert_eq!(runs[0]["items_per_second"], 2e6);
+        assert_eq!(runs[0]["time_unit"], "ns");
+        assert!(runs[0].get("error_occurred").is_none());
+        assert_eq!(runs[1]["name"], "Perf");
+        assert_eq!(runs[1]["error_occurred"], true);
+        assert_eq!(runs[1]["error_message"], "no perf");
+        assert_eq!(runs[1]["family_index"], 1);
+    }
+}
@@ -0,0 +1,122 @@
+// A ready-to-import Grafana dashboard (the grafana command) over what
+// --pushgateway or --influx publish: one time series panel per benchmark
+// category, plotting the mean of each benchmark in it. The data source is a
+// dashboard variable, picked when importing. Every run is its own
+// pushgateway group, all of them scraped together, so the Prometheus
+// queries follow the most recently pushed run; InfluxDB keeps a point per
+// run, which plots as history directly.
+
+use serde_json::{json, Value};
+
+use crate::prometheus::JOB;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Datasource {
+    Prometheus,
+    Influx,
+}
+
+impl Datasource {
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "prometheus" => Some(Datasource::Prometheus),
+            "influx" | "influxdb" => Some(Datasource::Influx),
+            _ => None,
+        }
+    }
+
+    fn plugin(self) -> &'static str {
+        match self {
+            Datasource::Prometheus => "prometheus",
+            Datasource::Influx => "influxdb",
+        }
+    }
+}
+
+// `categories` are (category, benchmark names) in the order to lay out
+pub fn dashboard(categories: &[(String, Vec<String>)], source: Datasource) -> Value {
+    let datasource = json!({ "type": source.plugin(), "uid": "${datasource}" });
+    let panels: Vec<Value> = categories.iter().enumerate().map(|(i, (category, names))| {
+        let (target, unit) = match source {
+            Datasource::Prometheus => (json!({
+                "refId": "A",
+                "datasource": datasource,
+                "expr": promql_mean(names),
+                "legendFormat": "{{benchmark}}",
+            }), "s"),
+            Datasource::Influx => (json!({
+                "refId": "A",
+                "datasource": datasource,
+                "query": flux_mean(names),
+            }), "ns"),
+        };
+        json!({
+            "id": i + 1,
+            "type": "timeseries",
+            "title": category,
+            "description": format!("Mean time per iteration of the {} benchmarks", category),
+            "datasource": datasource,
+            "gridPos": { "h": 9, "w": 12, "x": (i % 2) * 12, "y": (i / 2) * 9 },
+            "fieldConfig": {
+                "defaults": { "unit": unit, "custom": { "lineInterpolation": "stepAfter", "showPoints": "always" } },
+                "overrides": [],
+            },
+            "options": { "legend": { "displayMode": "table", "placement": "bottom", "calcs": ["lastNotNull", "min", "max"] } },
+            "targets": [target],
+        })
+    }).collect();
+
+    let mut variables = vec![json!({
+        "name": "datasource",
+        "label": "Data source",
+        "type": "datasource",
+        "query": source.plugin(),
+    })];
+    if source == Datasource::Influx {
+        variables.push(json!({
+            "name": "bucket",
+            "label": "Bucket",
+            "type": "textbox",
+            "query": "bench",
+            "current": { "text": "bench", "value": "bench" },
+        }));
+    }
+    json!({
+        "title": "Rust Benchmarks",
+        "uid": "professional-rust-benchmark",
+        "tags": ["benchmarks"],
+        "editable": true,
+        "schemaVersion": 39,
+        "time": { "from": "now-30d", "to": "now" },
+        "templating": { "list": variables },
+        "panels": panels,
+    })
+}
+
+// The means of the last run pushed, by benchmark
+fn promql_mean(names: &[String]) -> String {
+    let pattern: Vec<String> = names.iter().map(|name| escape_regex(name).replace('\\', "\\\\").replace('"', "\\\"")).collect();
+    let latest = format!("push_time_seconds{{job=\"{}\"}} == scalar(max(push_time_seconds{{job=\"{}\"}}))", JOB, JOB);
+    format!("max by (benchmark) (benchmark_mean_seconds{{job=\"{}\",benchmark=~\"{}\"}} and on(run_id) ({}))",
+        JOB, pattern.join("|"), latest)
+}
+
+fn flux_mean(names: &[String]) -> String {
+    let pattern: Vec<String> = names.iter().map(|name| escape_regex(name).replace('/', "\\/")).collect();
+    format!("from(bucket: \"${{bucket}}\")\n  |> range(start: v.timeRangeStart, stop: v.timeRangeStop)\n  \
+        |> filter(fn: (r) => r._measurement == \"benchmark\" and r._field == \"mean\" and r.unit == \"ns\")\n  \
+        |> filter(fn: (r) => r.name =~ /^({})$/)\n  |> keep(columns: [\"_time\", \"_value\", \"name\"])",
+        pattern.join("|"))
+}
+
+// Benchmark names carry brackets, parentheses and the like
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,79 @@
+// Every run kept instead of only the latest (--append-history), so trends
+// can be plotted from the files without a database.
+
+use chrono::{DateTime, Local};
+
+use crate::BenchmarkSuite;
+
+// Into a directory, one results file per run named by its time and commit;
+// into a file, one line of JSON per run.
+pub async fn append(suite: &BenchmarkSuite, path: &str) {
+    let is_dir = path.ends_with('/') || tokio::fs::metadata(path).await.is_ok_and(|metadata| metadata.is_dir());
+    let written = if is_dir {
+        let time = DateTime::from_timestamp(suite.system_info.timestamp as i64, 0)
+            .map(|time| time.with_timezone(&Local).format("%Y%m%d-%H%M%S").to_string())
+            .unwrap_or_default();
+        let commit = suite.system_info.git_commit.as_deref().map_or("nogit", |commit| &commit[..commit.len().min(12)]);
+        let dirty = if suite.system_info.git_dirty == Some(true) { "-dirty" } else { "" };
+        let file = std::path::Path::new(path).join(format!("{}-{}{}.json", time, commit, dirty));
+        match serde_json::to_string_pretty(suite) {
+            Ok(json) => match tokio::fs::create_dir_all(path).await {
+                Ok(()) => tokio::fs::write(&file, json).await.map(|()| file.display().to_string()),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e.into()),
+        }
+    } else {
+        match serde_json::to_string(suite) {
+            Ok(mut line) => {
+                line.push('\n');
+                crate::append_file(path, line.as_bytes()).await.map(|()| path.to_string())
+            }
+            Err(e) => Err(e.into()),
+        }
+    };
+    match written {
+        Ok(file) => tracing::info!(path = %file, "run appended to history"),
+        Err(e) => tracing::error!(path = %path, error = %e, "cannot append to history"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::completed;
+    use crate::{Labels, SystemInfo, DEFAULT_SEED, SCHEMA_VERSION};
+
+    fn suite(seed: u64) -> BenchmarkSuite {
+        BenchmarkSuite {
+            schema_version: SCHEMA_VERSION,
+            system_info: SystemInfo::current(None),
+            labels: Labels::new(),
+            seed,
+            shuffle_seed: None,
+            deterministic_seed: None,
+            results: vec![completed("Spawn", 1..=10)],
+        }
+    }
+
+    // A file gets one JSON line per run, a directory one file per run
+    #[tokio::test]
+    async fn runs_accumulate() {
+        let dir = std::env::temp_dir().join(format!("history-test-{}", std::process::id()));
+        let file = dir.join("history.jsonl").display().to_string();
+        std::fs::create_dir_all(&dir).unwrap();
+        append(&suite(1), &file).await;
+        append(&suite(DEFAULT_SEED), &file).await;
+        let seeds: Vec<u64> = std::fs::read_to_string(&file).unwrap().lines()
+            .map(|line| serde_json::from_str::<BenchmarkSuite>(line).unwrap().seed)
+            .collect();
+        assert_eq!(seeds, [1, DEFAULT_SEED]);
+
+        let runs = dir.join("runs");
+        append(&suite(1), &format!("{}/", runs.display())).await;
+        let written: Vec<_> = std::fs::read_dir(&runs).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(written.len(), 1);
+        assert!(written[0].ends_with(".json"), "{:?}", written);
+    }
+}
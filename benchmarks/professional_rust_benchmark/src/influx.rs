@@ -0,0 +1,97 @@
+// Results in InfluxDB's line protocol (--influx), for dashboards that keep
+// every run: printed to stdout, appended to a file or written straight to an
+// InfluxDB write endpoint over HTTP(S). --label annotations become tags.
+
+use crate::{BenchError, BenchmarkResult, BenchmarkStatus, Labels};
+
+// One "benchmark" point per completed result, one "benchmark_metric" point
+// per secondary measurement and one "benchmark_window" point per batch
+// window, tagged with benchmark name, measurement and unit.
+pub fn to_line_protocol(results: &[BenchmarkResult], labels: &Labels) -> String {
+    fn escape_tag(value: &str) -> String {
+        value.replace('\\', "\\\\").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+    }
+
+    let mut lines = String::new();
+    for result in results.iter().filter(|r| r.status == BenchmarkStatus::Completed) {
+        let mut tags = format!("name={},measurement={},unit={}",
+            escape_tag(&result.name), escape_tag(&result.measurement), escape_tag(&result.stats.unit));
+        for (key, value) in labels {
+            tags.push_str(&format!(",{}={}", escape_tag(key), escape_tag(value)));
+        }
+        let stats = &result.stats;
+        let mut fields = format!(
+            "iterations={}i,mean={},median={},min={},max={},stddev={},p95={},p99={},error_count={}i,error_rate={},anomalies={}i",
+            result.iterations, stats.mean_ns, stats.median_ns, stats.min_ns, stats.max_ns,
+            stats.stddev_ns, stats.p95_ns, stats.p99_ns, result.error_count, result.error_rate, stats.anomalies.count);
+        // Configured percentiles as extra fields: 99.9 becomes pct_99_9
+        for p in &stats.percentiles {
+            if let Some(value) = p.value {
+                fields.push_str(&format!(",pct_{}={}", p.percentile.to_string().replace('.', "_"), value));
+            }
+        }
+        lines.push_str(&format!("benchmark,{} {} {}\n", tags, fields, result.start_time_ns));
+        for metric in &result.metrics {
+            lines.push_str(&format!("benchmark_metric,name={},measurement={},unit={} mean={},median={},p95={},p99={} {}\n",
+                escape_tag(&result.name), escape_tag(&metric.name), escape_tag(&metric.stats.unit),
+                metric.stats.mean_ns, metric.stats.median_ns, metric.stats.p95_ns, metric.stats.p99_ns, result.start_time_ns));
+        }
+        for window in &result.windows {
+            lines.push_str(&format!("benchmark_window,{} samples={}i,errors={}i,mean={} {}\n",
+                tags, window.samples, window.errors, window.mean, window.end_time_ns));
+        }
+    }
+    lines
+}
+
+// POSTs line protocol to an InfluxDB write endpoint such as
+// http://localhost:8086/api/v2/write?org=perf&bucket=bench&precision=ns,
+// authenticating with $INFLUX_TOKEN when set.
+async fn post(url: &str, body: &str) -> Result<(), BenchError> {
+    let authorization = std::env::var("INFLUX_TOKEN").ok().map(|token| format!("Token {}", token));
+    crate::send_http(reqwest::Method::POST, url, "text/plain; charset=utf-8", authorization.as_deref(), body).await
+}
+
+pub async fn export(results: &[BenchmarkResult], labels: &Labels, target: &str) {
+    let body = to_line_protocol(results, labels);
+    if target == "-" {
+        // Every line starts with a benchmark* measurement, so
+        // `grep ^benchmark` separates them from the report
+        print!("{}", body);
+    } else if target.starts_with("http://") || target.starts_with("https://") {
+        match post(target, &body).await {
+            Ok(()) => tracing::info!(target = %target, "line protocol written"),
+            Err(e) => tracing::error!(target = %target, error = %e, "cannot write to InfluxDB"),
+        }
+    } else {
+        match tokio::fs::write(target, body).await {
+            Ok(_) => tracing::info!(path = %target, "line protocol saved"),
+            Err(e) => tracing::error!(path = %target, error = %e, "cannot write line protocol file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BenchmarkStats, StatsConfig};
+
+    // Spaces, commas and equals signs in tags are escaped; skipped results
+    // write no point
+    #[test]
+    fn escapes_tags_and_skips_incomplete_results() {
+        let mut result = BenchmarkResult::new("echo, tcp=1 conn".to_string());
+        result.stats = BenchmarkStats::with_precision(3);
+        for value in 1..=10 {
+            result.stats.record(value as f64);
+        }
+        result.stats.calculate(&StatsConfig::default());
+        result.iterations = 10;
+        let labels = Labels::from([("branch".to_string(), "my feature".to_string())]);
+        let skipped = BenchmarkResult::skipped("skipped", "no network".to_string());
+        let lines = to_line_protocol(&[result, skipped], &labels);
+        assert_eq!(lines.lines().count(), 1, "{}", lines);
+        assert!(lines.starts_with("benchmark,name=echo\\,\\ tcp\\=1\\ conn,measurement="), "{}", lines);
+        assert!(lines.contains(",branch=my\\ feature iterations=10i,mean=5.5,"), "{}", lines);
+    }
+}
@@ -0,0 +1,100 @@
+// JUnit XML (--junit) for CI systems that only understand test reports: one
+// test case per benchmark, skipped ones marked skipped, ones where every
+// iteration failed as errors, and with a --baseline, benchmarks whose mean
+// is more than `max_slowdown` slower than the baseline's as failures.
+
+use crate::{format_value_plain, BenchmarkResult, BenchmarkStatus};
+
+const SUITE_NAME: &str = "professional_rust_benchmark";
+
+pub fn to_xml(results: &[BenchmarkResult], baseline: &[(String, f64)], max_slowdown: f64) -> String {
+    let mut cases = String::new();
+    let (mut failures, mut errors, mut skipped) = (0, 0, 0);
+    let mut total_time = 0.0;
+    for result in results {
+        let time = result.total_time_ns / 1e9;
+        total_time += time;
+        cases.push_str(&format!("    <testcase classname=\"{}\" name=\"{}\" time=\"{:.6}\">\n",
+            SUITE_NAME, escape_xml(&result.name), time));
+        match &result.status {
+            BenchmarkStatus::Skipped(reason) => {
+                skipped += 1;
+                cases.push_str(&format!("      <skipped message=\"{}\"/>\n", escape_xml(reason)));
+            }
+            BenchmarkStatus::Completed if result.iterations == 0 && result.error_count > 0 => {
+                errors += 1;
+                let message = result.first_error.as_deref().unwrap_or("every iteration failed");
+                cases.push_str(&format!("      <error message=\"{}\" type=\"error\"/>\n", escape_xml(message)));
+            }
+            BenchmarkStatus::Completed => {
+                if let Some(message) = regression(result, baseline, max_slowdown) {
+                    failures += 1;
+                    cases.push_str(&format!("      <failure message=\"{}\" type=\"regression\"/>\n", escape_xml(&message)));
+                }
+                cases.push_str(&format!("      <system-out>{}</system-out>\n", escape_xml(&summary(result))));
+            }
+        }
+        cases.push_str("    </testcase>\n");
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.6}\">\n",
+        SUITE_NAME, results.len(), failures, errors, skipped, total_time));
+    xml.push_str(&format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.6}\">\n",
+        SUITE_NAME, results.len(), failures, errors, skipped, total_time));
+    xml.push_str(&cases);
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+// "mean 1.20 µs is 12.3% slower than the baseline's 1.07 µs (allowed: 5.0%)"
+fn regression(result: &BenchmarkResult, baseline: &[(String, f64)], max_slowdown: f64) -> Option<String> {
+    let slowdown = result.slowdown(baseline).filter(|&slowdown| slowdown > max_slowdown)?;
+    Some(format!("mean {} is {:.1}% slower than the baseline's {} (allowed: {:.1}%)",
+        format_value_plain(result.stats.mean_ns, "ns"), slowdown * 100.0,
+        format_value_plain(result.stats.mean_ns / (1.0 + slowdown), "ns"), max_slowdown * 100.0))
+}
+
+fn summary(result: &BenchmarkResult) -> String {
+    let unit = &result.stats.unit;
+    let mut summary = format!("{} iterations, mean {} ±{}, median {}, p99 {}", result.iterations,
+        format_value_plain(result.stats.mean_ns, unit), format_value_plain(result.stats.margin_ns, unit),
+        format_value_plain(result.stats.median_ns, unit), format_value_plain(result.stats.p99_ns, unit));
+    if result.error_count > 0 {
+        summary.push_str(&format!(", {} errors ({:.2}%)", result.error_count, result.error_rate * 100.0));
+    }
+    summary
+}
+
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::completed;
+
+    #[test]
+    fn one_case_per_benchmark_with_its_outcome() {
+        let mut failed = BenchmarkResult::new("Echo <tcp>".to_string());
+        failed.record_error(crate::BenchError::new("connection refused"));
+        failed.finish(&Default::default());
+        let results = [
+            completed("Spawn", 100..=199),
+            completed("Channel", 100..=199),
+            BenchmarkResult::skipped("Perf", "needs perf_event_open".to_string()),
+            failed,
+        ];
+        // Spawn is 50% slower than its baseline, Channel 10% faster
+        let baseline = [("Spawn".to_string(), 99.5), ("Channel".to_string(), 165.0)];
+        let xml = to_xml(&results, &baseline, 0.05);
+        assert!(xml.contains("<testsuite name=\"professional_rust_benchmark\" tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\""), "{}", xml);
+        assert_eq!(xml.matches("<testcase ").count(), 4);
+        assert!(xml.contains("<failure message=\"mean 149.50 ns is 50.3% slower than the baseline&apos;s 99.50 ns (allowed: 5.0%)\" type=\"regression\"/>"), "{}", xml);
+        assert!(xml.contains("name=\"Echo &lt;tcp&gt;\""));
+        assert!(xml.contains("<error message=\"connection refused\" type=\"error\"/>"));
+        assert!(xml.contains("<skipped message=\"needs perf_event_open\"/>"));
+        assert!(xml.contains("<system-out>100 iterations, mean 149.50 ns"), "{}", xml);
+    }
+}
@@ -0,0 +1,55 @@
+// Status output (-q/-v, --log-format): everything that is not part of the
+// report goes through tracing events, which tracing-subscriber's fmt layer
+// writes to stderr as plain lines or as one JSON object per line, so stdout
+// carries only the report and automation can parse the log. RUST_LOG, when
+// set, overrides the level -q/-v pick.
+
+use std::io::IsTerminal;
+
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// -q shows warnings and errors only, the default adds status messages
+// (files written, fixtures), -v each benchmark's progress and -vv the
+// harness's decisions along the way
+pub fn level_for(verbosity: i8) -> Level {
+    match verbosity {
+        i8::MIN..=-1 => Level::WARN,
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+// Installs the subscriber for the whole process; benchmarks registered
+// .without_tracing() still run with dispatch disabled
+pub fn install(level: Level, format: LogFormat) {
+    let filter = EnvFilter::builder().with_default_directive(level.into()).from_env_lossy();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal());
+    let installed = match format {
+        LogFormat::Text => builder.without_time().try_init(),
+        LogFormat::Json => builder.json().with_current_span(false).try_init(),
+    };
+    if installed.is_err() {
+        eprintln!("Warning: a tracing subscriber is already installed, log options ignored");
+    }
+}
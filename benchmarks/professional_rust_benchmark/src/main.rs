@@ -1,13 +1,59 @@
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::hash::{Hash, Hasher};
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use tokio::task::JoinSet;
+use std::future::Future;
+use std::pin::Pin;
+use tokio::io::AsyncWriteExt;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+mod badge;
+mod benchmarks;
+mod charts;
+mod cli;
+mod compare;
+mod criterion;
+mod fingerprint;
+mod gbench;
+mod grafana;
+mod history;
+mod influx;
+mod junit;
+mod logging;
+mod memory;
+mod pivot;
+mod profile;
+mod prometheus;
+mod report;
+mod runner;
+mod selftest;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stats;
+mod stream;
+mod tdigest;
+mod template;
+mod xlsx;
+
+use benchmarks::{register_benchmarks, SuiteFixtures};
+use cli::CliOptions;
+use fingerprint::MachineFingerprint;
+use profile::Profiler;
+use report::ReportTemplate;
+use runner::{on_own_thread, BenchmarkRunner, StderrProgress, SCHEDULE_RNG};
+use memory::{current_rss_bytes, format_bytes, MemoryUsage};
+use stats::{BatchMinima, Histogram, LogBucket, OutlierClass, OutlierFences, OutlierMethod};
+use tdigest::TDigest;
+
+// The *_ns field names predate pluggable measurements; `unit` says what the
+// values actually are ("ns" for clocks, "allocs", "bytes", "syscalls", ...).
+// Samples are only kept in `histogram`, so memory does not grow with the
+// number of iterations.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BenchmarkStats {
-    pub measurements: Vec<f64>,
+    #[serde(default = "default_unit")]
+    pub unit: String,
     pub min_ns: f64,
     pub max_ns: f64,
     pub mean_ns: f64,
@@ -15,12 +61,258 @@ pub struct BenchmarkStats {
     pub stddev_ns: f64,
     pub p95_ns: f64,
     pub p99_ns: f64,
+    // Median absolute deviation from median_ns, a spread that the odd
+    // scheduler hiccup doesn't inflate
+    #[serde(default)]
+    pub mad_ns: f64,
+    // Third and fourth standardized moments, the fourth less 3 so a normal
+    // distribution scores 0 on both
+    #[serde(default)]
+    pub skewness: f64,
+    #[serde(default)]
+    pub kurtosis: f64,
+    // Coefficient of variation, stddev_ns / mean_ns
+    #[serde(default)]
+    pub cv: f64,
+    // Standard error of mean_ns, and the half-width of its 95% confidence
+    // interval (Student's t with samples_used - 1 degrees of freedom)
+    #[serde(default)]
+    pub sem_ns: f64,
+    #[serde(default)]
+    pub margin_ns: f64,
+    // Samples the mean's error rests on: all of them, only those kept with
+    // --exclude-outliers, and with --winsorize those not clamped (n - 2k),
+    // as the clamped ones only repeat the values at the cut-offs
+    #[serde(default)]
+    pub samples_used: u64,
+    // Samples needed to tell this run from another differing by
+    // StatsConfig::resolve at 95% confidence, and the difference the samples
+    // taken can tell apart
+    #[serde(default)]
+    pub samples_needed: u64,
+    #[serde(default)]
+    pub resolvable: f64,
+    // All samples, at the configured precision (--precision); survives
+    // merging across workers. Left out of results files written with
+    // --tdigest, which keep `digest` instead.
+    #[serde(default, skip_serializing_if = "Histogram::is_empty")]
+    pub histogram: Histogram,
+    // The same samples as a t-digest (--tdigest): a few KB however long the
+    // run, and mergeable across workers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<TDigest>,
+    // Set when the histogram was rebuilt from the digest on loading, so
+    // quantiles come from the digest
+    #[serde(skip)]
+    digest_only: bool,
+    // The histogram regrouped into LOG_BUCKETS_PER_OCTAVE buckets per
+    // doubling, for plotting the distribution without decoding `histogram`
+    #[serde(default)]
+    pub buckets: Vec<LogBucket>,
+    // Peaks of the distribution (stats::modes); more than one means the mean
+    // is not a typical value
+    #[serde(default)]
+    pub modes: Vec<f64>,
+    // The runner's configured percentile list (--percentiles)
+    #[serde(default)]
+    pub percentiles: Vec<Percentile>,
+    #[serde(default)]
+    pub outliers: OutlierSummary,
+    #[serde(default)]
+    pub anomalies: Anomalies,
+    // Set with --winsorize, in which case mean_ns and stddev_ns are the
+    // winsorized figures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winsorized: Option<Winsorized>,
+    // Minimum of each fixed-size batch of samples (--min-of-batches)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_minima: Option<BatchMinima>,
+    // Samples in recording order, kept only until trim() while trimming
+    #[serde(skip)]
+    ordered: Option<Vec<f64>>,
+    // Every sample, as files from before the histogram stored them; read so
+    // restore_from_measurements() can rebuild the histogram, never written
+    #[serde(default, skip_serializing)]
+    measurements: Vec<f64>,
+}
+
+// Outlier counts plus mean/stddev with and without the outliers. mean_ns and
+// stddev_ns hold the filtered figures when `excluded` is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutlierSummary {
+    pub method: OutlierMethod,
+    pub low_mild: usize,
+    pub low_severe: usize,
+    pub high_mild: usize,
+    pub high_severe: usize,
+    pub percent: f64,
+    pub excluded: bool,
+    pub mean_all_ns: f64,
+    pub stddev_all_ns: f64,
+    pub mean_filtered_ns: f64,
+    pub stddev_filtered_ns: f64,
+}
+
+impl OutlierSummary {
+    pub fn count(&self) -> usize {
+        self.low_mild + self.low_severe + self.high_mild + self.high_severe
+    }
+}
+
+// Samples whose modified z-score |x - median| / scale exceeds `threshold`,
+// scale being the normal-consistent MAD (1.4826 MAD). The threshold sits
+// far beyond the tail of a healthy latency distribution, so what is counted
+// are stalls: a single 50ms hiccup among 50µs samples shows up here instead
+// of only nudging p99. A MAD no larger than the resolution around the median
+// (a clock tick or histogram bucket) says nothing about the spread, so those
+// benchmarks are not scored at all, and the scale never drops below
+// ANOMALY_MIN_SCALE of the median, which keeps quantized timings of a few ns
+// from flagging every sample one tick off.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Anomalies {
+    pub threshold: f64,
+    pub count: u64,
+    pub scale_ns: f64,
+    // Score of the sample furthest from the median
+    pub max_z: f64,
+}
+
+const ANOMALY_MIN_SCALE: f64 = 0.05;
+
+impl Anomalies {
+    fn detect(histogram: &Histogram, median: f64, mad: f64, threshold: f64) -> Self {
+        let mut anomalies = Self { threshold, ..Self::default() };
+        if histogram.is_empty() || mad <= histogram.bucket_width(median) {
+            return anomalies;
+        }
+        anomalies.scale_ns = (1.4826 * mad).max(ANOMALY_MIN_SCALE * median);
+        anomalies.count = histogram.midpoints()
+            .filter(|&(x, _)| anomalies.z_score(median, x) > threshold)
+            .map(|(_, count)| count)
+            .sum();
+        anomalies.max_z = anomalies.z_score(median, histogram.max()).max(anomalies.z_score(median, histogram.min()));
+        anomalies
+    }
+
+    pub fn z_score(&self, median: f64, value: f64) -> f64 {
+        if self.scale_ns > 0.0 { (value - median).abs() / self.scale_ns } else { 0.0 }
+    }
+}
+
+// Mean and stddev after clamping the lowest and highest `fraction` of the
+// samples to the values at those ranks, next to the raw figures. Unlike
+// dropping outliers, every sample still counts, but a one-off OS stall
+// weighs no more than the slowest ordinary sample.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Winsorized {
+    pub fraction: f64,
+    pub low_ns: f64,
+    pub high_ns: f64,
+    pub mean_ns: f64,
+    pub stddev_ns: f64,
+    pub raw_mean_ns: f64,
+    pub raw_stddev_ns: f64,
+    // Samples raised to low_ns and lowered to high_ns
+    #[serde(default)]
+    pub low_clamped: u64,
+    #[serde(default)]
+    pub high_clamped: u64,
+}
+
+// How samples are summarized; shared by the runner and the --merge tooling
+#[derive(Debug, Clone)]
+pub struct StatsConfig {
+    pub percentiles: Vec<f64>,
+    pub outlier_method: OutlierMethod,
+    pub exclude_outliers: bool,
+    // Coefficient of variation above which a result is reported as noisy
+    pub max_cv: f64,
+    // Significant digits kept by the sample histograms
+    pub precision: u32,
+    // Share of the samples dropped at each end of a run (ramp-up, wind-down)
+    pub trim: f64,
+    // Also keep a t-digest of this compression, which replaces the
+    // histogram in results files
+    pub digest_compression: Option<f64>,
+    // Also report the minimum of every this many consecutive samples
+    pub batch_size: Option<usize>,
+    // Samples that must lie beyond a percentile for it to be reported
+    pub min_tail_samples: u64,
+    // Share of the samples clamped at each end before computing mean and
+    // stddev
+    pub winsorize: Option<f64>,
+    // Relative difference between runs the samples should be able to resolve
+    pub resolve: f64,
+    // Modified z-score beyond which a sample counts as an anomaly
+    pub anomaly_z: f64,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            percentiles: DEFAULT_PERCENTILES.to_vec(),
+            outlier_method: OutlierMethod::default(),
+            exclude_outliers: false,
+            max_cv: 0.10,
+            precision: stats::DEFAULT_SIGNIFICANT_DIGITS,
+            trim: 0.0,
+            digest_compression: None,
+            batch_size: None,
+            min_tail_samples: DEFAULT_MIN_TAIL_SAMPLES,
+            winsorize: None,
+            resolve: DEFAULT_RESOLVE,
+            anomaly_z: DEFAULT_ANOMALY_Z,
+        }
+    }
+}
+
+// `value` is None when there were fewer than `samples_needed` samples, too
+// few beyond the percentile for it to mean anything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Percentile {
+    pub percentile: f64,
+    pub value: Option<f64>,
+    #[serde(default)]
+    pub samples_needed: u64,
+}
+
+impl Percentile {
+    // Samples needed for `min_tail` of them to lie beyond the percentile, on
+    // whichever side is thinner: 100k for p99.99 with the default of 10
+    fn required_samples(percentile: f64, min_tail: u64) -> u64 {
+        let tail = (percentile / 100.0).min(1.0 - percentile / 100.0);
+        if tail <= 0.0 {
+            return 0;
+        }
+        // Rounded first so that 10 / (1 - 0.9999) isn't taken as 100001
+        ((min_tail as f64 / tail * 1e6).round() / 1e6).ceil() as u64
+    }
+}
+
+pub const DEFAULT_PERCENTILES: [f64; 5] = [50.0, 90.0, 99.0, 99.9, 99.99];
+
+pub const DEFAULT_MIN_TAIL_SAMPLES: u64 = 10;
+pub const DEFAULT_RESOLVE: f64 = 0.02;
+pub const DEFAULT_ANOMALY_Z: f64 = 10.0;
+
+fn default_unit() -> String {
+    "ns".to_string()
 }
 
+// Resolution of BenchmarkStats::buckets: about 19% per bucket
+const LOG_BUCKETS_PER_OCTAVE: u32 = 4;
+// Size of the --histogram chart
+const HISTOGRAM_ROWS: usize = 24;
+const HISTOGRAM_BAR_WIDTH: usize = 40;
+
 impl BenchmarkStats {
     pub fn new() -> Self {
         Self {
-            measurements: Vec::new(),
+            unit: default_unit(),
             min_ns: 0.0,
             max_ns: 0.0,
             mean_ns: 0.0,
@@ -28,518 +320,4045 @@ impl BenchmarkStats {
             stddev_ns: 0.0,
             p95_ns: 0.0,
             p99_ns: 0.0,
+            mad_ns: 0.0,
+            skewness: 0.0,
+            kurtosis: 0.0,
+            cv: 0.0,
+            sem_ns: 0.0,
+            samples_used: 0,
+            samples_needed: 0,
+            resolvable: 0.0,
+            margin_ns: 0.0,
+            histogram: Histogram::new(),
+            digest: None,
+            digest_only: false,
+            buckets: Vec::new(),
+            modes: Vec::new(),
+            percentiles: Vec::new(),
+            outliers: OutlierSummary::default(),
+            anomalies: Anomalies::default(),
+            winsorized: None,
+            batch_minima: None,
+            ordered: None,
+            measurements: Vec::new(),
+        }
+    }
+
+    pub fn with_precision(significant_digits: u32) -> Self {
+        Self { histogram: Histogram::with_precision(significant_digits), ..Self::new() }
+    }
+
+    pub fn record(&mut self, value: f64) {
+        self.histogram.record(value);
+        if let Some(digest) = &mut self.digest {
+            digest.record(value);
+        }
+        if let Some(minima) = &mut self.batch_minima {
+            minima.record(value);
+        }
+        if let Some(ordered) = &mut self.ordered {
+            ordered.push(value);
+        }
+    }
+
+    // Also take the minimum of every `batch_size` consecutive samples
+    pub fn keep_batch_minima(&mut self, batch_size: usize) {
+        self.batch_minima = Some(BatchMinima::new(batch_size, self.histogram.significant_digits()));
+    }
+
+    // Also summarize the samples in a t-digest
+    pub fn keep_digest(&mut self, compression: f64) {
+        self.digest = Some(TDigest::new(compression));
+    }
+
+    // Folds another recording of the same benchmark into this one. The
+    // digest survives only if both sides have one.
+    pub fn merge(&mut self, other: &BenchmarkStats) {
+        self.histogram.merge(&other.histogram);
+        self.digest = match (self.digest.take(), &other.digest) {
+            (Some(mut own), Some(other_digest)) => {
+                own.merge(other_digest);
+                Some(own)
+            }
+            _ => None,
+        };
+        self.digest_only |= other.digest_only;
+        self.batch_minima = match (self.batch_minima.take(), &other.batch_minima) {
+            (Some(mut own), Some(other_minima)) => own.merge(other_minima).then_some(own),
+            _ => None,
+        };
+    }
+
+    // For stats loaded from a file written with --tdigest: rebuilds the
+    // histogram from the digest's centroids
+    pub fn restore_from_digest(&mut self) {
+        let Some(digest) = &self.digest else {
+            return;
+        };
+        if !self.histogram.is_empty() || digest.is_empty() {
+            return;
+        }
+        for (mean, weight) in digest.centroids() {
+            self.histogram.record_n(mean, weight);
         }
+        self.digest_only = true;
     }
 
-    pub fn calculate(&mut self) {
-        if self.measurements.is_empty() {
+    // For stats loaded from a file written before the histogram existed:
+    // rebuilds it from the raw samples
+    pub fn restore_from_measurements(&mut self) {
+        if !self.histogram.is_empty() {
             return;
         }
+        for value in std::mem::take(&mut self.measurements) {
+            self.histogram.record(value);
+        }
+    }
+
+    // Drops the histogram in favour of the digest, if there is one, before
+    // writing a results file
+    pub fn compact(&mut self) {
+        if self.digest.is_some() {
+            self.histogram = Histogram::with_precision(self.histogram.significant_digits());
+        }
+    }
+
+    fn quantile(&mut self, q: f64) -> f64 {
+        match &mut self.digest {
+            Some(digest) if self.digest_only => digest.quantile(q),
+            _ => self.histogram.quantile(q),
+        }
+    }
+
+    // Also keep the samples in recording order, so trim() can drop the ends
+    pub fn keep_order(&mut self) {
+        self.ordered = Some(Vec::new());
+    }
+
+    // Rebuilds the histogram without the first and last `fraction` of the
+    // samples recorded since keep_order(), returning how many were dropped at
+    // each end
+    pub fn trim(&mut self, fraction: f64) -> (usize, usize) {
+        let Some(ordered) = self.ordered.take() else {
+            return (0, 0);
+        };
+        let cut = (ordered.len() as f64 * fraction).floor() as usize;
+        if cut == 0 {
+            return (0, 0);
+        }
+        let mut histogram = Histogram::with_precision(self.histogram.significant_digits());
+        for &value in &ordered[cut..ordered.len() - cut] {
+            histogram.record(value);
+        }
+        self.histogram = histogram;
+        if let Some(digest) = &mut self.digest {
+            let mut rebuilt = TDigest::new(digest.compression());
+            for &value in &ordered[cut..ordered.len() - cut] {
+                rebuilt.record(value);
+            }
+            *digest = rebuilt;
+        }
+        if let Some(minima) = &mut self.batch_minima {
+            let mut rebuilt = BatchMinima::new(minima.batch_size, self.histogram.significant_digits());
+            for &value in &ordered[cut..ordered.len() - cut] {
+                rebuilt.record(value);
+            }
+            *minima = rebuilt;
+        }
+        (cut, cut)
+    }
+
+    pub fn is_multimodal(&self) -> bool {
+        self.modes.len() > 1
+    }
+
+    pub fn is_anomalous(&self, value: f64) -> bool {
+        self.anomalies.z_score(self.median_ns, value) > self.anomalies.threshold
+    }
 
-        self.measurements.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    pub fn calculate(&mut self, config: &StatsConfig) {
+        if self.histogram.is_empty() {
+            return;
+        }
 
-        self.min_ns = self.measurements[0];
-        self.max_ns = self.measurements[self.measurements.len() - 1];
+        self.min_ns = self.histogram.min();
+        self.max_ns = self.histogram.max();
+        self.buckets = self.histogram.log_buckets(LOG_BUCKETS_PER_OCTAVE);
+        self.modes = stats::modes(&self.histogram);
 
-        // Calculate mean
-        let sum: f64 = self.measurements.iter().sum();
-        self.mean_ns = sum / self.measurements.len() as f64;
+        // Median and percentiles, interpolated between closest ranks
+        if let Some(digest) = &mut self.digest {
+            digest.compress();
+        }
+        self.median_ns = self.quantile(0.5);
+        self.p95_ns = self.quantile(0.95);
+        self.p99_ns = self.quantile(0.99);
+        self.mad_ns = self.histogram.median_absolute_deviation(self.median_ns);
+        self.anomalies = Anomalies::detect(&self.histogram, self.median_ns, self.mad_ns, config.anomaly_z);
+        let midpoints: Vec<(f64, u64)> = self.histogram.midpoints().collect();
+        (self.skewness, self.kurtosis) = stats::skewness_and_kurtosis(&midpoints);
+        let samples = self.histogram.len();
+        self.percentiles = config.percentiles.iter()
+            .map(|&percentile| {
+                let samples_needed = Percentile::required_samples(percentile, config.min_tail_samples);
+                let value = (samples >= samples_needed).then(|| self.quantile(percentile / 100.0));
+                Percentile { percentile, value, samples_needed }
+            })
+            .collect();
 
-        // Calculate median
-        let n = self.measurements.len();
-        self.median_ns = if n % 2 == 0 {
-            (self.measurements[n / 2 - 1] + self.measurements[n / 2]) / 2.0
+        // Mean and standard deviation, with and without outliers. Buckets are
+        // classified by their midpoint.
+        let fences = OutlierFences::new(&self.histogram, config.outlier_method);
+        let mut outliers = OutlierSummary { method: config.outlier_method, excluded: config.exclude_outliers, ..Default::default() };
+        let mut filtered = Vec::new();
+        for (value, count) in self.histogram.midpoints() {
+            let count_usize = count as usize;
+            match fences.classify(value) {
+                OutlierClass::Normal => filtered.push((value, count)),
+                OutlierClass::LowMild => outliers.low_mild += count_usize,
+                OutlierClass::LowSevere => outliers.low_severe += count_usize,
+                OutlierClass::HighMild => outliers.high_mild += count_usize,
+                OutlierClass::HighSevere => outliers.high_severe += count_usize,
+            }
+        }
+        outliers.percent = outliers.count() as f64 / self.histogram.len() as f64 * 100.0;
+        (outliers.mean_all_ns, outliers.stddev_all_ns) = (self.histogram.mean(), self.histogram.stddev());
+        (outliers.mean_filtered_ns, outliers.stddev_filtered_ns) = if outliers.count() == 0 {
+            (outliers.mean_all_ns, outliers.stddev_all_ns)
+        } else {
+            stats::mean_and_stddev(&filtered)
+        };
+        (self.mean_ns, self.stddev_ns) = if config.exclude_outliers && !filtered.is_empty() {
+            (outliers.mean_filtered_ns, outliers.stddev_filtered_ns)
+        } else {
+            (outliers.mean_all_ns, outliers.stddev_all_ns)
+        };
+        // Buckets are clamped by their midpoint, like the outlier classification
+        self.winsorized = config.winsorize.map(|fraction| {
+            let (low_ns, high_ns) = (self.quantile(fraction), self.quantile(1.0 - fraction));
+            let clamped: Vec<(f64, u64)> = self.histogram.midpoints().map(|(value, count)| (value.clamp(low_ns, high_ns), count)).collect();
+            let (mean_ns, stddev_ns) = stats::mean_and_stddev(&clamped);
+            let low_clamped = self.histogram.midpoints().filter(|&(value, _)| value < low_ns).map(|(_, count)| count).sum();
+            let high_clamped = self.histogram.midpoints().filter(|&(value, _)| value > high_ns).map(|(_, count)| count).sum();
+            Winsorized { fraction, low_ns, high_ns, mean_ns, stddev_ns, raw_mean_ns: self.mean_ns, raw_stddev_ns: self.stddev_ns,
+                low_clamped, high_clamped }
+        });
+        if let Some(winsorized) = &self.winsorized {
+            (self.mean_ns, self.stddev_ns) = (winsorized.mean_ns, winsorized.stddev_ns);
+        }
+        self.cv = if self.mean_ns > 0.0 { self.stddev_ns / self.mean_ns } else { 0.0 };
+        let n = self.histogram.len();
+        self.samples_used = if config.exclude_outliers && !filtered.is_empty() {
+            n - outliers.count() as u64
+        } else if let Some(winsorized) = &self.winsorized {
+            n.saturating_sub(winsorized.low_clamped + winsorized.high_clamped)
+        } else {
+            n
+        };
+        let used = self.samples_used;
+        // stddev_ns is the population figure over the n samples it was taken
+        // from; the sample variance is n / (n - 1) times its square. Yuen's
+        // error of a winsorized mean divides that by h (h - 1) / (n - 1)
+        // instead, h being the samples not clamped, and has h - 1 degrees of
+        // freedom; without winsorizing h = n and this is stddev / sqrt(n - 1).
+        let stddev_over = if config.exclude_outliers { used } else { n };
+        (self.sem_ns, self.margin_ns) = if used > 1 {
+            let sem = self.stddev_ns * (stddev_over as f64 / (used as f64 * (used - 1) as f64)).sqrt();
+            (sem, sem * stats::t_critical_95(used - 1))
         } else {
-            self.measurements[n / 2]
+            (0.0, 0.0)
         };
+        self.samples_needed = stats::samples_to_resolve(self.cv, config.resolve);
+        self.resolvable = stats::resolvable_difference(self.cv, used);
+        self.outliers = outliers;
+        if let Some(minima) = &mut self.batch_minima {
+            minima.calculate();
+        }
+    }
+}
+
+// Error returned by fallible benchmark bodies. Any std error converts into it
+// so benchmark closures can use `?` directly.
+#[derive(Debug, Clone)]
+pub struct BenchError {
+    message: String,
+}
 
-        // Calculate percentiles
-        self.p95_ns = self.measurements[(n as f64 * 0.95) as usize];
-        self.p99_ns = self.measurements[(n as f64 * 0.99) as usize];
+impl BenchError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into() }
+    }
+}
 
-        // Calculate standard deviation
-        let variance: f64 = self.measurements
-            .iter()
-            .map(|x| (x - self.mean_ns).powi(2))
-            .sum::<f64>() / self.measurements.len() as f64;
-        self.stddev_ns = variance.sqrt();
+impl std::fmt::Display for BenchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
     }
 }
 
+impl<E: std::error::Error> From<E> for BenchError {
+    fn from(error: E) -> Self {
+        Self::new(error.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum BenchmarkStatus {
+    #[default]
+    Completed,
+    Skipped(String),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BenchmarkResult {
     pub name: String,
     pub stats: BenchmarkStats,
     pub iterations: usize,
     pub total_time_ns: f64,
+    // Iterations run before measuring started, and the successful ones'
+    // samples, kept apart from the steady-state stats
+    #[serde(default)]
+    pub warmup_iterations: usize,
+    #[serde(default)]
+    pub warmup: Option<BenchmarkStats>,
+    // Failed iterations are not part of stats/iterations
+    #[serde(default)]
+    pub error_count: usize,
+    #[serde(default)]
+    pub error_rate: f64,
+    #[serde(default)]
+    pub first_error: Option<String>,
+    #[serde(default)]
+    pub status: BenchmarkStatus,
+    #[serde(default = "default_measurement_name", alias = "clock")]
+    pub measurement: String,
+    // Unix time (ns) at which measuring started, and one window per batch of
+    // iterations, so results can be plotted over time
+    #[serde(default)]
+    pub start_time_ns: u64,
+    #[serde(default)]
+    pub windows: Vec<SampleWindow>,
+    #[serde(default)]
+    pub timeline: Option<SampleTimeline>,
+    #[serde(default)]
+    pub outlier_log: Option<OutlierLog>,
+    // Samples dropped from the start and end of the run (--trim); not part
+    // of stats or iterations
+    #[serde(default)]
+    pub trimmed_start: usize,
+    #[serde(default)]
+    pub trimmed_end: usize,
+    // Ops/sec per window; None for untimed results or ones without windows
+    #[serde(default)]
+    pub throughput: Option<ThroughputStats>,
+    // First vs second half of the run; None with fewer than two windows
+    #[serde(default)]
+    pub drift: Option<Drift>,
+    // Share of samples under each declared latency threshold
+    #[serde(default)]
+    pub sla: Vec<SlaBucket>,
+    // Order-independent sum of the hashes of every measured iteration's
+    // return value; None for benchmarks that return ()
+    #[serde(default)]
+    pub checksum: Option<u64>,
+    // Secondary measurements of the same iterations (--measure a,b,...)
+    #[serde(default)]
+    pub metrics: Vec<MetricStats>,
+    // Per-stage breakdown for composite benchmarks using a StageRecorder
+    #[serde(default)]
+    pub stages: Vec<StageAttribution>,
+    // Per-task latencies overall and per worker thread for concurrent
+    // benchmarks using a TaskRecorder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<WorkerBreakdown>,
+    // Per-iteration cost from batches of growing size (--regression)
+    #[serde(default)]
+    pub regression: Option<RegressionEstimate>,
+    // Memory occupancy while the samples were taken (--memory-interval)
+    #[serde(default)]
+    pub memory: Option<MemoryUsage>,
+    // Runtime the benchmark was measured on, and for a runtime variant the
+    // benchmark it is a variant of
+    #[serde(default)]
+    pub runtime: String,
+    #[serde(default)]
+    pub variant_of: Option<String>,
+    // The benchmark's parameters (BenchmarkDef::param) plus the runtime it
+    // ran on, the dimensions --pivot arranges results by
+    #[serde(default)]
+    pub params: std::collections::BTreeMap<String, String>,
 }
 
-impl BenchmarkResult {
-    pub fn new(name: String) -> Self {
-        Self {
-            name,
-            stats: BenchmarkStats::new(),
-            iterations: 0,
-            total_time_ns: 0.0,
-        }
+// Linear fit of batch total against batch size: the slope is the cost of one
+// iteration with anything paid once per batch (reading the clock, the loop
+// setup) pushed into the intercept
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegressionEstimate {
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+    pub batches: Vec<RegressionBatch>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegressionBatch {
+    pub iterations: u64,
+    pub total: f64,
+}
+
+impl RegressionEstimate {
+    pub fn fit(batches: Vec<RegressionBatch>) -> Self {
+        let points: Vec<(f64, f64)> = batches.iter().map(|b| (b.iterations as f64, b.total)).collect();
+        let (slope, intercept, r_squared) = stats::linear_fit(&points);
+        Self { slope, intercept, r_squared, batches }
     }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageAttribution {
+    pub name: String,
+    pub mean_ns: f64,
+    // Mean of the stage over the iterations at or above the p99 total
+    pub tail_mean_ns: f64,
+    pub mean_share: f64,
+    pub tail_share: f64,
+}
+
+// Latencies of the tasks a concurrent benchmark spawned, from just before the
+// spawn to the end of the task, overall and per worker thread of the runtime.
+// A task counts for the worker it finished on: work stealing can move it
+// between polls, so that is where it completed rather than where all of it
+// ran. Workers are numbered in the order they first completed a task.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkerBreakdown {
+    pub tasks: BenchmarkStats,
+    // One entry per runtime worker, including idle ones
+    pub workers: Vec<BenchmarkStats>,
+    // Busiest worker's task count over an even share; 1.0 is balanced
+    pub imbalance: f64,
+}
 
-    pub fn print_summary(&self) {
-        let throughput = 1e9 / self.stats.mean_ns;
-        println!("{:<30} {:>10} {:>12.0} ns {:>12.0} ns {:>14.2} ops/sec",
-            self.name, self.iterations, self.stats.mean_ns, self.stats.median_ns, throughput);
+impl WorkerBreakdown {
+    fn merge(&mut self, other: &WorkerBreakdown) {
+        self.tasks.merge(&other.tasks);
+        for (i, worker) in other.workers.iter().enumerate() {
+            match self.workers.get_mut(i) {
+                Some(own) => own.merge(worker),
+                None => self.workers.push(worker.clone()),
+            }
+        }
     }
 
-    pub fn print_detailed(&self) {
-        let throughput = 1e9 / self.stats.mean_ns;
-        println!("\n{} - Detailed Statistics:", self.name);
-        println!("  Iterations:    {}", self.iterations);
-        println!("  Mean:          {:.0} ns", self.stats.mean_ns);
-        println!("  Median:        {:.0} ns", self.stats.median_ns);
-        println!("  Min:           {:.0} ns", self.stats.min_ns);
-        println!("  Max:           {:.0} ns", self.stats.max_ns);
-        println!("  Std Dev:       {:.0} ns", self.stats.stddev_ns);
-        println!("  95th pct:      {:.0} ns", self.stats.p95_ns);
-        println!("  99th pct:      {:.0} ns", self.stats.p99_ns);
-        println!("  Throughput:    {:.2} ops/sec", throughput);
+    fn calculate(&mut self, config: &StatsConfig) {
+        self.tasks.calculate(config);
+        for worker in &mut self.workers {
+            worker.calculate(config);
+        }
+        let busiest = self.workers.iter().map(|worker| worker.histogram.len()).max().unwrap_or(0);
+        let even_share = self.tasks.histogram.len() as f64 / self.workers.len().max(1) as f64;
+        self.imbalance = if even_share > 0.0 { busiest as f64 / even_share } else { 0.0 };
     }
 }
 
-pub struct BenchmarkRunner {
-    warmup_iterations: usize,
-    min_iterations: usize,
-    max_iterations: usize,
-    min_benchmark_time_ns: u128,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricStats {
+    pub name: String,
+    pub stats: BenchmarkStats,
 }
 
-impl BenchmarkRunner {
-    pub fn new() -> Self {
-        Self {
-            warmup_iterations: 10,
-            min_iterations: 100,
-            max_iterations: 10000,
-            min_benchmark_time_ns: 100_000_000, // 100ms minimum
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SampleWindow {
+    // Unix time (ns) at which the batch finished
+    pub end_time_ns: u64,
+    pub samples: usize,
+    pub errors: usize,
+    pub mean: f64,
+}
+
+// Distribution of 1e9/mean over the sample windows, the spread that the
+// throughput derived from the overall mean hides
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThroughputStats {
+    pub p5: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub windows: usize,
+}
+
+impl ThroughputStats {
+    fn from_windows(windows: &[SampleWindow]) -> Option<Self> {
+        let mut rates: Vec<f64> = windows.iter().filter(|w| w.samples > 0 && w.mean > 0.0).map(|w| 1e9 / w.mean).collect();
+        if rates.is_empty() {
+            return None;
         }
+        rates.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Some(Self {
+            p5: stats::quantile(&rates, 0.05),
+            p50: stats::quantile(&rates, 0.50),
+            p95: stats::quantile(&rates, 0.95),
+            windows: rates.len(),
+        })
     }
+}
 
-    pub async fn run<F, Fut>(&self, name: &str, mut benchmark_func: F) -> BenchmarkResult
-    where
-        F: FnMut() -> Fut,
-        Fut: std::future::Future<Output = ()>,
-    {
-        // Warmup phase
-        for _ in 0..self.warmup_iterations {
-            benchmark_func().await;
-        }
+// Share of the samples at or below a latency threshold the benchmark declared
+// (BenchmarkDef::sla)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlaBucket {
+    pub threshold_ns: f64,
+    pub fraction: f64,
+}
 
-        let mut result = BenchmarkResult::new(name.to_string());
-        let total_start = Instant::now();
-        let mut iterations = self.min_iterations;
-        let mut elapsed = 0u128;
+// The thresholds the FlowCoro comparison results are usually discussed in
+const FLOWCORO_SLA: [std::time::Duration; 3] = [
+    std::time::Duration::from_micros(1),
+    std::time::Duration::from_micros(10),
+    std::time::Duration::from_micros(100),
+];
 
-        while elapsed < self.min_benchmark_time_ns && iterations <= self.max_iterations {
-            for _ in 0..iterations {
-                let start = Instant::now();
-                benchmark_func().await;
-                let duration = start.elapsed();
-                result.stats.measurements.push(duration.as_nanos() as f64);
-            }
+// "500ns", "1µs", "2.5ms"
+fn format_threshold(ns: f64) -> String {
+    let (value, unit) = if ns >= 1e9 {
+        (ns / 1e9, "s")
+    } else if ns >= 1e6 {
+        (ns / 1e6, "ms")
+    } else if ns >= 1e3 {
+        (ns / 1e3, "µs")
+    } else {
+        (ns, "ns")
+    };
+    format!("{}{}", (value * 1000.0).round() / 1000.0, unit)
+}
 
-            elapsed = total_start.elapsed().as_nanos();
-            if elapsed < self.min_benchmark_time_ns {
-                iterations = std::cmp::min(iterations * 2, self.max_iterations);
-            }
+// Mean of the first half of the samples against the second, from the sample
+// windows; a run that trends (thermal throttling, state that accumulates
+// across iterations) has no single representative mean
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Drift {
+    pub first_half_mean: f64,
+    pub second_half_mean: f64,
+    // (second - first) / first
+    pub change: f64,
+    // Difference in standard errors of the difference
+    pub z: f64,
+}
+
+// A drift is reported when it is both this large and this unlikely to be noise
+const DRIFT_MIN_CHANGE: f64 = 0.05;
+const DRIFT_MIN_Z: f64 = 3.0;
+
+impl Drift {
+    // Windows are assigned whole, to the half their middle sample falls in;
+    // `stddev` is that of all samples
+    fn from_windows(windows: &[SampleWindow], stddev: f64) -> Option<Self> {
+        let total: usize = windows.iter().map(|w| w.samples).sum();
+        let (mut halves, mut seen) = ([(0usize, 0.0f64); 2], 0usize);
+        for window in windows.iter().filter(|w| w.samples > 0) {
+            let half = usize::from(2 * seen + window.samples > total);
+            halves[half].0 += window.samples;
+            halves[half].1 += window.mean * window.samples as f64;
+            seen += window.samples;
+        }
+        let [(first_count, first_sum), (second_count, second_sum)] = halves;
+        if first_count == 0 || second_count == 0 {
+            return None;
+        }
+        let (first_half_mean, second_half_mean) = (first_sum / first_count as f64, second_sum / second_count as f64);
+        if first_half_mean <= 0.0 {
+            return None;
         }
+        let standard_error = stddev * (1.0 / first_count as f64 + 1.0 / second_count as f64).sqrt();
+        let difference = second_half_mean - first_half_mean;
+        Some(Self {
+            first_half_mean,
+            second_half_mean,
+            change: difference / first_half_mean,
+            z: if standard_error > 0.0 { difference / standard_error } else { 0.0 },
+        })
+    }
 
-        result.iterations = result.stats.measurements.len();
-        result.total_time_ns = elapsed as f64;
-        result.stats.calculate();
-        result
+    pub fn is_significant(&self) -> bool {
+        self.change.abs() > DRIFT_MIN_CHANGE && self.z.abs() > DRIFT_MIN_Z
     }
+}
 
-    pub fn run_sync<F>(&self, name: &str, mut benchmark_func: F) -> BenchmarkResult
-    where
-        F: FnMut(),
-    {
-        // Warmup phase
-        for _ in 0..self.warmup_iterations {
-            benchmark_func();
-        }
+// Samples above `multiple` times the median (--outlier-log), with what the
+// process looked like when they finished, to correlate spikes with system
+// events rather than only seeing a large max. While measuring, the median so
+// far (the warmup's before the first window) decides what to capture; finish()
+// keeps the ones above the final median's threshold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutlierLog {
+    pub multiple: f64,
+    pub samples: Vec<OutlierSample>,
+    // Spikes not captured because the log was full
+    pub missed: usize,
+    #[serde(skip)]
+    threshold: f64,
+}
 
-        let mut result = BenchmarkResult::new(name.to_string());
-        let total_start = Instant::now();
-        let mut iterations = self.min_iterations;
-        let mut elapsed = 0u128;
+const OUTLIER_LOG_CAPACITY: usize = 1000;
 
-        while elapsed < self.min_benchmark_time_ns && iterations <= self.max_iterations {
-            for _ in 0..iterations {
-                let start = Instant::now();
-                benchmark_func();
-                let duration = start.elapsed();
-                result.stats.measurements.push(duration.as_nanos() as f64);
-            }
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutlierSample {
+    // Position among the benchmark's recorded samples
+    pub index: u64,
+    pub value: f64,
+    // Unix and CLOCK_MONOTONIC time at which the sample finished
+    pub time_ns: u64,
+    pub monotonic_ns: u64,
+    pub rss_bytes: Option<u64>,
+    // Only with heap tracking on (--memory-interval)
+    pub heap_bytes: Option<u64>,
+    // Tokio runtime the benchmark ran on; None for sync benchmarks
+    pub alive_tasks: Option<usize>,
+    pub queue_depth: Option<usize>,
+}
 
-            elapsed = total_start.elapsed().as_nanos();
-            if elapsed < self.min_benchmark_time_ns {
-                iterations = std::cmp::min(iterations * 2, self.max_iterations);
-            }
+impl OutlierLog {
+    fn new(multiple: f64, reference_median: f64) -> Self {
+        Self { multiple, threshold: multiple * reference_median, ..Self::default() }
+    }
+
+    // Captures the context right after the sample, outside its measurement
+    fn observe(&mut self, index: u64, value: f64, time_ns: u64) {
+        if value <= self.threshold {
+            return;
+        }
+        if self.samples.len() >= OUTLIER_LOG_CAPACITY {
+            self.missed += 1;
+            return;
         }
+        let runtime = tokio::runtime::Handle::try_current().ok().map(|handle| handle.metrics());
+        self.samples.push(OutlierSample {
+            index,
+            value,
+            time_ns,
+            monotonic_ns: monotonic_time_ns(),
+            rss_bytes: current_rss_bytes(),
+            heap_bytes: HEAP_TRACKING.load(std::sync::atomic::Ordering::Relaxed).then(heap_in_use),
+            alive_tasks: runtime.as_ref().map(|metrics| metrics.num_alive_tasks()),
+            queue_depth: runtime.as_ref().map(|metrics| metrics.global_queue_depth()),
+        });
+    }
 
-        result.iterations = result.stats.measurements.len();
-        result.total_time_ns = elapsed as f64;
-        result.stats.calculate();
-        result
+    fn update_median(&mut self, median: f64) {
+        self.threshold = self.multiple * median;
+    }
+
+    fn finish(&mut self, median: f64) {
+        self.update_median(median);
+        let threshold = self.threshold;
+        self.samples.retain(|sample| sample.value > threshold);
+    }
+
+    fn merge(&mut self, other: OutlierLog, index_offset: u64) {
+        self.samples.extend(other.samples.into_iter().map(|sample| OutlierSample { index: sample.index + index_offset, ..sample }));
+        self.missed += other.missed;
     }
 }
 
-// Benchmark functions
+// When each sample was taken (--timestamps), to line latency spikes up with
+// external events afterwards. Offsets count from monotonic_start_ns, a
+// CLOCK_MONOTONIC reading, the clock perf, journald and most tracers use.
+//
+// With a reservoir (--reservoir) only a uniform random subset of at most
+// that many samples is kept (Vitter's algorithm R), so soak tests with
+// millions of samples run in bounded memory; the statistics come from the
+// histogram and stay exact either way.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SampleTimeline {
+    pub monotonic_start_ns: u64,
+    // (offset in ns at which the sample finished, sample value)
+    pub samples: Vec<(u64, f64)>,
+    // Samples offered, of which `samples` holds all or a reservoir sample
+    #[serde(default)]
+    pub seen: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reservoir: Option<usize>,
+    #[serde(skip)]
+    rng: BenchRng,
+}
+
+impl SampleTimeline {
+    fn start(reservoir: Option<usize>, rng: BenchRng) -> Self {
+        Self { monotonic_start_ns: monotonic_time_ns(), samples: Vec::new(), seen: 0, reservoir, rng }
+    }
 
-async fn benchmark_task_creation_and_execution() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run("Task Creation & Execution", || async {
-        let handle = tokio::spawn(async {
-            // 模拟任务执行中的一些计算
-            let mut sum = 0;
-            for i in 0..10 {
-                sum += i;
-            }
-            sum
-        });
-        let _ = handle.await.unwrap();
-    }).await
-}
-
-async fn benchmark_channel_ops() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run("Channel Operations", || async {
-        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
-        tx.send(42).await.unwrap();
-        let _ = rx.recv().await.unwrap();
-    }).await
-}
-
-fn benchmark_simple_computation() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run_sync("Simple Computation", || {
-        let mut sum = 0;
-        for i in 0..100 {
-            sum += i;
-        }
-        let _ = sum;
-    })
-}
-
-// 复杂任务基准测试 - 测试调度器处理复杂计算的能力
-fn benchmark_complex_computation() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run_sync("Complex Computation Task", || {
-        // 1. 矩阵运算 (3x3矩阵乘法)
-        let matrix_a = [1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7, 8.8, 9.9];
-        let matrix_b = [9.9, 8.8, 7.7, 6.6, 5.5, 4.4, 3.3, 2.2, 1.1];
-        let mut result_matrix = [0.0; 9];
-        
-        for i in 0..3 {
-            for j in 0..3 {
-                for k in 0..3 {
-                    result_matrix[i * 3 + j] += matrix_a[i * 3 + k] * matrix_b[k * 3 + j];
+    fn record(&mut self, offset: u64, value: f64) {
+        self.seen += 1;
+        match self.reservoir {
+            Some(capacity) if self.samples.len() >= capacity => {
+                let slot = self.rng.gen_range(self.seen) as usize;
+                if slot < capacity {
+                    self.samples[slot] = (offset, value);
                 }
             }
+            _ => self.samples.push((offset, value)),
         }
-        
-        // 2. 字符串处理和哈希计算
-        let data = "ComplexTaskBenchmark";
-        let mut hash: u64 = 0;
-        for c in data.chars() {
-            hash = hash.wrapping_mul(31).wrapping_add(c as u64);
-            hash ^= hash >> 16;
-        }
-        
-        // 3. 三角函数计算
-        let mut trig_sum = 0.0;
-        for i in 1..=50 {
-            let angle = i as f64 * 0.1;
-            trig_sum += angle.sin() * angle.cos() + (angle * 0.5).tan();
-        }
-        
-        // 4. 动态内存操作
-        let mut dynamic_data = Vec::with_capacity(100);
-        for i in 0..100 {
-            dynamic_data.push(i * i + (hash % 1000) as i32);
-        }
-        
-        // 5. 复杂条件分支和数据处理
-        let mut final_result = 0.0;
-        for (i, &val) in dynamic_data.iter().enumerate() {
-            if val % 3 == 0 {
-                final_result += (val as f64).sqrt();
-            } else if val % 5 == 0 {
-                final_result += (val as f64 + 1.0).ln();
-            } else {
-                final_result += val as f64 * 0.1;
-            }
-            let _ = i; // 防止编译器优化
-        }
-        
-        // 6. 合并所有计算结果
-        let mut total = 0.0;
-        for val in result_matrix.iter() {
-            total += val;
-        }
-        total += trig_sum + final_result + hash as f64;
-        
-        let _ = total; // 防止编译器优化
-    })
-}
-
-async fn benchmark_concurrent_tasks() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run("Concurrent Tasks (10)", || async {
-        let mut join_set = JoinSet::new();
-        
-        for _ in 0..10 {
-            join_set.spawn(async {
-                tokio::time::sleep(tokio::time::Duration::from_micros(1)).await;
-            });
-        }
+    }
 
-        while let Some(result) = join_set.join_next().await {
-            let _ = result.unwrap();
-        }
-    }).await
-}
-
-async fn benchmark_echo_server() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    
-    let result = runner.run("Echo Server Throughput", || async {
-        // Simulate network processing without server startup overhead
-        let data = vec![65u8; 20]; // Fill with 'A' characters
-        
-        // Simulate echo processing
-        let mut echo = Vec::with_capacity(data.len());
-        echo.extend_from_slice(&data);
-        
-        // Simulate checksum validation
-        let sum: u32 = echo.iter().map(|&b| b as u32).sum();
-        let _ = sum;
-    }).await;
-    
-    result
-}
-
-async fn benchmark_concurrent_echo_clients() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    const CLIENT_COUNT: usize = 100;  // 与FlowCoro和Go保持一致：100个并发任务
-    
-    let result = runner.run("Concurrent Echo Clients", || async {
-        let mut join_set = JoinSet::new();
-        
-        for _ in 0..CLIENT_COUNT {
-            join_set.spawn(async {
-                // 模拟更多的网络处理工作（与FlowCoro和Go一致）
-                let mut work = 0;
-                for j in 0..1000 {  // 1000次循环，与FlowCoro和Go一致
-                    work += j * j;  // 更复杂的计算
+    // Appends another block's samples, shifting them onto this block's start.
+    // Two reservoirs are combined by drawing how many of the kept samples
+    // come from each side as if drawing from all the samples both saw, then
+    // that many at random from each reservoir.
+    fn merge(&mut self, other: SampleTimeline) {
+        let shift = other.monotonic_start_ns as i128 - self.monotonic_start_ns as i128;
+        let mut shifted: Vec<(u64, f64)> = other.samples.iter().map(|&(offset, value)| ((offset as i128 + shift).max(0) as u64, value)).collect();
+        let capacity = self.reservoir.or(other.reservoir).unwrap_or(usize::MAX);
+        let (mut own_left, mut other_left) = (self.offered(), other.offered());
+        self.seen = own_left + other_left;
+        if self.samples.len() + shifted.len() <= capacity {
+            self.samples.append(&mut shifted);
+        } else {
+            // A side that isn't full saw only what it kept, so it is never
+            // asked for more than it has
+            let mut own_take = 0;
+            for _ in 0..capacity {
+                if self.rng.gen_range(own_left + other_left) < own_left {
+                    own_take += 1;
+                    own_left -= 1;
+                } else {
+                    other_left -= 1;
                 }
-                
-                // 模拟网络延迟（与FlowCoro和Go的sleep对应）
-                tokio::time::sleep(tokio::time::Duration::from_micros(1)).await;
-                
-                let _ = work; // 防止编译器优化
-            });
-        }
-        
-        while let Some(result) = join_set.join_next().await {
-            let _ = result.unwrap();
-        }
-    }).await;
-    
-    result
-}
-
-fn benchmark_small_data_transfer() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run_sync("Small Data Transfer (64B)", || {
-        let mut data = vec![0u8; 64];
-        for (i, byte) in data.iter_mut().enumerate() {
-            *byte = (i % 256) as u8;
-        }
-        // Simulate checksum
-        let sum: usize = data.iter().map(|&b| b as usize).sum();
-        let _ = sum;
-    })
-}
-
-fn benchmark_medium_data_transfer() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run_sync("Medium Data Transfer (4KB)", || {
-        let mut data = vec![0u8; 4096];
-        for (i, byte) in data.iter_mut().enumerate() {
-            *byte = (i % 256) as u8;
-        }
-        // Simulate checksum
-        let sum: usize = data.iter().map(|&b| b as usize).sum();
-        let _ = sum;
-    })
-}
-
-fn benchmark_large_data_transfer() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run_sync("Large Data Transfer (64KB)", || {
-        let mut data = vec![0u8; 65536];
-        for (i, byte) in data.iter_mut().enumerate() {
-            *byte = (i % 256) as u8;
-        }
-        // Simulate compression
-        let mut compressed_size = 0;
-        for i in (0..data.len()).step_by(64) {
-            if i > 0 && data[i] == data[i - 64] {
-                compressed_size += 1; // compression marker
-            } else {
-                compressed_size += 64; // raw data
             }
+            let mut own = std::mem::take(&mut self.samples);
+            self.pick(&mut own, own_take);
+            self.pick(&mut shifted, capacity - own_take);
+            own.append(&mut shifted);
+            self.samples = own;
+            self.reservoir = Some(capacity);
+        }
+        self.samples.sort_by_key(|&(offset, _)| offset);
+    }
+
+    // Keeps `count` of `samples` chosen at random (partial Fisher-Yates)
+    fn pick(&mut self, samples: &mut Vec<(u64, f64)>, count: usize) {
+        for i in 0..count.min(samples.len()) {
+            let j = i + self.rng.gen_range((samples.len() - i) as u64) as usize;
+            samples.swap(i, j);
+        }
+        samples.truncate(count);
+    }
+
+    // Files written before `seen` was recorded kept every sample
+    fn offered(&self) -> u64 {
+        self.seen.max(self.samples.len() as u64)
+    }
+
+    // A reservoir is filled out of order
+    fn finish(&mut self) {
+        if self.reservoir.is_some() {
+            self.samples.sort_by_key(|&(offset, _)| offset);
         }
-        let _ = compressed_size;
-    })
+    }
 }
 
-fn benchmark_memory_allocation() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run_sync("Memory Allocation (1KB)", || {
-        let mut data = vec![0u8; 1024];
-        // Use the data to prevent optimization
-        data[0] = 1;
-        data[1023] = 1;
-        let _ = data;
-    })
+#[cfg(unix)]
+fn monotonic_time_ns() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: ts is a valid timespec to write to
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
 }
 
-async fn benchmark_http_processing() -> BenchmarkResult {
-    let runner = BenchmarkRunner::new();
-    runner.run("HTTP Request Processing", || async {
-        let request = "GET /api/data HTTP/1.1\r\nHost: localhost\r\n\r\n";
-        let response = "HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nHello, World!";
-        
-        // Simulate request parsing
-        let _ = request.len();
-        // Simulate response generation
-        let _ = response.len();
-    }).await
+// No system-wide monotonic clock to correlate with; offsets are still relative
+// to the start of measuring
+#[cfg(not(unix))]
+fn monotonic_time_ns() -> u64 {
+    0
 }
 
-#[derive(Serialize, Deserialize)]
-struct SystemInfo {
-    rust_version: String,
-    os: String,
-    arch: String,
-    num_cpus: usize,
-    timestamp: u64,
+fn unix_time_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
 }
 
-#[derive(Serialize, Deserialize)]
-struct BenchmarkSuite {
-    system_info: SystemInfo,
-    results: Vec<BenchmarkResult>,
+fn default_measurement_name() -> String {
+    "wall".to_string()
 }
 
-fn print_system_info() {
-    println!("\n=== System Information ===");
-    println!("Rust Version: {}", env!("CARGO_PKG_VERSION"));
-    println!("OS/Arch: {}/{}", std::env::consts::OS, std::env::consts::ARCH);
-    println!("CPU Cores: {}", num_cpus::get());
-    println!("==========================");
+// Unit that nanosecond values are shown in: scaled per value by default,
+// or fixed with --units so reports of different runs diff line by line
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum DisplayUnit {
+    #[default]
+    Auto,
+    Fixed(&'static str, f64),
 }
 
-fn print_benchmark_header() {
-    println!("\n=== Rust Performance Benchmarks ===");
-    println!("====================================================================================================");
-    println!("{:<30} {:>10} {:>12} {:>12} {:>14}", "Benchmark Name", "Iterations", "Mean Time", "Median Time", "Throughput");
-    println!("----------------------------------------------------------------------------------------------------");
+impl DisplayUnit {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "auto" => Some(Self::Auto),
+            "ns" => Some(Self::Fixed("ns", 1.0)),
+            "us" | "µs" => Some(Self::Fixed("µs", 1e3)),
+            "ms" => Some(Self::Fixed("ms", 1e6)),
+            "s" => Some(Self::Fixed("s", 1e9)),
+            _ => None,
+        }
+    }
 }
 
-fn print_benchmark_footer() {
-    println!("====================================================================================================");
-    println!("\nBenchmark completed successfully.");
-    println!("Note: Results may vary based on system load and hardware configuration.");
+// Digit grouping of numbers in the terminal report (--thousands); files
+// always get plain numbers so other tools can parse them
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NumberStyle {
+    separator: Option<char>,
+    decimal: char,
 }
 
-async fn save_benchmark_results_json(results: Vec<BenchmarkResult>) {
-    let system_info = SystemInfo {
-        rust_version: env!("CARGO_PKG_VERSION").to_string(),
-        os: std::env::consts::OS.to_string(),
-        arch: std::env::consts::ARCH.to_string(),
-        num_cpus: num_cpus::get(),
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-    };
+impl Default for NumberStyle {
+    fn default() -> Self {
+        Self { separator: None, decimal: '.' }
+    }
+}
 
-    let suite = BenchmarkSuite {
-        system_info,
-        results,
-    };
+impl NumberStyle {
+    // A '.' separator makes ',' the decimal mark, as in 1.234.567,8
+    fn parse(text: &str) -> Option<Self> {
+        let separator = match text {
+            "," | "_" | "'" | "." => text.chars().next()?,
+            "space" => ' ',
+            "none" => return Some(Self::default()),
+            _ => return None,
+        };
+        Some(Self { separator: Some(separator), decimal: if separator == '.' { ',' } else { '.' } })
+    }
 
-    match serde_json::to_string_pretty(&suite) {
-        Ok(json_data) => {
-            match tokio::fs::write("rust_benchmark_results.json", json_data).await {
-                Ok(_) => println!("\nRust benchmark results saved to rust_benchmark_results.json"),
-                Err(e) => println!("Error writing JSON file: {}", e),
+    // Groups the integer digits of every number in `text`
+    fn apply(self, text: &str) -> String {
+        let Some(separator) = self.separator else {
+            return text.to_string();
+        };
+        let chars: Vec<char> = text.chars().collect();
+        let mut styled = String::with_capacity(text.len() + 8);
+        let mut i = 0;
+        while i < chars.len() {
+            if !chars[i].is_ascii_digit() {
+                styled.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            for (n, digit) in chars[start..i].iter().enumerate() {
+                if n > 0 && (i - start - n) % 3 == 0 {
+                    styled.push(separator);
+                }
+                styled.push(*digit);
+            }
+            if chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(char::is_ascii_digit) {
+                styled.push(self.decimal);
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    styled.push(chars[i]);
+                    i += 1;
+                }
             }
         }
-        Err(e) => println!("Error marshaling JSON: {}", e),
+        styled
     }
 }
 
-#[tokio::main]
-async fn main() {
-    print_system_info();
-    print_benchmark_header();
+// Set once from --thousands before anything is printed
+static NUMBER_STYLE: std::sync::OnceLock<NumberStyle> = std::sync::OnceLock::new();
 
-    let mut results = Vec::new();
+// A number formatted for the terminal report
+fn styled(text: &str) -> String {
+    NUMBER_STYLE.get().copied().unwrap_or_default().apply(text)
+}
 
-    // Core Rust benchmarks
-    results.push(benchmark_task_creation_and_execution().await);
-    results.push(benchmark_channel_ops().await);
-    results.push(benchmark_simple_computation());
-    
-    // 复杂任务基准测试 - 测试调度器能力
-    results.push(benchmark_complex_computation());
+// Set once from --no-color, NO_COLOR and whether stdout is a terminal
+static COLOR: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
 
-    // Concurrency benchmarks
-    results.push(benchmark_concurrent_tasks().await);
+#[derive(Debug, Clone, Copy)]
+enum Highlight {
+    Red,
+    Yellow,
+}
 
-    // Memory benchmarks
-    results.push(benchmark_memory_allocation());
+// `text` in the highlight's ANSI color when colors are on
+fn paint(text: &str, highlight: Highlight) -> String {
+    if !COLOR.get().copied().unwrap_or(false) {
+        return text.to_string();
+    }
+    let code = match highlight {
+        Highlight::Red => 31,
+        Highlight::Yellow => 33,
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
 
-    // Network and IO simulation benchmarks
-    results.push(benchmark_echo_server().await);
-    results.push(benchmark_concurrent_echo_clients().await);
-    results.push(benchmark_http_processing().await);
+// Set once from --units before anything is printed
+static DISPLAY_UNIT: std::sync::OnceLock<DisplayUnit> = std::sync::OnceLock::new();
 
-    // Data transfer benchmarks
-    results.push(benchmark_small_data_transfer());
-    results.push(benchmark_medium_data_transfer());
-    results.push(benchmark_large_data_transfer());
+// "1.53 µs", "2.10 ms", "3 allocs", "1.2 KiB", ... for the terminal report,
+// with --thousands grouping
+fn format_value(value: f64, unit: &str) -> String {
+    styled(&format_value_plain(value, unit))
+}
 
-    // Print summary
-    for result in &results {
-        result.print_summary();
+// format_value without digit grouping, for files
+fn format_value_plain(value: f64, unit: &str) -> String {
+    match (unit, DISPLAY_UNIT.get().copied().unwrap_or_default()) {
+        ("ns", DisplayUnit::Fixed("ns", _)) => format!("{:.1} ns", value),
+        // Down to the nanosecond whatever the unit
+        ("ns", DisplayUnit::Fixed(name, scale)) => format!("{:.*} {}", scale.log10().round() as usize, value / scale, name),
+        ("ns", DisplayUnit::Auto) => {
+            let (scaled, name) = if value.abs() >= 1e9 {
+                (value / 1e9, "s")
+            } else if value.abs() >= 1e6 {
+                (value / 1e6, "ms")
+            } else if value.abs() >= 1e3 {
+                (value / 1e3, "µs")
+            } else {
+                (value, "ns")
+            };
+            format!("{:.2} {}", scaled, name)
+        }
+        ("bytes", DisplayUnit::Auto) => format_bytes(value),
+        _ => format!("{:.0} {}", value, unit),
+    }
+}
+
+impl BenchmarkResult {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            stats: BenchmarkStats::new(),
+            iterations: 0,
+            warmup_iterations: 0,
+            warmup: None,
+            total_time_ns: 0.0,
+            error_count: 0,
+            error_rate: 0.0,
+            first_error: None,
+            status: BenchmarkStatus::Completed,
+            measurement: default_measurement_name(),
+            start_time_ns: 0,
+            windows: Vec::new(),
+            timeline: None,
+            outlier_log: None,
+            trimmed_start: 0,
+            trimmed_end: 0,
+            throughput: None,
+            drift: None,
+            sla: Vec::new(),
+            checksum: None,
+            metrics: Vec::new(),
+            stages: Vec::new(),
+            workers: None,
+            regression: None,
+            memory: None,
+            runtime: String::new(),
+            variant_of: None,
+            params: std::collections::BTreeMap::new(),
+        }
+    }
+
+    pub fn skipped(name: &str, reason: String) -> Self {
+        let mut result = Self::new(name.to_string());
+        result.status = BenchmarkStatus::Skipped(reason);
+        result
+    }
+
+    // Result for a benchmark whose setup failed before any iteration ran
+    pub fn setup_failed(name: &str, error: BenchError) -> Self {
+        let mut result = Self::new(name.to_string());
+        result.record_error(error);
+        result.error_rate = 1.0;
+        result
+    }
+
+    fn record_error(&mut self, error: BenchError) {
+        self.error_count += 1;
+        if self.first_error.is_none() {
+            self.first_error = Some(error.to_string());
+        }
+    }
+
+    fn record_output<T: Hash>(&mut self, output: &T) {
+        if std::mem::size_of::<T>() == 0 {
+            return;
+        }
+        let mut hasher = std::hash::DefaultHasher::new();
+        output.hash(&mut hasher);
+        self.checksum = Some(self.checksum.unwrap_or(0).wrapping_add(hasher.finish()));
+    }
+
+    // Closes the window for the batch that started at the given sample
+    // count, sample sum and error count
+    fn close_window(&mut self, samples_before: u64, sum_before: f64, errors_before: usize, elapsed_ns: u128) {
+        let samples = (self.stats.histogram.len() - samples_before) as usize;
+        let mean = if samples == 0 { 0.0 } else { (self.stats.histogram.sum() - sum_before) / samples as f64 };
+        self.windows.push(SampleWindow {
+            end_time_ns: self.start_time_ns + elapsed_ns as u64,
+            samples,
+            errors: self.error_count - errors_before,
+            mean,
+        });
+        if let Some(log) = &mut self.outlier_log {
+            if !self.stats.histogram.is_empty() {
+                log.update_median(self.stats.histogram.quantile(0.5));
+            }
+        }
+    }
+
+    // Folds the samples of another measurement block of the same benchmark
+    // into this one; call finish() afterwards to recompute the statistics.
+    pub fn merge(&mut self, block: BenchmarkResult) {
+        match (&mut self.outlier_log, block.outlier_log) {
+            (Some(own), Some(other)) => own.merge(other, self.stats.histogram.len()),
+            (own, other) => *own = own.take().or(other),
+        }
+        self.merge_stages(&block.stages, self.stats.histogram.len() as usize, block.stats.histogram.len() as usize);
+        match (&mut self.workers, block.workers) {
+            (Some(own), Some(other)) => own.merge(&other),
+            (own, other) => *own = own.take().or(other),
+        }
+        self.stats.merge(&block.stats);
+        self.total_time_ns += block.total_time_ns;
+        self.warmup_iterations += block.warmup_iterations;
+        if self.sla.is_empty() {
+            self.sla = block.sla;
+        }
+        match (&mut self.warmup, block.warmup) {
+            (Some(own), Some(other)) => own.histogram.merge(&other.histogram),
+            (own, other) => *own = own.take().or(other),
+        }
+        self.trimmed_start += block.trimmed_start;
+        self.trimmed_end += block.trimmed_end;
+        self.windows.extend(block.windows);
+        match (&mut self.timeline, block.timeline) {
+            (Some(own), Some(other)) => own.merge(other),
+            (own, other) => *own = own.take().or(other),
+        }
+        if let Some(checksum) = block.checksum {
+            self.checksum = Some(self.checksum.unwrap_or(0).wrapping_add(checksum));
+        }
+        for metric in block.metrics {
+            match self.metrics.iter_mut().find(|m| m.name == metric.name) {
+                Some(existing) => {
+                    existing.stats.merge(&metric.stats);
+                }
+                None => self.metrics.push(metric),
+            }
+        }
+        self.regression = match (self.regression.take(), block.regression) {
+            (Some(own), Some(other)) => Some(RegressionEstimate::fit([own.batches, other.batches].concat())),
+            (own, other) => own.or(other),
+        };
+        match (&mut self.memory, block.memory) {
+            (Some(own), Some(other)) => own.merge(&other),
+            (own, other) => *own = own.take().or(other),
+        }
+        self.error_count += block.error_count;
+        if self.first_error.is_none() {
+            self.first_error = block.first_error;
+        }
+        if let BenchmarkStatus::Skipped(_) = block.status {
+            self.status = block.status;
+        }
+    }
+
+    // Stage means weighted by the samples behind each side; the tail figures
+    // are approximated the same way
+    fn merge_stages(&mut self, stages: &[StageAttribution], own_samples: usize, block_samples: usize) {
+        if self.stages.is_empty() {
+            self.stages = stages.to_vec();
+            return;
+        }
+        let total = (own_samples + block_samples).max(1) as f64;
+        let (own, other) = (own_samples as f64 / total, block_samples as f64 / total);
+        for (stage, block_stage) in self.stages.iter_mut().zip(stages) {
+            stage.mean_ns = stage.mean_ns * own + block_stage.mean_ns * other;
+            stage.tail_mean_ns = stage.tail_mean_ns * own + block_stage.tail_mean_ns * other;
+        }
+        let mean_total: f64 = self.stages.iter().map(|s| s.mean_ns).sum();
+        let tail_total: f64 = self.stages.iter().map(|s| s.tail_mean_ns).sum();
+        for stage in &mut self.stages {
+            stage.mean_share = stage.mean_ns / mean_total;
+            stage.tail_share = stage.tail_mean_ns / tail_total;
+        }
+    }
+
+    fn finish(&mut self, config: &StatsConfig) {
+        let (trimmed_start, trimmed_end) = self.stats.trim(config.trim);
+        self.trimmed_start += trimmed_start;
+        self.trimmed_end += trimmed_end;
+        for metric in &mut self.metrics {
+            metric.stats.trim(config.trim);
+        }
+        self.iterations = self.stats.histogram.len() as usize;
+        let attempts = self.iterations + self.error_count;
+        self.error_rate = if attempts > 0 { self.error_count as f64 / attempts as f64 } else { 0.0 };
+        self.stats.calculate(config);
+        if let Some(timeline) = &mut self.timeline {
+            timeline.finish();
+        }
+        if let Some(log) = &mut self.outlier_log {
+            log.finish(self.stats.median_ns);
+        }
+        if let Some(workers) = &mut self.workers {
+            workers.calculate(config);
+        }
+        if let Some(warmup) = &mut self.warmup {
+            warmup.calculate(config);
+        }
+        self.throughput = if self.is_timed() { ThroughputStats::from_windows(&self.windows) } else { None };
+        self.drift = Drift::from_windows(&self.windows, self.stats.outliers.stddev_all_ns);
+        self.update_sla();
+        for metric in &mut self.metrics {
+            metric.stats.calculate(config);
+        }
+    }
+
+    fn is_timed(&self) -> bool {
+        self.stats.unit == "ns"
+    }
+
+    // Recomputes the fraction under each threshold from the histogram;
+    // thresholds are latencies, so other measurements get none
+    fn update_sla(&mut self) {
+        if !self.is_timed() {
+            self.sla.clear();
+        }
+        for bucket in &mut self.sla {
+            bucket.fraction = self.stats.histogram.fraction_at_or_below(bucket.threshold_ns);
+        }
+    }
+
+    // Warmup mean over steady-state mean; well above 1 means the warmup was
+    // still paying one-off costs, and if those continue past it the
+    // steady-state figures are not steady either
+    pub fn warmup_ratio(&self) -> Option<f64> {
+        let warmup = self.warmup.as_ref().filter(|warmup| !warmup.histogram.is_empty())?;
+        (self.stats.mean_ns > 0.0).then(|| warmup.mean_ns / self.stats.mean_ns)
+    }
+
+    // Red for errors or a slowdown against the baseline beyond
+    // `max_slowdown`, yellow for a CV above `max_cv`
+    pub fn print_summary(&self, max_cv: f64, baseline: &[(String, f64)], max_slowdown: f64) {
+        if let BenchmarkStatus::Skipped(reason) = &self.status {
+            println!("{:<30} {:>10}  ({})", self.name, "SKIPPED", reason);
+            return;
+        }
+        let unit = &self.stats.unit;
+        let throughput = if self.is_timed() {
+            format!("{} ops/sec", styled(&format!("{:.2}", 1e9 / self.stats.mean_ns)))
+        } else {
+            format!("{} per op", unit)
+        };
+        let margin = if self.stats.mean_ns > 0.0 { self.stats.margin_ns / self.stats.mean_ns * 100.0 } else { 0.0 };
+        let mut line = format!("{:<30} {:>10} {:>15} {:>9} {:>15} {:>22}",
+            self.name, styled(&self.iterations.to_string()),
+            format_value(self.stats.mean_ns, unit), format!("±{:.1}%", margin), format_value(self.stats.median_ns, unit), throughput);
+        let slowdown = self.slowdown(baseline).filter(|&slowdown| slowdown > max_slowdown);
+        if let Some(slowdown) = slowdown {
+            line.push_str(&format!("  [regressed {:+.1}%]", slowdown * 100.0));
+        }
+        if self.error_count > 0 {
+            line.push_str(&format!("  [{} errors, {:.2}%]", styled(&self.error_count.to_string()), self.error_rate * 100.0));
+        }
+        if self.stats.is_multimodal() {
+            line.push_str("  [multimodal]");
+        }
+        if self.stats.anomalies.count > 0 {
+            line.push_str(&format!("  [{} anomalies]", styled(&self.stats.anomalies.count.to_string())));
+        }
+        if self.drift.as_ref().is_some_and(Drift::is_significant) {
+            line.push_str("  [drifting]");
+        }
+        let highlight = if self.error_count > 0 || slowdown.is_some() {
+            Some(Highlight::Red)
+        } else if self.stats.cv > max_cv {
+            Some(Highlight::Yellow)
+        } else {
+            None
+        };
+        match highlight {
+            Some(highlight) => println!("{}", paint(&line, highlight)),
+            None => println!("{}", line),
+        }
+    }
+
+    // Relative change of the mean against the baseline's (0.1 is 10%
+    // slower); None without a baseline entry or for untimed results
+    pub fn slowdown(&self, baseline: &[(String, f64)]) -> Option<f64> {
+        let (_, baseline_mean) = baseline.iter().find(|(name, _)| *name == self.name)?;
+        (self.is_timed() && *baseline_mean > 0.0).then(|| self.stats.mean_ns / baseline_mean - 1.0)
+    }
+
+    pub fn print_detailed(&self) {
+        if self.status != BenchmarkStatus::Completed {
+            return;
+        }
+        let unit = &self.stats.unit;
+        println!("\n{} - Detailed Statistics:", self.name);
+        println!("  Measurement:   {}", self.measurement);
+        println!("  Iterations:    {}", styled(&self.iterations.to_string()));
+        println!("  Warmup Iters:  {}", styled(&self.warmup_iterations.to_string()));
+        if let (Some(warmup), Some(ratio)) = (&self.warmup, self.warmup_ratio()) {
+            println!("  Warmup Mean:   {} ({:.2}x steady state)", format_value(warmup.mean_ns, unit), ratio);
+        }
+        if self.trimmed_start + self.trimmed_end > 0 {
+            println!("  Trimmed:       {} first, {} last samples", self.trimmed_start, self.trimmed_end);
+        }
+        println!("  Precision:     {} significant digits", self.stats.histogram.significant_digits());
+        if let Some(digest) = &self.stats.digest {
+            println!("  T-Digest:      {} centroids (compression {}){}", digest.centroid_count(), digest.compression(),
+                if self.stats.digest_only { ", quantiles from the digest" } else { "" });
+        }
+        if let Some(checksum) = self.checksum {
+            println!("  Checksum:      {:016x}", checksum);
+        }
+        println!("  Mean:          {}", format_value(self.stats.mean_ns, unit));
+        println!("  Median:        {}", format_value(self.stats.median_ns, unit));
+        println!("  Min:           {}", format_value(self.stats.min_ns, unit));
+        println!("  Max:           {}", format_value(self.stats.max_ns, unit));
+        println!("  Std Dev:       {}", format_value(self.stats.stddev_ns, unit));
+        // 1.4826 * MAD estimates the standard deviation of a normal distribution
+        println!("  MAD:           {} (normal-consistent {})", format_value(self.stats.mad_ns, unit), format_value(1.4826 * self.stats.mad_ns, unit));
+        println!("  Std Error:     {} (mean ±{} at 95%)", format_value(self.stats.sem_ns, unit), format_value(self.stats.margin_ns, unit));
+        if self.stats.samples_needed > 0 {
+            println!("  Resolution:    {:.2}% between runs with {} samples ({} needed for the target)",
+                self.stats.resolvable * 100.0, self.stats.samples_used, self.stats.samples_needed);
+        }
+        println!("  CV:            {:.1}%", self.stats.cv * 100.0);
+        let symmetry = if self.stats.skewness > 1.0 {
+            "right-skewed"
+        } else if self.stats.skewness < -1.0 {
+            "left-skewed"
+        } else {
+            "roughly symmetric"
+        };
+        let tails = if self.stats.kurtosis > 3.0 { ", heavy tails" } else { "" };
+        println!("  Shape:         skewness {:.2}, excess kurtosis {:.2} ({}{})", self.stats.skewness, self.stats.kurtosis, symmetry, tails);
+        if self.stats.is_multimodal() {
+            let modes: Vec<String> = self.stats.modes.iter().map(|&mode| format_value(mode, unit)).collect();
+            println!("  Modes:         {} (multimodal, the mean is not a typical value)", modes.join(", "));
+        }
+        let outliers = &self.stats.outliers;
+        if outliers.count() > 0 {
+            println!("  Outliers:      {} ({:.1}%, {}): {} low severe, {} low mild, {} high mild, {} high severe",
+                outliers.count(), outliers.percent, outliers.method.name(),
+                outliers.low_severe, outliers.low_mild, outliers.high_mild, outliers.high_severe);
+            let (label, mean, stddev) = if outliers.excluded {
+                ("With outliers:", outliers.mean_all_ns, outliers.stddev_all_ns)
+            } else {
+                ("W/o outliers:", outliers.mean_filtered_ns, outliers.stddev_filtered_ns)
+            };
+            println!("  {:<14} mean {}, std dev {}", label, format_value(mean, unit), format_value(stddev, unit));
+        }
+        let anomalies = &self.stats.anomalies;
+        if anomalies.count > 0 {
+            println!("  Anomalies:     {} samples beyond modified z {} (max {}, z {:.1})", anomalies.count, anomalies.threshold,
+                format_value(self.stats.max_ns, unit), anomalies.max_z);
+        }
+        if let Some(winsorized) = &self.stats.winsorized {
+            println!("  Winsorized:    {:.1}% each end into [{}, {}]; raw mean {}, std dev {}", winsorized.fraction * 100.0,
+                format_value(winsorized.low_ns, unit), format_value(winsorized.high_ns, unit),
+                format_value(winsorized.raw_mean_ns, unit), format_value(winsorized.raw_stddev_ns, unit));
+        }
+        for percentile in &self.stats.percentiles {
+            let value = match percentile.value {
+                Some(value) => format_value(value, unit),
+                None => format!("n/a ({} samples, {} needed for this tail)", self.stats.histogram.len(), percentile.samples_needed),
+            };
+            println!("  {:<14} {}", format!("p{}:", percentile.percentile), value);
+        }
+        if let Some(minima) = &self.stats.batch_minima {
+            println!("  Batch Min:     mean {}, median {} ({} batches of {})", format_value(minima.mean, unit),
+                format_value(minima.median, unit), minima.batches, minima.batch_size);
+        }
+        if let Some(regression) = &self.regression {
+            println!("  Slope:         {} per iteration (intercept {}, R² {:.4}, {} batches)",
+                format_value(regression.slope, unit), format_value(regression.intercept, unit),
+                regression.r_squared, regression.batches.len());
+        }
+        if self.is_timed() {
+            println!("  Throughput:    {:.2} ops/sec", 1e9 / self.stats.mean_ns);
+        }
+        if let Some(drift) = &self.drift {
+            println!("  Drift:         {} first half, {} second half ({:+.1}%, z {:.1}){}",
+                format_value(drift.first_half_mean, unit), format_value(drift.second_half_mean, unit),
+                drift.change * 100.0, drift.z, if drift.is_significant() { ", drifting" } else { "" });
+        }
+        if !self.sla.is_empty() {
+            let buckets: Vec<String> = self.sla.iter()
+                .map(|bucket| format!("{:.2}% <= {}", bucket.fraction * 100.0, format_threshold(bucket.threshold_ns)))
+                .collect();
+            println!("  SLA:           {}", buckets.join(", "));
+        }
+        if let Some(throughput) = &self.throughput {
+            println!("  Per window:    p5 {:.2}, p50 {:.2}, p95 {:.2} ops/sec ({} windows)",
+                throughput.p5, throughput.p50, throughput.p95, throughput.windows);
+        }
+        if let Some(memory) = &self.memory {
+            if let Some(rss) = &memory.rss {
+                println!("  Peak RSS:      {} (+{} over start, mean {})", format_bytes(rss.peak_bytes as f64),
+                    format_bytes(rss.growth_bytes() as f64), format_bytes(rss.mean_bytes));
+            }
+            println!("  Peak heap:     {} (+{} over start, mean {})", format_bytes(memory.heap.peak_bytes as f64),
+                format_bytes(memory.heap.growth_bytes() as f64), format_bytes(memory.heap.mean_bytes));
+        }
+        for metric in &self.metrics {
+            println!("  {:<14} {} mean, {} median, {} p99", format!("{}:", metric.name),
+                format_value(metric.stats.mean_ns, &metric.stats.unit),
+                format_value(metric.stats.median_ns, &metric.stats.unit),
+                format_value(metric.stats.p99_ns, &metric.stats.unit));
+        }
+        if self.error_count > 0 {
+            println!("  Errors:        {} ({:.2}%)", self.error_count, self.error_rate * 100.0);
+            if let Some(error) = &self.first_error {
+                println!("  First error:   {}", error);
+            }
+        }
+    }
+
+    // The distribution as log-scaled buckets with bars scaled to the
+    // fullest one (--histogram); buckets are merged to at most
+    // HISTOGRAM_ROWS rows, and a '.' marks counts too small for a '#'
+    pub fn print_histogram(&self) {
+        if self.status != BenchmarkStatus::Completed || self.stats.histogram.is_empty() {
+            return;
+        }
+        let mut per_octave = LOG_BUCKETS_PER_OCTAVE;
+        let mut buckets = self.stats.histogram.log_buckets(per_octave);
+        while buckets.len() > HISTOGRAM_ROWS && per_octave > 1 {
+            per_octave /= 2;
+            buckets = self.stats.histogram.log_buckets(per_octave);
+        }
+        let unit = &self.stats.unit;
+        let total = self.stats.histogram.len().max(1) as f64;
+        let fullest = buckets.iter().map(|bucket| bucket.count).max().unwrap_or(1).max(1) as f64;
+        let ranges: Vec<String> = buckets.iter()
+            .map(|bucket| format!("[{}, {})", format_value(bucket.lower, unit), format_value(bucket.upper, unit)))
+            .collect();
+        let range_width = ranges.iter().map(|range| range.chars().count()).max().unwrap_or(0);
+        println!("  Histogram:");
+        for (bucket, range) in buckets.iter().zip(&ranges) {
+            let length = (bucket.count as f64 / fullest * HISTOGRAM_BAR_WIDTH as f64).round() as usize;
+            let bar = if length == 0 && bucket.count > 0 { ".".to_string() } else { "#".repeat(length) };
+            let mut marks = Vec::new();
+            if (bucket.lower..bucket.upper).contains(&self.stats.median_ns) {
+                marks.push("median");
+            }
+            if (bucket.lower..bucket.upper).contains(&self.stats.p99_ns) {
+                marks.push("p99");
+            }
+            let row = format!("    {:<range_width$} {:<bar_width$} {:>9} {:>5.1}%  {}", range, bar, bucket.count,
+                bucket.count as f64 / total * 100.0, marks.join(", "), bar_width = HISTOGRAM_BAR_WIDTH);
+            println!("{}", row.trim_end());
+        }
+    }
+}
+
+pub const DEFAULT_SEED: u64 = 42;
+
+// Deterministic SplitMix64 generator. Implemented here instead of pulling in
+// rand so the generated sequence can never change underneath old results.
+#[derive(Debug, Clone, Default)]
+pub struct BenchRng {
+    state: u64,
+}
+
+impl BenchRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // Each benchmark gets its own stream derived from the suite seed and its
+    // name (FNV-1a), so adding or reordering benchmarks doesn't shift the data.
+    pub fn for_benchmark(seed: u64, name: &str) -> Self {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in name.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        Self::new(seed ^ hash)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform value in 0..bound
+    pub fn gen_range(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+// Measurements. A measurement turns two raw readings taken around one
+// iteration into a sample value in its unit. Clocks measure "ns"; other
+// measurements count allocations, syscalls or bytes. The "minimum benchmark
+// time" bookkeeping always uses wall time regardless of the measurement.
+
+pub trait Measurement: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn unit(&self) -> &'static str;
+    // Raw reading, only the difference between two readings is meaningful
+    fn read(&self) -> u64;
+    fn delta(&self, start: u64, end: u64) -> f64;
+}
+
+pub struct WallClock {
+    origin: Instant,
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        Self { origin: Instant::now() }
+    }
+}
+
+impl Measurement for WallClock {
+    fn name(&self) -> &'static str {
+        "wall"
+    }
+
+    fn unit(&self) -> &'static str {
+        "ns"
+    }
+
+    fn read(&self) -> u64 {
+        self.origin.elapsed().as_nanos() as u64
+    }
+
+    fn delta(&self, start: u64, end: u64) -> f64 {
+        end.saturating_sub(start) as f64
+    }
+}
+
+// CPU time consumed by the calling thread. Unaffected by preemption, but
+// work done by tasks on other runtime workers is not counted, so it suits
+// sync and single-task benchmarks.
+pub struct ThreadCpuClock;
+
+impl Measurement for ThreadCpuClock {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn unit(&self) -> &'static str {
+        "ns"
+    }
+
+    fn read(&self) -> u64 {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: ts is a valid, writable timespec
+        unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+        ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+    }
+
+    fn delta(&self, start: u64, end: u64) -> f64 {
+        end.saturating_sub(start) as f64
+    }
+}
+
+// Raw time-stamp counter, converted to nanoseconds with a factor calibrated
+// against Instant at startup. Assumes an invariant TSC.
+#[cfg(target_arch = "x86_64")]
+pub struct TscClock {
+    ns_per_tick: f64,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl TscClock {
+    pub fn calibrate() -> Self {
+        let wall_start = Instant::now();
+        let tsc_start = Self::ticks();
+        while wall_start.elapsed() < std::time::Duration::from_millis(20) {
+            std::hint::spin_loop();
+        }
+        let ticks = Self::ticks() - tsc_start;
+        let wall_ns = wall_start.elapsed().as_nanos() as f64;
+        Self { ns_per_tick: wall_ns / ticks.max(1) as f64 }
+    }
+
+    fn ticks() -> u64 {
+        // SAFETY: rdtsc has no preconditions on x86_64
+        unsafe {
+            std::arch::x86_64::_rdtsc()
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Measurement for TscClock {
+    fn name(&self) -> &'static str {
+        "tsc"
+    }
+
+    fn unit(&self) -> &'static str {
+        "ns"
+    }
+
+    fn read(&self) -> u64 {
+        Self::ticks()
+    }
+
+    fn delta(&self, start: u64, end: u64) -> f64 {
+        end.saturating_sub(start) as f64 * self.ns_per_tick
+    }
+}
+
+// Counting wrapper around the system allocator. Counting only happens while
+// an allocation measurement is active, so other runs pay a single relaxed load.
+// Bytes in use are tracked separately, once memory sampling is enabled. It is
+// installed unless the counting-allocator feature (on by default) is turned
+// off, which the fingerprint records; without it allocs, alloc-bytes and
+// --memory-interval are unavailable.
+pub const COUNTING_ALLOCATOR: bool = cfg!(feature = "counting-allocator");
+
+#[cfg(feature = "counting-allocator")]
+struct CountingAllocator;
+
+static ALLOC_COUNTING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+static ALLOC_COUNT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+static ALLOC_BYTES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+static HEAP_TRACKING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+static HEAP_IN_USE: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0);
+
+#[cfg(feature = "counting-allocator")]
+impl CountingAllocator {
+    fn record(size: usize) {
+        use std::sync::atomic::Ordering::Relaxed;
+        if ALLOC_COUNTING.load(Relaxed) {
+            ALLOC_COUNT.fetch_add(1, Relaxed);
+            ALLOC_BYTES.fetch_add(size as u64, Relaxed);
+        }
+    }
+
+    fn track(delta: isize) {
+        use std::sync::atomic::Ordering::Relaxed;
+        if HEAP_TRACKING.load(Relaxed) {
+            HEAP_IN_USE.fetch_add(delta as i64, Relaxed);
+        }
+    }
+}
+
+// Heap bytes allocated and not yet freed since tracking was enabled. Blocks
+// allocated earlier and freed afterwards pull it down, hence the clamp.
+pub fn heap_in_use() -> u64 {
+    HEAP_IN_USE.load(std::sync::atomic::Ordering::Relaxed).max(0) as u64
+}
+
+// SAFETY: every call is forwarded unchanged to the system allocator
+#[cfg(feature = "counting-allocator")]
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        Self::record(layout.size());
+        Self::track(layout.size() as isize);
+        unsafe { std::alloc::System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+        Self::record(layout.size());
+        Self::track(layout.size() as isize);
+        unsafe { std::alloc::System.alloc_zeroed(layout) }
+    }
+
+    // Only growth counts as an allocation, of the bytes added: the block's
+    // old size was counted when it was allocated
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        let growth = new_size.saturating_sub(layout.size());
+        if growth > 0 {
+            Self::record(growth);
+        }
+        Self::track(new_size as isize - layout.size() as isize);
+        unsafe { std::alloc::System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        Self::track(-(layout.size() as isize));
+        unsafe { std::alloc::System.dealloc(ptr, layout) }
+    }
+}
+
+#[cfg(feature = "counting-allocator")]
+#[global_allocator]
+static GLOBAL_ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Heap allocations (count or requested bytes) made by the whole process
+// during an iteration, including runtime worker threads.
+pub struct AllocationCounter {
+    bytes: bool,
+}
+
+impl AllocationCounter {
+    pub fn new(bytes: bool) -> Self {
+        ALLOC_COUNTING.store(true, std::sync::atomic::Ordering::Relaxed);
+        Self { bytes }
+    }
+}
+
+impl Measurement for AllocationCounter {
+    fn name(&self) -> &'static str {
+        if self.bytes { "alloc-bytes" } else { "allocs" }
+    }
+
+    fn unit(&self) -> &'static str {
+        if self.bytes { "bytes" } else { "allocs" }
+    }
+
+    fn read(&self) -> u64 {
+        let counter = if self.bytes { &ALLOC_BYTES } else { &ALLOC_COUNT };
+        counter.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn delta(&self, start: u64, end: u64) -> f64 {
+        end.saturating_sub(start) as f64
+    }
+}
+
+// Read/write syscalls or bytes moved through them, from /proc/self/io. Reading
+// the file costs syscalls itself, so a baseline measured at construction is
+// subtracted from every sample. The kernel only counts read/write-family calls
+// here: socket send/recv (used by std and tokio TCP streams) do not show up,
+// while eventfd wakeups between runtime workers do.
+#[cfg(target_os = "linux")]
+pub struct ProcIoCounter {
+    bytes: bool,
+    baseline: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl ProcIoCounter {
+    pub fn new(bytes: bool) -> Self {
+        let mut counter = Self { bytes, baseline: 0 };
+        let start = counter.read();
+        let end = counter.read();
+        counter.baseline = end.saturating_sub(start);
+        counter
+    }
+
+    fn field(io: &str, key: &str) -> u64 {
+        io.lines()
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Measurement for ProcIoCounter {
+    fn name(&self) -> &'static str {
+        if self.bytes { "io-bytes" } else { "io-syscalls" }
+    }
+
+    fn unit(&self) -> &'static str {
+        if self.bytes { "bytes" } else { "syscalls" }
+    }
+
+    fn read(&self) -> u64 {
+        let io = std::fs::read_to_string("/proc/self/io").unwrap_or_default();
+        if self.bytes {
+            Self::field(&io, "rchar:") + Self::field(&io, "wchar:")
+        } else {
+            Self::field(&io, "syscr:") + Self::field(&io, "syscw:")
+        }
+    }
+
+    fn delta(&self, start: u64, end: u64) -> f64 {
+        end.saturating_sub(start).saturating_sub(self.baseline) as f64
+    }
+}
+
+// Retired user-space instructions of the calling thread, from a hardware
+// perf counter opened lazily on each thread that reads it. Like the cpu clock
+// it only sees work done on the measuring thread.
+#[cfg(target_os = "linux")]
+pub struct InstructionCounter;
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    reserved: [u64; 10],
+}
+
+#[cfg(target_os = "linux")]
+struct PerfCounterFd(libc::c_int);
+
+#[cfg(target_os = "linux")]
+impl PerfCounterFd {
+    fn open_instructions() -> std::io::Result<Self> {
+        const PERF_TYPE_HARDWARE: u32 = 0;
+        const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+        const EXCLUDE_KERNEL: u64 = 1 << 5;
+        const EXCLUDE_HV: u64 = 1 << 6;
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_HARDWARE,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config: PERF_COUNT_HW_INSTRUCTIONS,
+            sample_period: 0,
+            sample_type: 0,
+            read_format: 0,
+            flags: EXCLUDE_KERNEL | EXCLUDE_HV,
+            reserved: [0; 10],
+        };
+        // SAFETY: attr is a valid perf_event_attr for the duration of the
+        // call; pid 0 / cpu -1 counts the calling thread on any CPU
+        let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr, 0, -1, -1, 0) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self(fd as libc::c_int))
+    }
+
+    fn read(&self) -> u64 {
+        let mut count = 0u64;
+        // SAFETY: reads exactly 8 bytes into a u64
+        unsafe { libc::read(self.0, (&mut count as *mut u64).cast(), 8) };
+        count
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for PerfCounterFd {
+    fn drop(&mut self) {
+        // SAFETY: the fd is owned by this value
+        unsafe { libc::close(self.0) };
+    }
+}
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    static INSTRUCTIONS_FD: std::io::Result<PerfCounterFd> = PerfCounterFd::open_instructions();
+}
+
+#[cfg(target_os = "linux")]
+impl InstructionCounter {
+    pub fn new() -> Result<Self, String> {
+        match INSTRUCTIONS_FD.with(|fd| fd.as_ref().err().map(|e| e.to_string())) {
+            None => Ok(Self),
+            Some(error) => Err(format!(
+                "cannot open the instructions perf counter: {} (check /proc/sys/kernel/perf_event_paranoid)",
+                error
+            )),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Measurement for InstructionCounter {
+    fn name(&self) -> &'static str {
+        "instructions"
+    }
+
+    fn unit(&self) -> &'static str {
+        "instructions"
+    }
+
+    fn read(&self) -> u64 {
+        INSTRUCTIONS_FD.with(|fd| fd.as_ref().map_or(0, PerfCounterFd::read))
+    }
+
+    fn delta(&self, start: u64, end: u64) -> f64 {
+        end.saturating_sub(start) as f64
+    }
+}
+
+pub fn measurement_by_name(name: &str) -> Result<Arc<dyn Measurement>, String> {
+    match name {
+        "wall" => Ok(Arc::new(WallClock::new())),
+        "cpu" => Ok(Arc::new(ThreadCpuClock)),
+        #[cfg(target_arch = "x86_64")]
+        "tsc" => Ok(Arc::new(TscClock::calibrate())),
+        #[cfg(not(target_arch = "x86_64"))]
+        "tsc" => Err("the tsc clock is only available on x86_64".to_string()),
+        "allocs" if COUNTING_ALLOCATOR => Ok(Arc::new(AllocationCounter::new(false))),
+        "alloc-bytes" if COUNTING_ALLOCATOR => Ok(Arc::new(AllocationCounter::new(true))),
+        "allocs" | "alloc-bytes" => Err(format!("the {} measurement needs the counting-allocator feature", name)),
+        #[cfg(target_os = "linux")]
+        "instructions" => Ok(Arc::new(InstructionCounter::new()?)),
+        #[cfg(target_os = "linux")]
+        "io-syscalls" => Ok(Arc::new(ProcIoCounter::new(false))),
+        #[cfg(target_os = "linux")]
+        "io-bytes" => Ok(Arc::new(ProcIoCounter::new(true))),
+        other => Err(format!(
+            "unknown measurement '{}' (expected wall, cpu, tsc, allocs, alloc-bytes, instructions, io-syscalls or io-bytes)",
+            other
+        )),
+    }
+}
+
+// Benchmark registry
+
+type BenchFuture<'a> = Pin<Box<dyn Future<Output = BenchmarkResult> + 'a>>;
+// Send and Sync so a benchmark can be started on a thread of its own
+type BenchFn = Box<dyn for<'a> Fn(&'a BenchmarkRunner, &'a str) -> BenchFuture<'a> + Send + Sync>;
+
+pub struct BenchmarkDef {
+    pub name: String,
+    pub tags: Vec<&'static str>,
+    pub simulated_time: bool,
+    pub env: BenchEnv,
+    pub requirements: Vec<Requirement>,
+    pub hooks: Vec<EnvHook>,
+    // Registered benchmark this one repeats on a different runtime
+    pub variant_of: Option<String>,
+    // Where the benchmark sits in a parameter sweep, e.g. transport=tcp
+    pub params: Vec<(&'static str, String)>,
+    // Latency thresholds to report the share of samples under
+    pub sla: Vec<std::time::Duration>,
+    // Former names and the names of the Go/C++ counterparts, matched when
+    // reading other results files
+    pub aliases: Vec<String>,
+    // Included in the detailed statistics section
+    pub detailed: bool,
+    // Label of the throughput badge written for this benchmark by --badges
+    pub badge: Option<&'static str>,
+    func: BenchFn,
+}
+
+// Something the machine has to provide for a benchmark to be meaningful. An
+// unmet requirement turns the benchmark into a Skipped result instead of a
+// failure.
+#[derive(Debug, Clone, Copy)]
+pub enum Requirement {
+    Linux,
+    // io_uring_setup(2) works (not compiled out, disabled or seccomp-filtered)
+    IoUring,
+    // Hardware perf counters can be opened, which usually needs root or a
+    // relaxed perf_event_paranoid
+    PerfCounters,
+    MinCores(usize),
+}
+
+impl Requirement {
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Self::Linux if cfg!(target_os = "linux") => Ok(()),
+            Self::Linux => Err(format!("requires Linux (running on {})", std::env::consts::OS)),
+            #[cfg(target_os = "linux")]
+            Self::IoUring => benchmarks::IoUring::new(1).map(drop).map_err(|e| format!("io_uring unavailable: {}", e)),
+            #[cfg(target_os = "linux")]
+            Self::PerfCounters => InstructionCounter::new().map(drop),
+            #[cfg(not(target_os = "linux"))]
+            Self::IoUring | Self::PerfCounters => Err("requires Linux".to_string()),
+            Self::MinCores(cores) if num_cpus::get() >= *cores => Ok(()),
+            Self::MinCores(cores) => Err(format!("requires at least {} CPU cores (found {})", cores, num_cpus::get())),
+        }
+    }
+}
+
+// Process state a benchmark needs changed while it runs. The runner applies
+// these before the benchmark's runtime is created and reverts them in reverse
+// order afterwards; one that cannot be applied skips the benchmark.
+#[derive(Debug, Clone)]
+pub enum EnvHook {
+    // Raise the soft RLIMIT_NOFILE to at least this many descriptors
+    NoFileLimit(u64),
+    // SO_BUSY_POLL (µs) on the TCP sockets the benchmark's transports open
+    BusyPoll(u32),
+}
+
+// Busy-poll budget applied by configure_socket(); 0 leaves sockets alone
+static BUSY_POLL_US: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+enum EnvRevert {
+    Nothing,
+    #[cfg(unix)]
+    NoFileLimit(libc::rlimit),
+    BusyPoll(u32),
+}
+
+// Reverts the applied hooks when dropped
+struct AppliedEnv(Vec<EnvRevert>);
+
+impl std::fmt::Display for EnvHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoFileLimit(limit) => write!(f, "RLIMIT_NOFILE >= {}", limit),
+            Self::BusyPoll(us) => write!(f, "SO_BUSY_POLL {}µs", us),
+        }
+    }
+}
+
+impl EnvHook {
+    fn apply(&self) -> Result<EnvRevert, String> {
+        match self {
+            #[cfg(unix)]
+            Self::NoFileLimit(limit) => {
+                let mut current = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+                // SAFETY: current is a valid rlimit to write to
+                if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut current) } != 0 {
+                    return Err(format!("getrlimit failed: {}", std::io::Error::last_os_error()));
+                }
+                let wanted = *limit as libc::rlim_t;
+                if current.rlim_cur >= wanted {
+                    return Ok(EnvRevert::Nothing);
+                }
+                if current.rlim_max < wanted {
+                    return Err(format!("needs RLIMIT_NOFILE >= {} but the hard limit is {}", limit, current.rlim_max));
+                }
+                let raised = libc::rlimit { rlim_cur: wanted, rlim_max: current.rlim_max };
+                // SAFETY: raised is a valid rlimit within the hard limit
+                if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } != 0 {
+                    return Err(format!("cannot raise RLIMIT_NOFILE to {}: {}", limit, std::io::Error::last_os_error()));
+                }
+                Ok(EnvRevert::NoFileLimit(current))
+            }
+            #[cfg(not(unix))]
+            Self::NoFileLimit(_) => Err("RLIMIT_NOFILE requires a Unix platform".to_string()),
+            Self::BusyPoll(_) if !cfg!(target_os = "linux") => Err("SO_BUSY_POLL requires Linux".to_string()),
+            Self::BusyPoll(us) => Ok(EnvRevert::BusyPoll(BUSY_POLL_US.swap(*us, std::sync::atomic::Ordering::Relaxed))),
+        }
+    }
+}
+
+impl AppliedEnv {
+    fn apply(hooks: &[EnvHook]) -> Result<Self, String> {
+        let mut applied = Self(Vec::with_capacity(hooks.len()));
+        for hook in hooks {
+            // On failure, dropping `applied` reverts the hooks applied so far
+            applied.0.push(hook.apply()?);
+        }
+        Ok(applied)
+    }
+}
+
+impl Drop for AppliedEnv {
+    fn drop(&mut self) {
+        for revert in self.0.drain(..).rev() {
+            match revert {
+                EnvRevert::Nothing => {}
+                #[cfg(unix)]
+                EnvRevert::NoFileLimit(previous) => {
+                    // SAFETY: previous is the rlimit read before raising it
+                    unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &previous) };
+                }
+                EnvRevert::BusyPoll(previous) => BUSY_POLL_US.store(previous, std::sync::atomic::Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+// Environment a benchmark asks for. Anything other than the default makes the
+// benchmark run on a dedicated runtime built just for it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BenchEnv {
+    pub runtime: Option<RuntimeFlavor>,
+    // Run with tracing dispatch disabled, whatever subscriber is installed
+    pub quiet_tracing: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RuntimeFlavor {
+    CurrentThread,
+    MultiThread { worker_threads: usize },
+}
+
+impl BenchEnv {
+    fn is_default(&self) -> bool {
+        self.runtime.is_none() && !self.quiet_tracing
+    }
+
+    fn runtime_builder(&self) -> tokio::runtime::Builder {
+        let mut builder = match self.runtime {
+            Some(RuntimeFlavor::CurrentThread) => tokio::runtime::Builder::new_current_thread(),
+            Some(RuntimeFlavor::MultiThread { worker_threads }) => {
+                let mut builder = tokio::runtime::Builder::new_multi_thread();
+                builder.worker_threads(worker_threads);
+                builder
+            }
+            None => tokio::runtime::Builder::new_multi_thread(),
+        };
+        if self.quiet_tracing {
+            // Worker threads live as long as the runtime, so the thread
+            // default is never restored
+            builder.on_thread_start(|| {
+                std::mem::forget(tracing::subscriber::set_default(tracing::subscriber::NoSubscriber::default()));
+            });
+        }
+        builder.enable_all();
+        builder
+    }
+
+    // Runs `f` with tracing disabled on the calling thread if requested
+    fn scoped<R>(&self, f: impl FnOnce() -> R) -> R {
+        if self.quiet_tracing {
+            tracing::subscriber::with_default(tracing::subscriber::NoSubscriber::default(), f)
+        } else {
+            f()
+        }
+    }
+}
+
+impl BenchmarkDef {
+    pub async fn run(&self, runner: &BenchmarkRunner) -> BenchmarkResult {
+        tracing::debug!(benchmark = %self.name, "starting");
+        let started = Instant::now();
+        let profiler = runner.profiler.as_ref().filter(|profiler| profiler.selects(&self.name));
+        let session = profiler.map(|profiler| profiler.start());
+        let mut result = self.run_in_env(runner).await;
+        if let (Some(profiler), Some(session)) = (profiler, session) {
+            profiler.finish(&self.name, session);
+        }
+        match &result.status {
+            BenchmarkStatus::Completed => tracing::debug!(benchmark = %self.name, iterations = result.iterations,
+                errors = result.error_count, mean_ns = result.stats.mean_ns,
+                elapsed_ms = started.elapsed().as_millis() as u64, "finished"),
+            BenchmarkStatus::Skipped(reason) => tracing::debug!(benchmark = %self.name, reason = %reason, "skipped"),
+        }
+        result.runtime = self.runtime_label(runner);
+        result.variant_of = self.variant_of.clone();
+        result.params = self.params.iter().map(|(key, value)| (key.to_string(), value.clone())).collect();
+        result.params.insert("runtime".to_string(), result.runtime.clone());
+        if result.status == BenchmarkStatus::Completed {
+            result.sla = self.sla.iter().map(|threshold| SlaBucket { threshold_ns: threshold.as_nanos() as f64, fraction: 0.0 }).collect();
+            result.update_sla();
+        }
+        result
+    }
+
+    fn runtime_label(&self, runner: &BenchmarkRunner) -> String {
+        if runner.deterministic_seed.is_some() {
+            return "deterministic".to_string();
+        }
+        if self.simulated_time {
+            return "paused current-thread".to_string();
+        }
+        match self.env.runtime {
+            None => "multi-thread".to_string(),
+            Some(RuntimeFlavor::CurrentThread) => "current-thread".to_string(),
+            Some(RuntimeFlavor::MultiThread { worker_threads: 1 }) => "1 worker".to_string(),
+            Some(RuntimeFlavor::MultiThread { worker_threads }) => format!("{} workers", worker_threads),
+        }
+    }
+
+    async fn run_in_env(&self, runner: &BenchmarkRunner) -> BenchmarkResult {
+        if let Err(reason) = self.check_requirements() {
+            return BenchmarkResult::skipped(&self.name, reason);
+        }
+        let _applied = match AppliedEnv::apply(&self.hooks) {
+            Ok(applied) => applied,
+            Err(reason) => return BenchmarkResult::skipped(&self.name, reason),
+        };
+        if let Some(seed) = runner.deterministic_seed {
+            tracing::trace!(benchmark = %self.name, seed, "running on a deterministic executor");
+            let schedule_rng = BenchRng::for_benchmark(seed, &self.name);
+            return on_own_thread(|| self.run_with_paused_clock(runner, Some(schedule_rng)));
+        }
+        if self.simulated_time {
+            tracing::trace!(benchmark = %self.name, "running with a paused clock");
+            return on_own_thread(|| self.run_with_paused_clock(runner, None));
+        }
+        if !self.env.is_default() {
+            tracing::trace!(benchmark = %self.name, env = ?self.env, "running on a dedicated runtime");
+            return on_own_thread(|| self.run_on_dedicated_runtime(runner));
+        }
+        (self.func)(runner, &self.name).await
+    }
+
+    fn run_on_dedicated_runtime(&self, runner: &BenchmarkRunner) -> BenchmarkResult {
+        match self.env.runtime_builder().build() {
+            Ok(runtime) => self.env.scoped(|| runtime.block_on((self.func)(runner, &self.name))),
+            Err(error) => BenchmarkResult::setup_failed(&self.name, error.into()),
+        }
+    }
+
+    // Runs the benchmark on its own current-thread runtime with the tokio
+    // clock paused: whenever the runtime is idle the clock jumps to the next
+    // timer, so sleeps cost nothing and only the timer machinery is measured.
+    // With a schedule RNG, tasks started through spawn_task() are additionally
+    // polled in a seeded pseudo-random order (deterministic executor mode).
+    fn run_with_paused_clock(&self, runner: &BenchmarkRunner, schedule_rng: Option<BenchRng>) -> BenchmarkResult {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build();
+        match runtime {
+            Ok(runtime) => {
+                SCHEDULE_RNG.with(|rng| *rng.borrow_mut() = schedule_rng);
+                let result = self.env.scoped(|| runtime.block_on((self.func)(runner, &self.name)));
+                SCHEDULE_RNG.with(|rng| *rng.borrow_mut() = None);
+                result
+            }
+            Err(error) => BenchmarkResult::setup_failed(&self.name, error.into()),
+        }
+    }
+
+    // Measure under tokio's paused, auto-advancing clock
+    pub fn simulated_time(&mut self) -> &mut Self {
+        self.simulated_time = true;
+        self.tags.push("simulated-time");
+        self
+    }
+
+    // Measure on a dedicated current-thread runtime
+    pub fn current_thread(&mut self) -> &mut Self {
+        self.env.runtime = Some(RuntimeFlavor::CurrentThread);
+        self.tags.push("dedicated-runtime");
+        self
+    }
+
+    // Measure on a dedicated multi-thread runtime with this many workers
+    pub fn worker_threads(&mut self, worker_threads: usize) -> &mut Self {
+        self.env.runtime = Some(RuntimeFlavor::MultiThread { worker_threads });
+        self.tags.push("dedicated-runtime");
+        self
+    }
+
+    // Same workload as the registered benchmark `name`, on another runtime;
+    // reported as an extra column of that benchmark's row
+    pub fn variant_of(&mut self, name: &str) -> &mut Self {
+        self.variant_of = Some(name.to_string());
+        self
+    }
+
+    // Value of the sweep dimension `key` this benchmark measures
+    pub fn param(&mut self, key: &'static str, value: &str) -> &mut Self {
+        self.params.push((key, value.to_string()));
+        self
+    }
+
+    // Report the share of samples at or below each of `thresholds`
+    pub fn sla(&mut self, thresholds: &[std::time::Duration]) -> &mut Self {
+        self.sla = thresholds.to_vec();
+        self
+    }
+
+    // Also match results recorded under `name`
+    pub fn also_known_as(&mut self, name: &str) -> &mut Self {
+        self.aliases.push(name.to_string());
+        self
+    }
+
+    // Print this benchmark's detailed statistics after the run
+    pub fn detailed(&mut self) -> &mut Self {
+        self.detailed = true;
+        self
+    }
+
+    // A headline benchmark, with a badge such as "tasks/sec | 1.23M"
+    pub fn badge(&mut self, label: &'static str) -> &mut Self {
+        self.badge = Some(label);
+        self
+    }
+
+    pub fn answers_to(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|alias| alias == name)
+    }
+
+    // Measure with tracing disabled even if a subscriber is installed
+    pub fn without_tracing(&mut self) -> &mut Self {
+        self.env.quiet_tracing = true;
+        self
+    }
+
+    // Skip the benchmark unless `requirement` holds on this machine
+    pub fn requires(&mut self, requirement: Requirement) -> &mut Self {
+        self.requirements.push(requirement);
+        self
+    }
+
+    // Change the process environment while this benchmark runs
+    pub fn with_env(&mut self, hook: EnvHook) -> &mut Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    // First unmet requirement, if any
+    pub fn check_requirements(&self) -> Result<(), String> {
+        self.requirements.iter().try_for_each(Requirement::check)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+#[derive(Default)]
+pub struct BenchmarkRegistry {
+    defs: Vec<BenchmarkDef>,
+}
+
+impl BenchmarkRegistry {
+    // Benchmark registered under `name` or one of its aliases
+    pub fn resolve(&self, name: &str) -> Option<&BenchmarkDef> {
+        self.defs.iter().find(|def| def.name == name).or_else(|| self.defs.iter().find(|def| def.answers_to(name)))
+    }
+
+    // Names that collide or point at nothing, which would otherwise silently
+    // drop benchmarks from reports
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        for (i, def) in self.defs.iter().enumerate() {
+            for name in std::iter::once(&def.name).chain(&def.aliases) {
+                if let Some(other) = self.defs[..i].iter().find(|other| other.answers_to(name)) {
+                    problems.push(format!("'{}' of '{}' is already taken by '{}'", name, def.name, other.name));
+                }
+            }
+            if let Some(base) = &def.variant_of {
+                if self.defs.iter().all(|other| other.name != *base) {
+                    problems.push(format!("'{}' is a variant of '{}', which is not registered", def.name, base));
+                }
+            }
+        }
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    pub fn add_async<F>(&mut self, name: &str, tags: &[&'static str], func: F) -> &mut BenchmarkDef
+    where
+        F: AsyncFn(&BenchmarkRunner, &str) -> BenchmarkResult + Send + Sync + 'static,
+    {
+        let func = Arc::new(func);
+        self.push(name, tags, "async", Box::new(move |runner, name| {
+            let func = Arc::clone(&func);
+            Box::pin(async move { func(runner, name).await })
+        }))
+    }
+
+    pub fn add_sync<F>(&mut self, name: &str, tags: &[&'static str], func: F) -> &mut BenchmarkDef
+    where
+        F: Fn(&BenchmarkRunner, &str) -> BenchmarkResult + Send + Sync + 'static,
+    {
+        let func = Arc::new(func);
+        self.push(name, tags, "sync", Box::new(move |runner, name| {
+            let func = Arc::clone(&func);
+            Box::pin(async move { func(runner, name) })
+        }))
+    }
+
+    fn push(&mut self, name: &str, tags: &[&'static str], kind: &'static str, func: BenchFn) -> &mut BenchmarkDef {
+        let mut tags = tags.to_vec();
+        tags.push(kind);
+        self.defs.push(BenchmarkDef {
+            name: name.to_string(),
+            tags,
+            simulated_time: false,
+            env: BenchEnv::default(),
+            requirements: Vec::new(),
+            hooks: Vec::new(),
+            variant_of: None,
+            params: Vec::new(),
+            sla: Vec::new(),
+            aliases: Vec::new(),
+            detailed: false,
+            badge: None,
+            func,
+        });
+        self.defs.last_mut().unwrap()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &BenchmarkDef> {
+        self.defs.iter()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SystemInfo {
+    rust_version: String,
+    os: String,
+    arch: String,
+    num_cpus: usize,
+    timestamp: u64,
+    // Absent in files from before fingerprints were recorded
+    #[serde(default)]
+    fingerprint: Option<MachineFingerprint>,
+    // Commit the binary was built from, if it was built in a git checkout,
+    // its branch (none on a detached HEAD) and whether tracked files had
+    // uncommitted changes
+    #[serde(default)]
+    git_commit: Option<String>,
+    #[serde(default)]
+    git_branch: Option<String>,
+    #[serde(default)]
+    git_dirty: Option<bool>,
+}
+
+// Where the binary was built from, recorded by build.rs; empty outside a git
+// checkout
+const GIT_COMMIT: &str = env!("GIT_COMMIT");
+const GIT_BRANCH: &str = env!("GIT_BRANCH");
+const GIT_DIRTY: &str = env!("GIT_DIRTY");
+
+impl SystemInfo {
+    fn current(fingerprint: Option<MachineFingerprint>) -> Self {
+        let non_empty = |text: &str| Some(text.to_string()).filter(|text| !text.is_empty());
+        Self {
+            rust_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            num_cpus: num_cpus::get(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            fingerprint,
+            git_commit: non_empty(GIT_COMMIT),
+            git_branch: non_empty(GIT_BRANCH),
+            git_dirty: GIT_DIRTY.parse().ok(),
+        }
+    }
+
+    // "1a2b3c4d5e6f on main, dirty"; None without a commit
+    fn git_description(&self) -> Option<String> {
+        let commit = self.git_commit.as_deref()?;
+        let mut description = commit[..commit.len().min(12)].to_string();
+        if let Some(branch) = &self.git_branch {
+            description.push_str(&format!(" on {}", branch));
+        }
+        if self.git_dirty == Some(true) {
+            description.push_str(", dirty");
+        }
+        Some(description)
+    }
+}
+
+// --label key=value annotations describing what variant a run measured
+type Labels = std::collections::BTreeMap<String, String>;
+
+fn format_labels(labels: &Labels) -> String {
+    if labels.is_empty() {
+        return "(none)".to_string();
+    }
+    labels.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join(", ")
+}
+
+// Version of the results file layout (README, "结果JSON格式"). Adding a
+// field with a serde default keeps the version; renaming, removing or
+// changing the meaning of a field bumps it. Files from before the field
+// existed read as 0 and load unchanged, as every field added since has a
+// default; their raw `measurements` rebuild the histogram.
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct BenchmarkSuite {
+    #[serde(default)]
+    schema_version: u32,
+    system_info: SystemInfo,
+    #[serde(default)]
+    labels: Labels,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    shuffle_seed: Option<u64>,
+    #[serde(default)]
+    deterministic_seed: Option<u64>,
+    results: Vec<BenchmarkResult>,
+}
+
+const REGRESSION_BATCHES: u64 = 30;
+
+fn print_benchmark_plan(runner: &BenchmarkRunner, selected: &[&BenchmarkDef], registered: usize, plan: &TimePlan) {
+    println!("\n=== Benchmark Plan (dry run) ===");
+    println!("====================================================================================================");
+    println!("{:<30} {:<26} {:>8} {:>10} {:>10} {:>10}", "Benchmark Name", "Tags", "Warmup", "Min Iters", "Max Iters", "Min Time");
+    println!("----------------------------------------------------------------------------------------------------");
+    for def in selected {
+        let runner = plan.runner_for(runner, &def.name);
+        println!("{:<30} {:<26} {:>6}ms {:>10} {:>10} {:>8}ms",
+            def.name,
+            def.tags.join(","),
+            runner.warmup_time_ns / 1_000_000,
+            runner.min_iterations,
+            runner.max_iterations,
+            runner.min_benchmark_time_ns / 1_000_000);
+        if let Err(reason) = def.check_requirements() {
+            println!("  -> will be skipped: {}", reason);
+        }
+        if !def.hooks.is_empty() {
+            let hooks: Vec<String> = def.hooks.iter().map(EnvHook::to_string).collect();
+            println!("  -> environment: {}", hooks.join(", "));
+        }
+        if !def.sla.is_empty() {
+            let thresholds: Vec<String> = def.sla.iter().map(|threshold| format_threshold(threshold.as_nanos() as f64)).collect();
+            println!("  -> SLA thresholds: {}", thresholds.join(", "));
+        }
+        if !def.params.is_empty() {
+            let params: Vec<String> = def.params.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+            println!("  -> parameters: {}", params.join(", "));
+        }
+    }
+    println!("====================================================================================================");
+    println!("\n{} of {} registered benchmarks selected, nothing was executed.", selected.len(), registered);
+}
+
+fn print_system_info(options: &CliOptions) {
+    println!("\n=== System Information ===");
+    println!("Rust Version: {}", env!("CARGO_PKG_VERSION"));
+    println!("OS/Arch: {}/{}", std::env::consts::OS, std::env::consts::ARCH);
+    println!("CPU Model: {}", MachineFingerprint::current().cpu_model);
+    println!("CPU Cores: {}", num_cpus::get());
+    if let Some(git) = SystemInfo::current(None).git_description() {
+        println!("Git: {}", git);
+    }
+    println!("Seed: {}", options.seed);
+    if !options.labels.is_empty() {
+        println!("Labels: {}", format_labels(&options.labels));
+    }
+    if let Some(shuffle_seed) = options.shuffle_seed {
+        println!("Shuffle Seed: {}", shuffle_seed);
+    }
+    if let Some(schedule_seed) = options.deterministic_seed {
+        println!("Executor: deterministic (schedule seed {})", schedule_seed);
+    }
+    if !options.measurements.is_empty() {
+        let names: Vec<String> = options.measurements.iter()
+            .map(|measurement| format!("{} ({})", measurement.name(), measurement.unit()))
+            .collect();
+        println!("Measurement: {}", names.join(", "));
+    }
+    println!("==========================");
+}
+
+fn print_benchmark_header() {
+    println!("\n=== Rust Performance Benchmarks ===");
+    println!("====================================================================================================");
+    println!("{:<30} {:>10} {:>15} {:>9} {:>15} {:>22}", "Benchmark Name", "Iterations", "Mean", "± 95%", "Median", "Throughput");
+    println!("----------------------------------------------------------------------------------------------------");
+}
+
+// Lines up every "<workload> [tcp]" result with its "<workload> [mem]" twin
+// Completed benchmarks with completed runtime variants, each with its variants
+fn runtime_groups(results: &[BenchmarkResult]) -> Vec<(&BenchmarkResult, Vec<&BenchmarkResult>)> {
+    results
+        .iter()
+        .filter(|r| r.variant_of.is_none() && r.status == BenchmarkStatus::Completed)
+        .filter_map(|base| {
+            let variants: Vec<&BenchmarkResult> = results
+                .iter()
+                .filter(|r| r.variant_of.as_deref() == Some(base.name.as_str()) && r.status == BenchmarkStatus::Completed)
+                .collect();
+            (!variants.is_empty()).then_some((base, variants))
+        })
+        .collect()
+}
+
+// Summary rows, leaving out runtime variants that are shown side by side in
+// the runtime comparison instead
+fn print_summary_rows(results: &[BenchmarkResult], options: &CliOptions, baseline: &[(String, f64)]) {
+    let groups = runtime_groups(results);
+    let in_comparison = |result: &BenchmarkResult| {
+        groups.iter().any(|(_, variants)| variants.iter().any(|v| std::ptr::eq(*v, result)))
+    };
+    for result in results.iter().filter(|r| !in_comparison(r)) {
+        result.print_summary(options.stats_config.max_cv, baseline, options.max_slowdown);
+    }
+}
+
+// One row per benchmark with the mean on each runtime it ran on, plus how
+// much slower the worst runtime was than the best
+fn print_runtime_comparison(results: &[BenchmarkResult]) {
+    let groups = runtime_groups(results);
+    if groups.is_empty() {
+        return;
+    }
+    let mut runtimes: Vec<&str> = Vec::new();
+    for (base, variants) in &groups {
+        for result in std::iter::once(base).chain(variants) {
+            if !runtimes.contains(&result.runtime.as_str()) {
+                runtimes.push(&result.runtime);
+            }
+        }
+    }
+
+    println!("\n=== Runtime Comparison (mean) ===");
+    print!("{:<30}", "Benchmark Name");
+    for runtime in &runtimes {
+        print!(" {:>16}", runtime);
+    }
+    println!(" {:>11}", "Worst/Best");
+    println!("{}", "-".repeat(30 + runtimes.len() * 17 + 12));
+    for (base, variants) in &groups {
+        let row: Vec<&BenchmarkResult> = std::iter::once(*base).chain(variants.iter().copied()).collect();
+        print!("{:<30}", base.name);
+        for runtime in &runtimes {
+            match row.iter().find(|r| r.runtime == *runtime) {
+                Some(result) => print!(" {:>16}", format_value(result.stats.mean_ns, &result.stats.unit)),
+                None => print!(" {:>16}", "-"),
+            }
+        }
+        let means = row.iter().map(|r| r.stats.mean_ns);
+        let best = means.clone().fold(f64::INFINITY, f64::min);
+        let worst = means.fold(0.0, f64::max);
+        if best > 0.0 {
+            println!(" {:>10.2}x", worst / best);
+        } else {
+            println!(" {:>11}", "-");
+        }
+    }
+}
+
+// Cost of each way of handing a request to its handler, over calling it directly
+fn print_dispatch_comparison(results: &[BenchmarkResult]) {
+    let completed = |label: &str| {
+        results
+            .iter()
+            .find(|r| r.name == format!("Request Handling [{}]", label) && r.status == BenchmarkStatus::Completed && r.stats.mean_ns > 0.0)
+    };
+    let Some(direct) = completed("direct") else {
+        return;
+    };
+    let others: Vec<(&str, &BenchmarkResult)> = [("mpsc worker", "queue"), ("spawn per request", "spawn")]
+        .into_iter()
+        .filter_map(|(description, label)| Some((description, completed(label)?)))
+        .collect();
+    if others.is_empty() {
+        return;
+    }
+
+    println!("\n=== Request Dispatch Comparison (vs direct call, {}) ===", format_value(direct.stats.mean_ns, &direct.stats.unit));
+    println!("{:<30} {:>14} {:>14} {:>10}", "Dispatch", "Mean", "Overhead", "Slowdown");
+    println!("----------------------------------------------------------------------");
+    for (description, result) in others {
+        println!("{:<30} {:>14} {:>14} {:>9.2}x",
+            description,
+            format_value(result.stats.mean_ns, &result.stats.unit),
+            format_value(result.stats.mean_ns - direct.stats.mean_ns, &result.stats.unit),
+            result.stats.mean_ns / direct.stats.mean_ns);
+    }
+}
+
+fn print_transport_comparison(results: &[BenchmarkResult]) {
+    let completed = |name: &str| {
+        results
+            .iter()
+            .find(|r| r.name == name && r.status == BenchmarkStatus::Completed && r.stats.mean_ns > 0.0)
+    };
+    let pairs: Vec<(&str, &BenchmarkResult, &BenchmarkResult)> = results
+        .iter()
+        .filter_map(|r| {
+            let workload = r.name.strip_suffix(" [tcp]")?;
+            let loopback = completed(&r.name)?;
+            let in_memory = completed(&format!("{} [mem]", workload))?;
+            Some((workload, loopback, in_memory))
+        })
+        .collect();
+    if pairs.is_empty() {
+        return;
+    }
+
+    println!("\n=== Transport Comparison (loopback TCP vs in-memory) ===");
+    println!("{:<30} {:>14} {:>14} {:>14}", "Workload", "Loopback", "In-Memory", "Kernel Share");
+    println!("--------------------------------------------------------------------------");
+    for (workload, loopback, in_memory) in pairs {
+        let kernel_share = (loopback.stats.mean_ns - in_memory.stats.mean_ns) / loopback.stats.mean_ns * 100.0;
+        println!("{:<30} {:>14} {:>14} {:>13.1}%",
+            workload,
+            format_value(loopback.stats.mean_ns, &loopback.stats.unit),
+            format_value(in_memory.stats.mean_ns, &in_memory.stats.unit),
+            kernel_share);
+    }
+}
+
+fn print_stage_attribution(results: &[BenchmarkResult]) {
+    let staged: Vec<&BenchmarkResult> = results.iter().filter(|r| !r.stages.is_empty()).collect();
+    if staged.is_empty() {
+        return;
+    }
+
+    println!("\n=== Latency Budget Attribution ===");
+    println!("{:<30} {:>12} {:>10} {:>12} {:>10}", "Benchmark / Stage", "Mean", "% Mean", "p99 Tail", "% p99");
+    println!("--------------------------------------------------------------------------------");
+    for result in staged {
+        println!("{}", result.name);
+        for stage in &result.stages {
+            println!("  {:<28} {:>12} {:>9.1}% {:>12} {:>9.1}%",
+                stage.name, format_value(stage.mean_ns, "ns"), stage.mean_share * 100.0,
+                format_value(stage.tail_mean_ns, "ns"), stage.tail_share * 100.0);
+        }
+    }
+}
+
+// Task latency per runtime worker; a worker finishing far more than its
+// share of the tasks, or with a much higher p99, points at the scheduler
+fn print_worker_breakdown(results: &[BenchmarkResult]) {
+    let breakdowns: Vec<(&BenchmarkResult, &WorkerBreakdown)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .filter_map(|r| Some((r, r.workers.as_ref()?)))
+        .collect();
+    if breakdowns.is_empty() {
+        return;
+    }
+
+    println!("\n=== Task Latency per Worker ===");
+    println!("{:<30} {:>10} {:>8} {:>12} {:>12} {:>12}", "Benchmark / Worker", "Tasks", "Share", "Mean", "p50", "p99");
+    println!("------------------------------------------------------------------------------------------");
+    for (result, breakdown) in breakdowns {
+        let total = breakdown.tasks.histogram.len().max(1) as f64;
+        let row = |label: &str, stats: &BenchmarkStats| {
+            println!("{:<30} {:>10} {:>7.1}% {:>12} {:>12} {:>12}", label, stats.histogram.len(),
+                stats.histogram.len() as f64 / total * 100.0, format_value(stats.mean_ns, "ns"),
+                format_value(stats.median_ns, "ns"), format_value(stats.p99_ns, "ns"));
+        };
+        row(&result.name, &breakdown.tasks);
+        for (i, worker) in breakdown.workers.iter().enumerate() {
+            row(&format!("  worker {}", i), worker);
+        }
+        println!("  imbalance {:.2}x (busiest worker's tasks over an even share across {} worker{})",
+            breakdown.imbalance, breakdown.workers.len(), if breakdown.workers.len() == 1 { "" } else { "s" });
+    }
+}
+
+// Results whose coefficient of variation exceeds `max_cv` are too noisy to
+// compare against the FlowCoro or Go numbers
+fn print_multimodal_warnings(results: &[BenchmarkResult]) {
+    let multimodal: Vec<&BenchmarkResult> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed && r.stats.is_multimodal())
+        .collect();
+    if multimodal.is_empty() {
+        return;
+    }
+
+    println!("\n=== Multimodal Distributions ===");
+    for result in &multimodal {
+        let unit = &result.stats.unit;
+        let modes: Vec<String> = result.stats.modes.iter().map(|&mode| format_value(mode, unit)).collect();
+        println!("WARNING: {:<30} modes at {} (mean {})", result.name, modes.join(", "), format_value(result.stats.mean_ns, unit));
+    }
+    println!("These alternate between distinct paths (e.g. fast and slow); compare their percentiles, not their means.");
+}
+
+fn print_sla_buckets(results: &[BenchmarkResult]) {
+    let declared: Vec<&BenchmarkResult> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed && !r.sla.is_empty())
+        .collect();
+    if declared.is_empty() {
+        return;
+    }
+
+    println!("\n=== SLA Buckets (share of samples at or below) ===");
+    for result in declared {
+        let buckets: Vec<String> = result.sla.iter()
+            .map(|bucket| format!("{:>8} {:>7.3}%", format!("<={}", format_threshold(bucket.threshold_ns)), bucket.fraction * 100.0))
+            .collect();
+        println!("{:<30} {}", result.name, buckets.join("  "));
+    }
+}
+
+fn print_drift_warnings(results: &[BenchmarkResult]) {
+    let drifting: Vec<(&BenchmarkResult, &Drift)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .filter_map(|r| r.drift.as_ref().filter(|drift| drift.is_significant()).map(|drift| (r, drift)))
+        .collect();
+    if drifting.is_empty() {
+        return;
+    }
+
+    println!("\n=== Drift ===");
+    for (result, drift) in &drifting {
+        let unit = &result.stats.unit;
+        println!("WARNING: {:<30} {} -> {} from the first to the second half ({:+.1}%)",
+            result.name, format_value(drift.first_half_mean, unit), format_value(drift.second_half_mean, unit), drift.change * 100.0);
+    }
+    println!("Performance trended during the run (thermal throttling, or state accumulating across iterations); the mean depends on how long it ran.");
+}
+
+const SPARKLINE_WIDTH: usize = 48;
+const SPARKLINE_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// Mean per stretch of the run, in order: from the per-sample timeline when
+// --timestamps or --raw-samples kept one, else from the batch windows
+fn timeline_means(result: &BenchmarkResult, columns: usize) -> Vec<f64> {
+    let points: Vec<(f64, f64)> = match &result.timeline {
+        Some(timeline) if !timeline.samples.is_empty() => {
+            let mut samples = timeline.samples.clone();
+            samples.sort_by_key(|&(offset, _)| offset);
+            samples.into_iter().map(|(_, value)| (value, 1.0)).collect()
+        }
+        _ => result.windows.iter().filter(|w| w.samples > 0).map(|w| (w.mean, w.samples as f64)).collect(),
+    };
+    let per_column = points.len().div_ceil(columns.max(1)).max(1);
+    points
+        .chunks(per_column)
+        .map(|chunk| {
+            let weight: f64 = chunk.iter().map(|(_, weight)| weight).sum();
+            chunk.iter().map(|(value, weight)| value * weight).sum::<f64>() / weight
+        })
+        .collect()
+}
+
+fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let top = SPARKLINE_LEVELS.len() - 1;
+    values
+        .iter()
+        .map(|&value| {
+            let level = if max > min { ((value - min) / (max - min) * top as f64).round() as usize } else { 0 };
+            SPARKLINE_LEVELS[level.min(top)]
+        })
+        .collect()
+}
+
+// How each benchmark's samples moved over its run (--sparkline), scaled
+// from the lowest to the highest stretch, so drift shows as a slope and
+// spikes as peaks
+fn print_sparklines(results: &[BenchmarkResult]) {
+    let lines: Vec<(&BenchmarkResult, Vec<f64>)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .map(|r| (r, timeline_means(r, SPARKLINE_WIDTH)))
+        .filter(|(_, means)| means.len() > 1)
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+
+    println!("\n=== Sample Timeline ===");
+    for (result, means) in &lines {
+        let unit = &result.stats.unit;
+        let min = means.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = means.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        println!("{:<30} {:<width$} {} .. {}", result.name, sparkline(means), format_value(min, unit), format_value(max, unit),
+            width = SPARKLINE_WIDTH);
+    }
+}
+
+// Warmup mean this far from the steady-state mean, either way, is reported
+const WARMUP_RATIO_WARNING: f64 = 1.5;
+
+fn print_warmup_warnings(results: &[BenchmarkResult]) {
+    let unsettled: Vec<(&BenchmarkResult, f64)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .filter_map(|r| r.warmup_ratio().map(|ratio| (r, ratio)))
+        .filter(|&(_, ratio)| !(1.0 / WARMUP_RATIO_WARNING..=WARMUP_RATIO_WARNING).contains(&ratio))
+        .collect();
+    if unsettled.is_empty() {
+        return;
+    }
+
+    println!("\n=== Warmup vs Steady State ===");
+    for (result, ratio) in &unsettled {
+        let unit = &result.stats.unit;
+        let warmup_mean = result.warmup.as_ref().map_or(0.0, |warmup| warmup.mean_ns);
+        println!("WARNING: {:<30} warmup mean {} is {:.2}x the steady-state mean {} ({} warmup iterations)",
+            result.name, format_value(warmup_mean, unit), ratio, format_value(result.stats.mean_ns, unit), result.warmup_iterations);
+    }
+    println!("Costs that have not settled by the end of warmup may still be present in the measurement; try a longer --warmup.");
+}
+
+// Results that stopped measuring before they had enough samples to resolve a
+// difference of `resolve` against another run
+fn print_sample_size_warnings(results: &[BenchmarkResult], resolve: f64) {
+    let short: Vec<&BenchmarkResult> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed && r.stats.samples_used < r.stats.samples_needed)
+        .collect();
+    if short.is_empty() {
+        return;
+    }
+
+    println!("\n=== Sample Size Warnings (resolving {:.1}% at 95%) ===", resolve * 100.0);
+    for result in &short {
+        println!("WARNING: {:<30} {:>9} samples, {:>9} needed (resolves {:.1}%)",
+            result.name, result.stats.samples_used, result.stats.samples_needed, result.stats.resolvable * 100.0);
+    }
+    println!("{} of {} results stopped too early to detect a {:.1}% change; give them more time with --budget or accept --resolve {:.1}.",
+        short.len(), results.iter().filter(|r| r.status == BenchmarkStatus::Completed).count(), resolve * 100.0,
+        short.iter().map(|r| r.stats.resolvable * 100.0).fold(0.0, f64::max));
+}
+
+// Benchmarks with anomalous samples; with --timestamps or --raw-samples the
+// worst stored ones are shown with when they happened
+fn print_anomalies(results: &[BenchmarkResult], threshold: f64) {
+    const SHOWN: usize = 5;
+    let anomalous: Vec<&BenchmarkResult> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed && r.stats.anomalies.count > 0)
+        .collect();
+    if anomalous.is_empty() {
+        return;
+    }
+
+    println!("\n=== Anomalous Samples (modified z-score above {}) ===", threshold);
+    for result in &anomalous {
+        let (stats, unit) = (&result.stats, &result.stats.unit);
+        println!("{}: {} of {} samples, median {}, worst {} (z {:.1})", result.name, stats.anomalies.count, stats.histogram.len(),
+            format_value(stats.median_ns, unit), format_value(stats.max_ns, unit), stats.anomalies.max_z);
+        let Some(timeline) = &result.timeline else {
+            continue;
+        };
+        let mut flagged: Vec<&(u64, f64)> = timeline.samples.iter().filter(|(_, value)| stats.is_anomalous(*value)).collect();
+        flagged.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        for (offset, value) in flagged.into_iter().take(SHOWN) {
+            println!("  at +{:.3}ms {:>15}  z {:.1}", *offset as f64 / 1e6, format_value(*value, unit), stats.anomalies.z_score(stats.median_ns, *value));
+        }
+    }
+}
+
+fn print_noise_warnings(results: &[BenchmarkResult], max_cv: f64) {
+    let noisy: Vec<&BenchmarkResult> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed && r.stats.cv > max_cv)
+        .collect();
+    if noisy.is_empty() {
+        return;
+    }
+
+    println!("\n=== Noise Warnings (CV above {:.1}%) ===", max_cv * 100.0);
+    for result in &noisy {
+        let outliers = &result.stats.outliers;
+        let filtered_cv = if outliers.mean_filtered_ns > 0.0 { outliers.stddev_filtered_ns / outliers.mean_filtered_ns } else { 0.0 };
+        print!("WARNING: {:<30} CV {:>6.1}%", result.name, result.stats.cv * 100.0);
+        if !outliers.excluded && outliers.count() > 0 && filtered_cv <= max_cv {
+            print!("  ({:.1}% without outliers, see --exclude-outliers)", filtered_cv * 100.0);
+        }
+        println!();
+    }
+    println!("{} of {} results are too noisy to compare against other runtimes; rerun on a quieter machine or with more --budget.",
+        noisy.len(), results.iter().filter(|r| r.status == BenchmarkStatus::Completed).count());
+}
+
+// Slope next to the per-sample mean: a mean well above the slope means the
+// per-sample overhead is a noticeable part of what the mean measures
+fn print_regression_estimates(results: &[BenchmarkResult]) {
+    let estimates: Vec<(&BenchmarkResult, &RegressionEstimate)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .filter_map(|r| Some((r, r.regression.as_ref()?)))
+        .collect();
+    if estimates.is_empty() {
+        return;
+    }
+
+    println!("\n=== Regression Estimates (per iteration) ===");
+    println!("{:<30} {:>15} {:>15} {:>15} {:>8}", "Benchmark Name", "Slope", "Mean", "Intercept", "R²");
+    println!("--------------------------------------------------------------------------------------");
+    for (result, regression) in estimates {
+        let unit = &result.stats.unit;
+        println!("{:<30} {:>15} {:>15} {:>15} {:>8.4}",
+            result.name,
+            format_value(regression.slope, unit),
+            format_value(result.stats.mean_ns, unit),
+            format_value(regression.intercept, unit),
+            regression.r_squared);
+    }
+}
+
+fn print_batch_minima(results: &[BenchmarkResult]) {
+    let minima: Vec<(&BenchmarkResult, &BatchMinima)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .filter_map(|r| Some((r, r.stats.batch_minima.as_ref()?)))
+        .filter(|(_, minima)| minima.batches > 0)
+        .collect();
+    if minima.is_empty() {
+        return;
+    }
+
+    println!("\n=== Minimum of Batches ===");
+    println!("{:<30} {:>8} {:>15} {:>15} {:>15} {:>15}", "Benchmark Name", "Batches", "Mean", "Median", "Batch Min Mean", "Batch Min Med");
+    println!("------------------------------------------------------------------------------------------------------");
+    for (result, minima) in minima {
+        let unit = &result.stats.unit;
+        println!("{:<30} {:>8} {:>15} {:>15} {:>15} {:>15}",
+            result.name,
+            format!("{}x{}", minima.batches, minima.batch_size),
+            format_value(result.stats.mean_ns, unit),
+            format_value(result.stats.median_ns, unit),
+            format_value(minima.mean, unit),
+            format_value(minima.median, unit));
+    }
+}
+
+fn print_memory_usage(results: &[BenchmarkResult]) {
+    let usages: Vec<(&BenchmarkResult, &MemoryUsage)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .filter_map(|r| Some((r, r.memory.as_ref()?)))
+        .collect();
+    if usages.is_empty() {
+        return;
+    }
+
+    println!("\n=== Memory High-Water Marks ===");
+    println!("{:<30} {:>12} {:>12} {:>12} {:>12} {:>8}", "Benchmark Name", "Peak RSS", "RSS Growth", "Peak Heap", "Mean Heap", "Samples");
+    println!("------------------------------------------------------------------------------------------------");
+    for (result, memory) in usages {
+        let (peak_rss, rss_growth) = match &memory.rss {
+            Some(rss) => (format_bytes(rss.peak_bytes as f64), format!("+{}", format_bytes(rss.growth_bytes() as f64))),
+            None => ("n/a".to_string(), "n/a".to_string()),
+        };
+        println!("{:<30} {:>12} {:>12} {:>12} {:>12} {:>8}",
+            result.name, peak_rss, rss_growth,
+            format_bytes(memory.heap.peak_bytes as f64), format_bytes(memory.heap.mean_bytes), memory.samples);
+    }
+}
+
+fn print_outlier_logs(results: &[BenchmarkResult]) {
+    const SHOWN: usize = 5;
+    let logs: Vec<(&BenchmarkResult, &OutlierLog)> = results
+        .iter()
+        .filter(|r| r.status == BenchmarkStatus::Completed)
+        .filter_map(|r| Some((r, r.outlier_log.as_ref()?)))
+        .filter(|(_, log)| !log.samples.is_empty() || log.missed > 0)
+        .collect();
+    if logs.is_empty() {
+        return;
+    }
+
+    println!("\n=== Outlier Log ===");
+    for (result, log) in logs {
+        let unit = &result.stats.unit;
+        println!("{}: {} samples above {}x the median {}{}", result.name, log.samples.len(), log.multiple,
+            format_value(result.stats.median_ns, unit),
+            if log.missed > 0 { format!(" ({} more not logged)", log.missed) } else { String::new() });
+        let mut largest: Vec<&OutlierSample> = log.samples.iter().collect();
+        largest.sort_by(|a, b| b.value.partial_cmp(&a.value).unwrap());
+        for sample in largest.into_iter().take(SHOWN) {
+            let mut context = vec![format!("at +{:.3}ms", sample.time_ns.saturating_sub(result.start_time_ns) as f64 / 1e6)];
+            if let Some(rss) = sample.rss_bytes {
+                context.push(format!("rss {}", format_bytes(rss as f64)));
+            }
+            if let Some(heap) = sample.heap_bytes {
+                context.push(format!("heap {}", format_bytes(heap as f64)));
+            }
+            if let (Some(tasks), Some(depth)) = (sample.alive_tasks, sample.queue_depth) {
+                context.push(format!("{} tasks, queue depth {}", tasks, depth));
+            }
+            println!("  #{:<8} {:>15}  {}", sample.index, format_value(sample.value, unit), context.join(", "));
+        }
+    }
+    println!("Full logs, with CLOCK_MONOTONIC times for lining up with perf or journald, are in the results file (outlier_log).");
+}
+
+async fn print_pivot(results: &[BenchmarkResult], options: &CliOptions) {
+    let Some((rows, columns)) = &options.pivot else {
+        return;
+    };
+    let tables = pivot::build(results, rows, columns);
+    pivot::print(&tables, rows, columns);
+    for path in &options.pivot_files {
+        let body = if path.ends_with(".csv") { pivot::to_csv(&tables, rows) } else { pivot::to_html(&tables, rows, columns) };
+        match tokio::fs::write(path, body).await {
+            Ok(_) => tracing::info!(path = %path, "pivot tables saved"),
+            Err(e) => tracing::error!(path = %path, error = %e, "cannot write pivot tables"),
+        }
+    }
+}
+
+fn print_benchmark_footer() {
+    println!("====================================================================================================");
+    println!("\nBenchmark completed successfully.");
+    println!("Note: Results may vary based on system load and hardware configuration.");
+}
+
+// Histograms of files written with --tdigest are rebuilt from their digests
+async fn load_results_file(path: &str) -> Result<BenchmarkSuite, String> {
+    let json_data = tokio::fs::read_to_string(path).await.map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let mut suite: BenchmarkSuite = serde_json::from_str(&json_data).map_err(|e| format!("cannot parse {}: {}", path, e))?;
+    if suite.schema_version > SCHEMA_VERSION {
+        tracing::warn!(path = %path, schema_version = suite.schema_version, supported = SCHEMA_VERSION,
+            "results file uses a newer schema; fields this harness does not know are ignored");
+    }
+    for result in &mut suite.results {
+        result.stats.restore_from_measurements();
+        result.stats.restore_from_digest();
+        for metric in &mut result.metrics {
+            metric.stats.restore_from_digest();
+        }
+    }
+    Ok(suite)
+}
+
+// One shields.io endpoint file per headline benchmark, named after it
+async fn export_badges(results: &[BenchmarkResult], registry: &BenchmarkRegistry, baseline: &[(String, f64)], options: &CliOptions, dir: &str) {
+    if let Err(e) = tokio::fs::create_dir_all(dir).await {
+        tracing::error!(path = %dir, error = %e, "cannot create directory");
+        return;
+    }
+    let mut written = 0;
+    for result in results {
+        let Some(label) = registry.iter().find(|def| def.answers_to(&result.name)).and_then(|def| def.badge) else {
+            continue;
+        };
+        let Some(json) = badge::to_json(label, result, baseline, options.stats_config.max_cv, options.max_slowdown) else {
+            continue;
+        };
+        let path = std::path::Path::new(dir).join(format!("{}.json", file_stem(&result.name)));
+        match tokio::fs::write(&path, json).await {
+            Ok(()) => written += 1,
+            Err(e) => tracing::error!(path = %path.display(), error = %e, "cannot write badge"),
+        }
+    }
+    tracing::info!(path = %dir, badges = written, "badges saved");
+}
+
+// A distribution chart per benchmark and the suite's throughput chart
+async fn export_charts(results: &[BenchmarkResult], dir: &str) {
+    if let Err(e) = tokio::fs::create_dir_all(dir).await {
+        tracing::error!(path = %dir, error = %e, "cannot create directory");
+        return;
+    }
+    let dir = std::path::Path::new(dir);
+    let charts = results.iter()
+        .map(|result| charts::write_distribution(result, &dir.join(file_stem(&result.name))))
+        .chain([charts::write_throughput(results, &dir.join("throughput"))]);
+    let mut written = 0;
+    for chart in charts {
+        match chart {
+            Ok(drawn) => written += drawn as usize,
+            Err(e) => tracing::error!(error = %e, "cannot draw chart"),
+        }
+    }
+    tracing::info!(path = %dir.display(), charts = written, "charts saved");
+}
+
+async fn export_junit(results: &[BenchmarkResult], baseline: &[(String, f64)], options: &CliOptions, path: &str) {
+    match tokio::fs::write(path, junit::to_xml(results, baseline, options.max_slowdown)).await {
+        Ok(_) => tracing::info!(path = %path, "JUnit report saved"),
+        Err(e) => tracing::error!(path = %path, error = %e, "cannot write JUnit report"),
+    }
+}
+
+// The selected benchmarks grouped by their first tag, their category
+fn print_grafana_dashboard(registry: &BenchmarkRegistry, options: &CliOptions, source: grafana::Datasource) {
+    let mut categories: Vec<(String, Vec<String>)> = Vec::new();
+    for def in registry.iter().filter(|def| options.selects(def)) {
+        let category = def.tags.first().copied().unwrap_or("other");
+        match categories.iter_mut().find(|(name, _)| name == category) {
+            Some((_, names)) => names.push(def.name.clone()),
+            None => categories.push((category.to_string(), vec![def.name.clone()])),
+        }
+    }
+    match serde_json::to_string_pretty(&grafana::dashboard(&categories, source)) {
+        Ok(json) => println!("{}", json),
+        Err(e) => tracing::error!(error = %e, "cannot serialize dashboard"),
     }
+}
+
+// Refuses to combine results measured on different machines or builds unless
+// `force` is set, in which case the differences are only printed. Files
+// without a fingerprint cannot be checked and only get a warning.
+fn check_fingerprint(
+    reference: (&str, Option<&MachineFingerprint>),
+    other: (&str, Option<&MachineFingerprint>),
+    force: bool,
+) -> Result<(), String> {
+    let (reference_path, other_path) = (reference.0, other.0);
+    let (Some(reference), Some(other)) = (reference.1, other.1) else {
+        tracing::warn!(reference = %reference_path, other = %other_path,
+            "cannot check that the files come from the same machine (no fingerprint recorded)");
+        return Ok(());
+    };
+    let differences = reference.differences(other);
+    if differences.is_empty() {
+        return Ok(());
+    }
+    let message = format!("{} and {} were measured on different machines or builds ({})",
+        reference_path, other_path, differences.join("; "));
+    if force {
+        tracing::warn!("{} (--force)", message);
+        Ok(())
+    } else {
+        Err(format!("{}; pass --force to combine them anyway", message))
+    }
+}
+
+// Combines per-benchmark histograms across results files (keeping the order in
+// which benchmarks first appear), then removes any --subtract snapshots.
+// Percentiles of the output come from the histograms, not raw samples. All
+// files must share the first file's machine fingerprint, which the merged
+// results inherit along with its labels, which --label adds to or overrides.
+// Results recorded under an alias are merged into the benchmark's current
+// name.
+async fn merge_result_files(
+    registry: &BenchmarkRegistry,
+    options: &CliOptions,
+) -> Result<(Vec<BenchmarkResult>, Option<MachineFingerprint>, Labels), String> {
+    let mut merged: Vec<BenchmarkResult> = Vec::new();
+    let mut reference: Option<(&str, Option<MachineFingerprint>)> = None;
+    let mut labels: Option<Labels> = None;
+    for path in &options.merge_files {
+        let suite = load_results_file(path).await?;
+        match &labels {
+            Some(first) if *first != suite.labels => tracing::warn!("{} is labelled {} but {} is labelled {}",
+                options.merge_files[0], format_labels(first), path, format_labels(&suite.labels)),
+            Some(_) => {}
+            None => labels = Some(suite.labels.clone()),
+        }
+        match &reference {
+            Some((reference_path, fingerprint)) => check_fingerprint(
+                (reference_path, fingerprint.as_ref()),
+                (path, suite.system_info.fingerprint.as_ref()),
+                options.force,
+            )?,
+            None => reference = Some((path, suite.system_info.fingerprint)),
+        }
+        compare::warn_unknown_names(registry, path, suite.results.iter().map(|r| r.name.as_str()));
+        for mut result in suite.results {
+            if result.status != BenchmarkStatus::Completed {
+                continue;
+            }
+            if let Some(def) = registry.resolve(&result.name) {
+                result.name = def.name.clone();
+            }
+            match merged.iter_mut().find(|r| r.name == result.name) {
+                Some(existing) => {
+                    if existing.stats.unit != result.stats.unit {
+                        return Err(format!("{}: '{}' was measured in {} elsewhere but in {} here",
+                            path, result.name, existing.stats.unit, result.stats.unit));
+                    }
+                    existing.merge(result);
+                }
+                None => merged.push(result),
+            }
+        }
+    }
+
+    let (reference_path, fingerprint) = reference.unwrap_or_default();
+    for path in &options.subtract_files {
+        let suite = load_results_file(path).await?;
+        check_fingerprint((reference_path, fingerprint.as_ref()), (path, suite.system_info.fingerprint.as_ref()), options.force)?;
+        for result in suite.results {
+            let name = registry.resolve(&result.name).map_or(result.name.as_str(), |def| def.name.as_str());
+            let Some(existing) = merged.iter_mut().find(|r| r.name == name) else {
+                continue;
+            };
+            existing.stats.histogram.subtract(&result.stats.histogram)
+                .map_err(|e| format!("{}: '{}': {}", path, result.name, e))?;
+            if existing.stats.digest.take().is_some() {
+                tracing::warn!(benchmark = %existing.name, "t-digests cannot be subtracted, quantiles come from the rebuilt histogram");
+            }
+            if existing.stats.batch_minima.take().is_some() {
+                tracing::warn!(benchmark = %existing.name, "batch minima cannot be subtracted and were dropped");
+            }
+            existing.error_count = existing.error_count.saturating_sub(result.error_count);
+            existing.total_time_ns = (existing.total_time_ns - result.total_time_ns).max(0.0);
+        }
+    }
+
+    for result in &mut merged {
+        result.finish(&options.stats_config);
+    }
+    let mut labels = labels.unwrap_or_default();
+    labels.extend(options.labels.clone());
+    Ok((merged, fingerprint, labels))
+}
+
+// Connecting and the whole exchange are bounded, so an endpoint that does not
+// answer delays the end of the run instead of hanging it
+const HTTP_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const HTTP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+// One request with the body; anything but a 2xx answer is an error carrying
+// the status and whatever the endpoint said
+async fn send_http(method: reqwest::Method, url: &str, content_type: &str, authorization: Option<&str>, body: &str) -> Result<(), BenchError> {
+    let client = reqwest::Client::builder().connect_timeout(HTTP_CONNECT_TIMEOUT).timeout(HTTP_TIMEOUT).build()?;
+    let mut request = client.request(method, url).header(reqwest::header::CONTENT_TYPE, content_type).body(body.to_string());
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let answer = response.text().await.unwrap_or_default();
+    Err(BenchError::new(format!("endpoint answered {} {}", status, answer.trim())))
+}
+
+async fn export_profiles(profiler: &Profiler, dir: &str) {
+    if let Err(e) = tokio::fs::create_dir_all(dir).await {
+        tracing::error!(path = %dir, error = %e, "cannot create directory");
+        return;
+    }
+    let mut written = 0;
+    for (name, stacks) in profiler.take_profiles() {
+        if stacks.is_empty() {
+            continue;
+        }
+        let svg = match profile::flamegraph_svg(&name, &stacks) {
+            Ok(svg) => svg,
+            Err(e) => {
+                tracing::error!(benchmark = %name, error = %e, "cannot draw flamegraph");
+                continue;
+            }
+        };
+        let path = std::path::Path::new(dir).join(file_stem(&name));
+        let writes = [
+            (path.with_extension("folded"), profile::folded_text(&stacks)),
+            (path.with_extension("svg"), svg),
+        ];
+        let mut complete = true;
+        for (path, contents) in writes {
+            if let Err(e) = tokio::fs::write(&path, contents).await {
+                tracing::error!(path = %path.display(), error = %e, "cannot write profile");
+                complete = false;
+            }
+        }
+        written += complete as usize;
+    }
+    tracing::info!(path = %dir, benchmarks = written, "profiles saved");
+}
+
+// File name for a benchmark: lowercase alphanumerics, runs of anything else
+// collapsed to a single underscore
+fn file_stem(name: &str) -> String {
+    let mut stem = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            stem.push(c.to_ascii_lowercase());
+        } else if !stem.is_empty() && !stem.ends_with('_') {
+            stem.push('_');
+        }
+    }
+    stem.trim_end_matches('_').to_string()
+}
+
+// One .hgrm percentile spectrum per completed benchmark. Clock readings are
+// written in microseconds: the format prints a fixed number of decimals, and
+// in milliseconds most of these benchmarks would round to zero.
+async fn export_hgrm(results: &[BenchmarkResult], dir: &str) {
+    if let Err(e) = tokio::fs::create_dir_all(dir).await {
+        tracing::error!(path = %dir, error = %e, "cannot create directory");
+        return;
+    }
+    let mut written = 0;
+    for result in results.iter().filter(|r| r.status == BenchmarkStatus::Completed) {
+        let scale = if result.is_timed() { 1000.0 } else { 1.0 };
+        let path = std::path::Path::new(dir).join(format!("{}.hgrm", file_stem(&result.name)));
+        match tokio::fs::write(&path, result.stats.histogram.percentile_spectrum(scale)).await {
+            Ok(_) => written += 1,
+            Err(e) => tracing::error!(path = %path.display(), error = %e, "cannot write percentile spectrum"),
+        }
+    }
+    tracing::info!(path = %dir, benchmarks = written, "percentile spectra saved");
+}
+
+async fn export_gbench_json(results: &[BenchmarkResult], labels: &Labels, path: &str) {
+    let json = match gbench::to_json(results, gbench::Context::current(labels)) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::error!(path = %path, error = %e, "cannot serialize Google Benchmark JSON");
+            return;
+        }
+    };
+    match tokio::fs::write(path, json).await {
+        Ok(_) => tracing::info!(path = %path, "Google Benchmark JSON saved"),
+        Err(e) => tracing::error!(path = %path, error = %e, "cannot write Google Benchmark JSON"),
+    }
+}
+
+// Criterion writes every run to <name>/new and copies it to the baseline
+// being saved; both are written here the same way
+async fn export_criterion(results: &[BenchmarkResult], dir: &str, baseline: &str) {
+    let mut written = 0;
+    for result in results.iter().filter(|r| r.status == BenchmarkStatus::Completed && r.is_timed()) {
+        let files = match criterion::files(result) {
+            Ok(files) => files,
+            Err(e) => {
+                tracing::error!(benchmark = %result.name, error = %e, "cannot serialize criterion estimates");
+                continue;
+            }
+        };
+        let benchmark_dir = std::path::Path::new(dir).join(criterion::directory_name(&result.name));
+        let mut complete = true;
+        for run in ["new", baseline] {
+            let run_dir = benchmark_dir.join(run);
+            if let Err(e) = tokio::fs::create_dir_all(&run_dir).await {
+                tracing::error!(path = %run_dir.display(), error = %e, "cannot create directory");
+                complete = false;
+                continue;
+            }
+            for (file, contents) in &files {
+                if let Err(e) = tokio::fs::write(run_dir.join(file), contents).await {
+                    tracing::error!(path = %run_dir.join(file).display(), error = %e, "cannot write criterion file");
+                    complete = false;
+                }
+            }
+        }
+        written += usize::from(complete);
+    }
+    tracing::info!(path = %dir, benchmarks = written, baseline = %baseline, "criterion estimates saved");
+}
+
+async fn save_benchmark_results_json(mut results: Vec<BenchmarkResult>, fingerprint: Option<MachineFingerprint>, labels: Labels, options: &CliOptions) {
+    // Raw samples go to their own file; the results file only keeps them
+    // when --timestamps asked for that too
+    if let Some(path) = &options.raw_samples {
+        save_raw_samples(&results, path).await;
+        if !options.timestamps {
+            for result in &mut results {
+                result.timeline = None;
+            }
+        }
+    }
+    for result in &mut results {
+        result.stats.compact();
+        for metric in &mut result.metrics {
+            metric.stats.compact();
+        }
+    }
+    let system_info = SystemInfo::current(fingerprint);
+
+    let suite = BenchmarkSuite {
+        schema_version: SCHEMA_VERSION,
+        system_info,
+        labels,
+        seed: options.seed,
+        shuffle_seed: options.shuffle_seed,
+        deterministic_seed: options.deterministic_seed,
+        results,
+    };
+
+    for (format, path) in options.output_paths() {
+        match format.render(&suite) {
+            Ok(contents) => match tokio::fs::write(&path, contents).await {
+                Ok(_) => tracing::info!(path = %path, "results saved"),
+                Err(e) => tracing::error!(path = %path, error = %e, "cannot write results"),
+            },
+            Err(e) => tracing::error!(path = %path, error = %e, "cannot serialize results"),
+        }
+    }
+    if !options.templates.is_empty() {
+        render_templates(&suite, &options.templates).await;
+    }
+    if let Some(path) = &options.append_history {
+        history::append(&suite, path).await;
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &options.sqlite {
+        export_sqlite(&suite, path);
+    }
+    if let Some(path) = &options.xlsx {
+        match xlsx::to_xlsx(&suite) {
+            Ok(workbook) => match tokio::fs::write(path, workbook).await {
+                Ok(()) => tracing::info!(path = %path, "workbook saved"),
+                Err(e) => tracing::error!(path = %path, error = %e, "cannot write workbook"),
+            },
+            Err(e) => tracing::error!(path = %path, error = %e, "cannot build workbook"),
+        }
+    }
+}
+
+async fn render_templates(suite: &BenchmarkSuite, templates: &[ReportTemplate]) {
+    let context = match report::template_context(suite) {
+        Ok(context) => context,
+        Err(e) => {
+            tracing::error!(error = %e, "cannot serialize results for templates");
+            return;
+        }
+    };
+    for template in templates {
+        let rendered = match template.template.render(&context) {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                tracing::error!(template = %template.path, error = %e, "cannot render template");
+                continue;
+            }
+        };
+        match &template.output {
+            Some(path) => match tokio::fs::write(path, rendered).await {
+                Ok(()) => tracing::info!(path = %path, template = %template.path, "template rendered"),
+                Err(e) => tracing::error!(path = %path, error = %e, "cannot write rendered template"),
+            },
+            None => print!("{}", rendered),
+        }
+    }
+}
+
+// rusqlite blocks the runtime thread, which has nothing else to do once the
+// run is over
+#[cfg(feature = "sqlite")]
+fn export_sqlite(suite: &BenchmarkSuite, path: &str) {
+    match sqlite::add_run(path, suite) {
+        Ok(run_id) => tracing::info!(path = %path, run_id, benchmarks = suite.results.len(), "run added to SQLite database"),
+        Err(e) => tracing::error!(path = %path, error = %e, "cannot add run to SQLite database"),
+    }
+}
+
+async fn append_file(path: &str, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+    file.write_all(bytes).await?;
+    file.flush().await
+}
+
+// One row per sample in recording order, from the sample timelines
+async fn save_raw_samples(results: &[BenchmarkResult], path: &str) {
+    let mut csv = String::from("benchmark,sample,monotonic_ns,value,unit,anomaly\n");
+    for result in results {
+        let Some(timeline) = &result.timeline else {
+            continue;
+        };
+        let name = pivot::csv_field(&result.name);
+        for (index, (offset, value)) in timeline.samples.iter().enumerate() {
+            csv.push_str(&format!("{},{},{},{},{},{}\n", name, index, timeline.monotonic_start_ns + offset, value, result.stats.unit,
+                u8::from(result.stats.is_anomalous(*value))));
+        }
+    }
+    match tokio::fs::write(path, csv).await {
+        Ok(_) => tracing::info!(path = %path, "raw samples saved"),
+        Err(e) => tracing::error!(path = %path, error = %e, "cannot write raw samples"),
+    }
+}
+
+// Fisher-Yates with BenchRng, so an order can be replayed from its seed
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut rng = BenchRng::new(seed);
+    for i in (1..items.len()).rev() {
+        let j = rng.gen_range(i as u64 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+// Per-benchmark minimum measurement time overriding the runner's default
+#[derive(Default)]
+struct TimePlan {
+    min_time_ns: HashMap<String, u128>,
+}
+
+impl TimePlan {
+    fn runner_for(&self, runner: &BenchmarkRunner, name: &str) -> BenchmarkRunner {
+        match self.min_time_ns.get(name) {
+            Some(&min_time_ns) => runner.clone().with_min_time_ns(min_time_ns),
+            None => runner.clone(),
+        }
+    }
+}
+
+// Splits the measurement part of a suite budget across the selected
+// benchmarks in proportion to their squared coefficient of variation in the
+// previous run: the samples needed for a given relative precision grow with
+// CV², so noisy benchmarks get more time and stable ones less. Benchmarks
+// without history are weighted like the median benchmark. A fifth of the
+// budget plus all warmups are held back for setup and reporting.
+fn plan_time_budget(
+    runner: &BenchmarkRunner,
+    selected: &[&BenchmarkDef],
+    budget: std::time::Duration,
+    history: &[BenchmarkResult],
+) -> TimePlan {
+    const MIN_SHARE_NS: f64 = 10_000_000.0;
+
+    let reserved = selected.len() as f64 * runner.warmup_time_ns as f64 + budget.as_nanos() as f64 * 0.2;
+    let available = budget.as_nanos() as f64 - reserved;
+    if selected.is_empty() || available < MIN_SHARE_NS * selected.len() as f64 {
+        return TimePlan::default();
+    }
+
+    let noise: Vec<Option<f64>> = selected.iter().map(|def| {
+        history.iter()
+            .find(|r| def.answers_to(&r.name) && r.status == BenchmarkStatus::Completed && r.stats.mean_ns > 0.0)
+            .map(|r| {
+                // Prefer the outlier-free spread so one preemption spike in
+                // the last run does not claim most of the budget
+                let outliers = &r.stats.outliers;
+                let cv = if outliers.mean_filtered_ns > 0.0 {
+                    outliers.stddev_filtered_ns / outliers.mean_filtered_ns
+                } else {
+                    r.stats.stddev_ns / r.stats.mean_ns
+                };
+                cv.max(0.01)
+            })
+    }).collect();
+    let mut known: Vec<f64> = noise.iter().flatten().copied().collect();
+    if known.is_empty() {
+        return TimePlan::default();
+    }
+    known.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let typical = stats::quantile(&known, 0.5);
+
+    let weights: Vec<f64> = noise.iter().map(|cv| cv.unwrap_or(typical).powi(2)).collect();
+    let total_weight: f64 = weights.iter().sum();
+    let min_time_ns = selected.iter().zip(&weights).map(|(def, weight)| {
+        let share = (available * weight / total_weight).clamp(MIN_SHARE_NS, available / 2.0);
+        (def.name.clone(), share as u128)
+    }).collect();
+    TimePlan { min_time_ns }
+}
+
+fn budget_exhausted(budget: std::time::Duration) -> String {
+    format!("suite time budget of {:?} exhausted", budget)
+}
+
+async fn run_sequential(
+    runner: &BenchmarkRunner,
+    selected: &[&BenchmarkDef],
+    budget: Option<std::time::Duration>,
+    plan: &TimePlan,
+    stream: Option<&stream::ResultStream>,
+) -> Vec<BenchmarkResult> {
+    let suite_start = Instant::now();
+    let mut results = Vec::new();
+    for def in selected {
+        let result = match budget {
+            Some(budget) if suite_start.elapsed() >= budget => BenchmarkResult::skipped(&def.name, budget_exhausted(budget)),
+            _ => def.run(&plan.runner_for(runner, &def.name)).await,
+        };
+        stream::emit(stream, &result).await;
+        results.push(result);
+    }
+    results
+}
+
+// Alternates short measurement blocks between benchmarks so slow drift of the
+// machine (thermal state, frequency scaling, background load) is spread over
+// all of them instead of penalising whichever runs last.
+async fn run_interleaved(
+    runner: &BenchmarkRunner,
+    selected: &[&BenchmarkDef],
+    rounds: usize,
+    budget: Option<std::time::Duration>,
+    plan: &TimePlan,
+    stream: Option<&stream::ResultStream>,
+) -> Vec<BenchmarkResult> {
+    let block_runners: Vec<BenchmarkRunner> = selected.iter()
+        .map(|def| plan.runner_for(runner, &def.name).for_block(rounds))
+        .collect();
+    let suite_start = Instant::now();
+    let mut merged: Vec<Option<BenchmarkResult>> = selected.iter().map(|_| None).collect();
+    // A benchmark is finished, and streamed, after its block of the last round
+    let mut finished = vec![false; selected.len()];
+
+    'rounds: for round in 0..rounds {
+        for (((def, slot), done), block_runner) in selected.iter().zip(merged.iter_mut()).zip(finished.iter_mut()).zip(&block_runners) {
+            if budget.is_some_and(|budget| suite_start.elapsed() >= budget) {
+                break 'rounds;
+            }
+            let block = def.run(block_runner).await;
+            let result = match slot {
+                Some(result) => {
+                    result.merge(block);
+                    result
+                }
+                None => slot.insert(block),
+            };
+            if round + 1 == rounds {
+                result.finish(&runner.stats_config);
+                stream::emit(stream, result).await;
+                *done = true;
+            }
+        }
+    }
+
+    let mut results = Vec::new();
+    for ((def, slot), done) in selected.iter().zip(merged).zip(finished) {
+        let result = match (slot, budget) {
+            (Some(result), _) if done => {
+                results.push(result);
+                continue;
+            }
+            (Some(mut result), _) => {
+                result.finish(&runner.stats_config);
+                result
+            }
+            (None, Some(budget)) => BenchmarkResult::skipped(&def.name, budget_exhausted(budget)),
+            (None, None) => unreachable!("every benchmark runs at least one block without a budget"),
+        };
+        stream::emit(stream, &result).await;
+        results.push(result);
+    }
+    results
+}
+
+// The report below the table and every export of --merge and of a run alike,
+// so merged files get the same summaries as the runs they came from
+async fn report_and_export(
+    results: Vec<BenchmarkResult>,
+    fingerprint: Option<MachineFingerprint>,
+    labels: Labels,
+    registry: &BenchmarkRegistry,
+    profiler: Option<&Profiler>,
+    options: &CliOptions,
+) {
+    let baseline = compare::load_baseline(registry, options).await;
+    if options.stdout_tsv {
+        print!("{}", report::to_tsv(&results));
+    } else {
+        print_summary_rows(&results, options, &baseline);
 
-    print_benchmark_footer();
+        print_benchmark_footer();
+        print_noise_warnings(&results, options.stats_config.max_cv);
+        print_sample_size_warnings(&results, options.stats_config.resolve);
+        print_multimodal_warnings(&results);
+        print_anomalies(&results, options.stats_config.anomaly_z);
+        print_warmup_warnings(&results);
+        print_drift_warnings(&results);
+        if options.sparkline {
+            print_sparklines(&results);
+        }
+        print_regression_estimates(&results);
+        print_batch_minima(&results);
+        print_sla_buckets(&results);
+        print_memory_usage(&results);
+        print_outlier_logs(&results);
+        print_runtime_comparison(&results);
+        print_pivot(&results, options).await;
+        print_transport_comparison(&results);
+        print_dispatch_comparison(&results);
+        print_stage_attribution(&results);
+        print_worker_breakdown(&results);
+        compare::print_baseline(&results, &labels, registry, options).await;
+    }
 
     // Save JSON results
-    save_benchmark_results_json(results.clone()).await;
+    save_benchmark_results_json(results.clone(), fingerprint, labels.clone(), options).await;
+    if let Some(target) = &options.influx {
+        influx::export(&results, &labels, target).await;
+    }
+    if let Some(url) = &options.pushgateway {
+        prometheus::push(&results, &labels, options.run_id.as_deref(), url).await;
+    }
+    if let Some(dir) = &options.hgrm_dir {
+        export_hgrm(&results, dir).await;
+    }
+    if let Some(path) = &options.junit {
+        export_junit(&results, &baseline, options, path).await;
+    }
+    if let Some(dir) = &options.criterion_dir {
+        export_criterion(&results, dir, &options.criterion_baseline).await;
+    }
+    if let Some(path) = &options.gbench_json {
+        export_gbench_json(&results, &labels, path).await;
+    }
+    if let Some(dir) = &options.badges_dir {
+        export_badges(&results, registry, &baseline, options, dir).await;
+    }
+    if let Some(dir) = &options.charts_dir {
+        export_charts(&results, dir).await;
+    }
+    if let (Some(profiler), Some(dir)) = (profiler, &options.profile_dir) {
+        export_profiles(profiler, dir).await;
+    }
+
+    if options.stdout_tsv {
+        return;
+    }
 
-    // Print detailed statistics for key benchmarks
+    // Print detailed statistics for the benchmarks registered as detailed
     println!("\n=== Detailed Statistics ===");
     for result in &results {
-        if result.name == "Task Creation" ||
-           result.name == "Echo Server Simulation" ||
-           result.name == "HTTP Request Processing" ||
-           result.name.contains("Data Transfer") {
+        if registry.resolve(&result.name).is_some_and(|def| def.detailed) {
             result.print_detailed();
+            if options.histogram {
+                result.print_histogram();
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let options = CliOptions::parse();
+    logging::install(logging::level_for(options.verbosity), options.log_format);
+    DISPLAY_UNIT.set(options.display_unit).ok();
+    NUMBER_STYLE.set(options.number_style).ok();
+    COLOR.set(options.color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()).ok();
+    let mut runner = BenchmarkRunner::new()
+        .with_seed(options.seed)
+        .with_stats_config(options.stats_config.clone());
+    if let Some(warmup) = options.warmup {
+        runner = runner.with_warmup(warmup);
+    }
+    if options.progress {
+        runner = runner.with_progress(Arc::new(StderrProgress));
+    }
+    if options.regression {
+        runner = runner.with_regression(REGRESSION_BATCHES);
+    }
+    if options.task_latency {
+        runner = runner.with_task_latency();
+    }
+    if let Some(interval) = options.memory_interval {
+        runner = runner.with_memory_sampling(interval);
+    }
+    if options.timestamps || options.raw_samples.is_some() {
+        runner = runner.with_timestamps();
+    }
+    if let Some(capacity) = options.reservoir {
+        runner = runner.with_reservoir(capacity);
+    }
+    if let Some(multiple) = options.outlier_multiple {
+        runner = runner.with_outlier_log(multiple);
+    }
+    let profiler = options.profile_dir.as_ref().map(|_| Arc::new(Profiler::new(options.profile_filters.clone())));
+    if let Some(profiler) = &profiler {
+        runner = runner.with_profiler(Arc::clone(profiler));
+    }
+    if let Some(schedule_seed) = options.deterministic_seed {
+        runner = runner.with_deterministic_executor(schedule_seed);
+    }
+    if let Some((primary, extras)) = options.measurements.split_first() {
+        runner = runner.with_measurement(Arc::clone(primary));
+        for measurement in extras {
+            runner = runner.with_extra_measurement(Arc::clone(measurement));
+        }
+    }
+    if options.selftest {
+        print_system_info(&options);
+        let passed = selftest::run(&runner).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    let registry = register_benchmarks();
+    if let Err(problems) = registry.validate() {
+        for problem in problems {
+            tracing::error!("benchmark registry: {}", problem);
+        }
+        std::process::exit(1);
+    }
+    for filter in &options.filters {
+        if !registry.iter().any(|def| def.name.to_lowercase().contains(filter.as_str())) {
+            tracing::warn!(filter = %filter, "--filter matches no registered benchmark");
+        }
+    }
+    if !options.compare_files.is_empty() {
+        if let Err(message) = compare::print_comparison(&registry, &options).await {
+            tracing::error!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(source) = options.grafana {
+        print_grafana_dashboard(&registry, &options, source);
+        return;
+    }
+    if let Some((old, new)) = &options.diff_files {
+        match compare::print_diff(&registry, &options, old, new).await {
+            Ok(regressed) => std::process::exit(if regressed { 1 } else { 0 }),
+            Err(message) => {
+                tracing::error!("{}", message);
+                std::process::exit(2);
+            }
+        }
+    }
+    if !options.cross_language_files.is_empty() {
+        if let Err(message) = compare::print_cross_language(&registry, &options).await {
+            tracing::error!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut selected: Vec<&BenchmarkDef> = registry.iter().filter(|def| options.selects(def)).collect();
+    if let Some(shuffle_seed) = options.shuffle_seed {
+        shuffle(&mut selected, shuffle_seed);
+    }
+
+    let plan = match options.budget {
+        Some(budget) => {
+            let history = load_results_file(&options.history).await.ok();
+            if let Some(differences) = history.as_ref()
+                .and_then(|suite| suite.system_info.fingerprint.as_ref())
+                .map(|fingerprint| MachineFingerprint::current().differences(fingerprint))
+                .filter(|differences| !differences.is_empty())
+            {
+                tracing::warn!(path = %options.history, differences = %differences.join("; "),
+                    "history was measured on a different machine or build; its noise figures may not apply");
+            }
+            let history = history.map(|suite| suite.results).unwrap_or_default();
+            compare::warn_unknown_names(&registry, &options.history, history.iter().map(|r| r.name.as_str()));
+            plan_time_budget(&runner, &selected, budget, &history)
+        }
+        None => TimePlan::default(),
+    };
+
+    if options.dry_run {
+        print_benchmark_plan(&runner, &selected, registry.iter().count(), &plan);
+        return;
+    }
+
+    if !options.merge_files.is_empty() {
+        let (results, fingerprint, labels) = match merge_result_files(&registry, &options).await {
+            Ok(merged) => merged,
+            Err(message) => {
+                tracing::error!("{}", message);
+                std::process::exit(1);
+            }
+        };
+        if !options.stdout_tsv {
+            if !labels.is_empty() {
+                println!("Labels: {}", format_labels(&labels));
+            }
+            print_benchmark_header();
+        }
+        report_and_export(results, fingerprint, labels, &registry, None, &options).await;
+        return;
+    }
+
+    if !options.stdout_tsv {
+        print_system_info(&options);
+        if !plan.min_time_ns.is_empty() {
+            println!("Time Plan: measurement time apportioned by noise in {}", options.history);
+        }
+    }
+
+    let needs_pool = selected.iter().any(|def| def.has_tag("pooled"));
+    match SuiteFixtures::establish(needs_pool.then_some(options.pool_size)).await {
+        Ok(fixtures) => runner = runner.with_fixtures(Arc::new(fixtures)),
+        Err(error) => tracing::warn!(error = %error, "fixture setup failed, pooled benchmarks will report errors"),
+    }
+
+    let stream = match &options.stream {
+        Some(path) => stream::ResultStream::create(path).await,
+        None => None,
+    };
+
+    if !options.stdout_tsv {
+        print_benchmark_header();
+    }
+
+    let results = match options.interleave_rounds {
+        Some(rounds) => run_interleaved(&runner, &selected, rounds, options.budget, &plan, stream.as_ref()).await,
+        None => run_sequential(&runner, &selected, options.budget, &plan, stream.as_ref()).await,
+    };
+
+    report_and_export(results, Some(MachineFingerprint::current()), options.labels.clone(), &registry, profiler.as_deref(), &options).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    // A one-shot endpoint answering every request with `response`
+    async fn endpoint(response: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let _ = stream.read(&mut request).await;
+            let _ = stream.write_all(response).await;
+        });
+        format!("http://{}/write", addr)
+    }
+
+    // A completed result of `values` in ns, for the tests of every module
+    pub fn completed(name: &str, values: impl IntoIterator<Item = u64>) -> BenchmarkResult {
+        let mut result = BenchmarkResult::new(name.to_string());
+        result.stats = BenchmarkStats::with_precision(3);
+        for value in values {
+            result.stats.record(value as f64);
+        }
+        result.finish(&StatsConfig::default());
+        result
+    }
+
+    fn registry() -> BenchmarkRegistry {
+        let mut registry = BenchmarkRegistry::default();
+        registry.add_sync("Spawn", &["core"], |runner, name| runner.run_sync(name, || 1)).also_known_as("Task Spawn");
+        registry.add_sync("Channel", &["core"], |runner, name| runner.run_sync(name, || 2));
+        registry
+    }
+
+    // Results files in a directory of their own, removed when dropped
+    struct Files(std::path::PathBuf);
+
+    impl Files {
+        fn new(test: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("{}-{}", test, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
         }
+
+        fn write(&self, file: &str, results: Vec<BenchmarkResult>) -> String {
+            let suite = BenchmarkSuite {
+                schema_version: SCHEMA_VERSION,
+                system_info: SystemInfo::current(None),
+                labels: Labels::new(),
+                seed: DEFAULT_SEED,
+                shuffle_seed: None,
+                deterministic_seed: None,
+                results,
+            };
+            let path = self.0.join(file);
+            std::fs::write(&path, serde_json::to_string(&suite).unwrap()).unwrap();
+            path.display().to_string()
+        }
+    }
+
+    impl Drop for Files {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Subtracting a snapshot that was merged in leaves the other file's
+    // distribution, up to the histogram's 3 significant digits
+    #[tokio::test]
+    async fn merge_then_subtract_round_trips() {
+        let files = Files::new("merge-subtract");
+        let first = files.write("first.json", vec![completed("Spawn", 1..=100), completed("Channel", 500..=599)]);
+        let second = files.write("second.json", vec![completed("Spawn", 1000..=1199)]);
+
+        let options = CliOptions { merge_files: vec![first.clone(), second.clone()], ..CliOptions::default() };
+        let (merged, _, _) = merge_result_files(&registry(), &options).await.unwrap();
+        let names: Vec<(&str, usize)> = merged.iter().map(|r| (r.name.as_str(), r.iterations)).collect();
+        assert_eq!(names, [("Spawn", 300), ("Channel", 100)]);
+
+        let options = CliOptions { merge_files: vec![first, second.clone()], subtract_files: vec![second], ..CliOptions::default() };
+        let (merged, _, _) = merge_result_files(&registry(), &options).await.unwrap();
+        let spawn = &merged[0];
+        assert_eq!(spawn.iterations, 100);
+        assert!((spawn.stats.mean_ns - 50.5).abs() < 0.1, "{}", spawn.stats.mean_ns);
+        assert_eq!(spawn.stats.min_ns, 1.0);
+        assert!((spawn.stats.max_ns - 100.0).abs() <= 0.1, "{}", spawn.stats.max_ns);
+
+        // A snapshot larger than what was merged cannot be taken out
+        let too_many = files.write("too-many.json", vec![completed("Spawn", 1..=1000)]);
+        let options = CliOptions { merge_files: vec![files.write("small.json", vec![completed("Spawn", 1..=10)])], subtract_files: vec![too_many], ..CliOptions::default() };
+        assert!(merge_result_files(&registry(), &options).await.is_err());
+    }
+
+    // Results recorded under an alias merge into the benchmark's current name
+    #[tokio::test]
+    async fn aliases_merge_into_the_current_name() {
+        let files = Files::new("merge-aliases");
+        let old = files.write("old.json", vec![completed("Task Spawn", 1..=50)]);
+        let new = files.write("new.json", vec![completed("Spawn", 51..=100), completed("Unregistered", 1..=10)]);
+        let options = CliOptions { merge_files: vec![old, new], ..CliOptions::default() };
+        let (merged, _, _) = merge_result_files(&registry(), &options).await.unwrap();
+        let names: Vec<(&str, usize)> = merged.iter().map(|r| (r.name.as_str(), r.iterations)).collect();
+        assert_eq!(names, [("Spawn", 100), ("Unregistered", 10)]);
+        assert!((merged[0].stats.mean_ns - 50.5).abs() < 0.1);
+
+        let registry = registry();
+        assert_eq!(registry.resolve("Task Spawn").map(|def| def.name.as_str()), Some("Spawn"));
+        assert!(registry.resolve("Unregistered").is_none());
+    }
+
+    // A file written before schema_version, seeds and histograms existed
+    // (trimmed to 16 samples per result) loads, and its raw samples merge
+    #[tokio::test]
+    async fn baseline_results_files_load() {
+        let files = Files::new("baseline-load");
+        let legacy = files.0.join("baseline.json");
+        std::fs::write(&legacy, include_str!("testdata/baseline_results.json")).unwrap();
+        let legacy = legacy.display().to_string();
+
+        let suite = load_results_file(&legacy).await.unwrap();
+        assert_eq!((suite.schema_version, suite.seed), (0, 0));
+        assert!(suite.system_info.fingerprint.is_none());
+        let channel = &suite.results[1];
+        assert_eq!(channel.name, "Channel Operations");
+        assert_eq!(channel.stats.histogram.len(), 16);
+        assert_eq!(channel.stats.histogram.min(), 314.0);
+        assert_eq!(channel.stats.unit, "ns");
+        assert_eq!(channel.measurement, default_measurement_name());
+
+        let current = files.write("current.json", vec![completed("Channel Operations", 300..=383)]);
+        let options = CliOptions { merge_files: vec![legacy, current], ..CliOptions::default() };
+        let (merged, _, _) = merge_result_files(&registry(), &options).await.unwrap();
+        let names: Vec<(&str, usize)> = merged.iter().map(|r| (r.name.as_str(), r.iterations)).collect();
+        assert_eq!(names, [("Task Creation & Execution", 16), ("Channel Operations", 100), ("Concurrent Tasks (10)", 16)]);
+        assert_eq!(merged[1].stats.min_ns, 300.0);
+        assert_eq!(merged[1].stats.max_ns, 383.0);
+    }
+
+    // Shares follow the squared CV of the last run, capped at half of what
+    // is available; benchmarks without history get the median CV's share
+    #[test]
+    fn time_budget_shares_follow_noise() {
+        let registry = registry();
+        let mut extra = BenchmarkRegistry::default();
+        extra.add_sync("New", &["core"], |runner, name| runner.run_sync(name, || 3));
+        let selected: Vec<&BenchmarkDef> = registry.iter().chain(extra.iter()).collect();
+        let history_result = |name: &str, stddev: f64| {
+            let mut result = BenchmarkResult::new(name.to_string());
+            result.stats.mean_ns = 100.0;
+            result.stats.stddev_ns = stddev;
+            result
+        };
+        // Recorded under its alias in the previous run
+        let history = [history_result("Task Spawn", 20.0), history_result("Channel", 10.0)];
+        let runner = BenchmarkRunner::new();
+        let budget = std::time::Duration::from_secs(10);
+        let plan = plan_time_budget(&runner, &selected, budget, &history);
+
+        // 10 s less a fifth and three 100 ms warmups
+        let available = 7.7e9;
+        let share = |name: &str| plan.min_time_ns[name] as f64;
+        assert!((share("Spawn") - available / 2.0).abs() < 1e3);
+        assert!((share("Channel") - available * 0.01 / 0.0725).abs() < 1e3);
+        assert!((share("New") - available * 0.0225 / 0.0725).abs() < 1e3);
+        assert_eq!(plan.runner_for(&runner, "Channel").min_benchmark_time_ns, share("Channel") as u128);
+
+        // Nothing to plan from, or too little time to split
+        assert!(plan_time_budget(&runner, &selected, budget, &[]).min_time_ns.is_empty());
+        assert!(plan_time_budget(&runner, &selected, std::time::Duration::from_millis(400), &history).min_time_ns.is_empty());
+    }
+
+    // Clock reads of a few ns land on whole ticks: a MAD of one tick says
+    // nothing about the spread, so nothing is flagged, not even a stall
+    #[test]
+    fn quantized_timings_are_not_scored() {
+        let ticks = [(23, 10), (24, 25), (25, 30), (26, 25), (27, 10)];
+        let values = ticks.iter().flat_map(|&(value, count)| std::iter::repeat_n(value, count * 100));
+        let result = completed("SystemTime::now", values.chain([40, 41, 5000]));
+        assert_eq!(result.stats.mad_ns, 1.0);
+        assert_eq!(result.stats.anomalies.count, 0);
+        assert_eq!(result.stats.anomalies.scale_ns, 0.0);
+    }
+
+    // Just above the resolution the scale is floored at 5% of the median:
+    // 40% above the median is ordinary jitter, 100% a stall
+    #[test]
+    fn anomaly_scale_is_floored() {
+        let values = (0..1000).map(|i| 97 + i % 7);
+        let result = completed("Quantized", values.chain([140, 140, 200]));
+        let anomalies = &result.stats.anomalies;
+        assert_eq!(result.stats.mad_ns, 2.0);
+        assert_eq!(anomalies.scale_ns, 5.0);
+        assert_eq!(anomalies.count, 1);
+        assert_eq!(anomalies.max_z, 20.0);
+
+        // A 50 ms stall among 50 µs samples still stands out
+        let values = (0..1000).map(|i| 45_000 + i * 10);
+        let result = completed("Echo", values.chain([50_000_000]));
+        assert_eq!(result.stats.anomalies.count, 1);
+    }
+
+    #[test]
+    fn shuffle_replays_from_its_seed() {
+        let mut first: Vec<u32> = (0..20).collect();
+        let mut second = first.clone();
+        shuffle(&mut first, 7);
+        shuffle(&mut second, 7);
+        assert_eq!(first, second);
+        assert_ne!(first, (0..20).collect::<Vec<u32>>());
+        second.sort_unstable();
+        assert_eq!(second, (0..20).collect::<Vec<u32>>());
+    }
+
+    #[tokio::test]
+    async fn http_status_decides_success() {
+        let accepted = endpoint(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").await;
+        assert!(send_http(reqwest::Method::POST, &accepted, "text/plain", None, "m v=1").await.is_ok());
+
+        let refused = endpoint(b"HTTP/1.1 400 Bad Request\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nbad\xff!\r\n0\r\n\r\n").await;
+        let error = send_http(reqwest::Method::POST, &refused, "text/plain", None, "m v=1").await.unwrap_err();
+        assert!(error.to_string().starts_with("endpoint answered 400 Bad Request bad"), "{}", error);
     }
 }
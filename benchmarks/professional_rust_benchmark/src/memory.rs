@@ -0,0 +1,176 @@
+// Memory occupancy sampled from a background thread while a benchmark is
+// measured. Comparing usage before and after a run misses transient peaks,
+// such as 100k tasks that are spawned and dropped within one iteration.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Occupancy {
+    pub start_bytes: u64,
+    pub peak_bytes: u64,
+    pub mean_bytes: f64,
+}
+
+impl Occupancy {
+    fn from_samples(samples: &[u64]) -> Option<Self> {
+        let start_bytes = *samples.first()?;
+        Some(Self {
+            start_bytes,
+            peak_bytes: samples.iter().copied().max().unwrap_or(start_bytes),
+            mean_bytes: samples.iter().map(|&bytes| bytes as f64).sum::<f64>() / samples.len() as f64,
+        })
+    }
+
+    // Peak above the level at which the benchmark started
+    pub fn growth_bytes(&self) -> u64 {
+        self.peak_bytes.saturating_sub(self.start_bytes)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryUsage {
+    pub interval_ns: u64,
+    pub samples: u64,
+    // Resident set size of the process; None where it cannot be read
+    pub rss: Option<Occupancy>,
+    // Bytes handed out by the global allocator and not yet freed
+    pub heap: Occupancy,
+}
+
+impl MemoryUsage {
+    // Peaks of both runs, means weighted by their sample counts
+    pub fn merge(&mut self, other: &MemoryUsage) {
+        let total = (self.samples + other.samples).max(1) as f64;
+        let (own, theirs) = (self.samples as f64 / total, other.samples as f64 / total);
+        let combine = |a: &mut Occupancy, b: &Occupancy| {
+            a.peak_bytes = a.peak_bytes.max(b.peak_bytes);
+            a.mean_bytes = a.mean_bytes * own + b.mean_bytes * theirs;
+        };
+        combine(&mut self.heap, &other.heap);
+        match (&mut self.rss, &other.rss) {
+            (Some(rss), Some(other_rss)) => combine(rss, other_rss),
+            (rss @ None, other_rss) => *rss = other_rss.clone(),
+            (Some(_), None) => {}
+        }
+        self.samples += other.samples;
+    }
+}
+
+pub struct MemorySampler {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<MemoryUsage>,
+}
+
+impl MemorySampler {
+    // Samples once right away, then every `interval` until stopped
+    pub fn start(interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = std::thread::Builder::new()
+            .name("memory-sampler".to_string())
+            .spawn(move || sample_until(&thread_stop, interval))
+            .expect("failed to spawn memory sampler thread");
+        Self { stop, handle }
+    }
+
+    pub fn stop(self) -> MemoryUsage {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        self.handle.join().unwrap_or_default()
+    }
+}
+
+// Sample buffers are allocated up front and grown by doubling, so the
+// sampler's own allocations barely register in the heap figures
+fn sample_until(stop: &AtomicBool, interval: Duration) -> MemoryUsage {
+    let statm = StatmReader::open();
+    let mut rss = Vec::with_capacity(4096);
+    let mut heap = Vec::with_capacity(4096);
+    loop {
+        heap.push(crate::heap_in_use());
+        if let Some(bytes) = statm.as_ref().and_then(StatmReader::rss_bytes) {
+            rss.push(bytes);
+        }
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        std::thread::park_timeout(interval);
+    }
+    MemoryUsage {
+        interval_ns: interval.as_nanos() as u64,
+        samples: heap.len() as u64,
+        rss: Occupancy::from_samples(&rss),
+        heap: Occupancy::from_samples(&heap).unwrap_or_default(),
+    }
+}
+
+// Resident set size right now, for one-off readings outside the sampler;
+// None where it cannot be read
+pub fn current_rss_bytes() -> Option<u64> {
+    StatmReader::open()?.rss_bytes()
+}
+
+// /proc/self/statm kept open and re-read with pread, which avoids allocating
+// on every sample
+#[cfg(target_os = "linux")]
+struct StatmReader {
+    file: std::fs::File,
+    page_size: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl StatmReader {
+    fn open() -> Option<Self> {
+        let file = std::fs::File::open("/proc/self/statm").ok()?;
+        // SAFETY: sysconf has no preconditions
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Some(Self { file, page_size: u64::try_from(page_size).ok()? })
+    }
+
+    // Second field: resident pages
+    fn rss_bytes(&self) -> Option<u64> {
+        use std::os::unix::fs::FileExt;
+        let mut buf = [0u8; 128];
+        let len = self.file.read_at(&mut buf, 0).ok()?;
+        let text = std::str::from_utf8(&buf[..len]).ok()?;
+        let pages: u64 = text.split_whitespace().nth(1)?.parse().ok()?;
+        Some(pages * self.page_size)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct StatmReader;
+
+#[cfg(not(target_os = "linux"))]
+impl StatmReader {
+    fn open() -> Option<Self> {
+        None
+    }
+
+    fn rss_bytes(&self) -> Option<u64> {
+        None
+    }
+}
+
+// "12.3 MiB"
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
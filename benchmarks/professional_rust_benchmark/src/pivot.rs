@@ -0,0 +1,175 @@
+// Pivot tables over benchmark parameters (--pivot rows,columns). Results are
+// grouped by the values of their remaining parameters, one table per group,
+// so a sweep such as workload x transport x runtime reads as a few grids
+// instead of a long list of flat rows.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{format_value, format_value_plain, BenchmarkResult, BenchmarkStatus};
+
+pub struct PivotTable {
+    // Parameters other than the row and column ones, the same for every cell
+    pub fixed: BTreeMap<String, String>,
+    pub unit: String,
+    // In order of first appearance, which follows registration order
+    pub row_keys: Vec<String>,
+    pub column_keys: Vec<String>,
+    // Mean per (row, column)
+    cells: HashMap<(String, String), f64>,
+}
+
+impl PivotTable {
+    fn new(fixed: BTreeMap<String, String>, unit: String) -> Self {
+        Self { fixed, unit, row_keys: Vec::new(), column_keys: Vec::new(), cells: HashMap::new() }
+    }
+
+    // The first result for a cell wins
+    fn insert(&mut self, row: &str, column: &str, mean: f64) {
+        if !self.row_keys.iter().any(|key| key == row) {
+            self.row_keys.push(row.to_string());
+        }
+        if !self.column_keys.iter().any(|key| key == column) {
+            self.column_keys.push(column.to_string());
+        }
+        self.cells.entry((row.to_string(), column.to_string())).or_insert(mean);
+    }
+
+    pub fn cell(&self, row: &str, column: &str) -> Option<f64> {
+        self.cells.get(&(row.to_string(), column.to_string())).copied()
+    }
+
+    // "runtime=multi-thread, size=4KB"
+    pub fn title(&self) -> String {
+        if self.fixed.is_empty() {
+            return "all results".to_string();
+        }
+        self.fixed.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join(", ")
+    }
+}
+
+// Completed results that have both parameters, grouped into tables
+pub fn build(results: &[BenchmarkResult], rows: &str, columns: &str) -> Vec<PivotTable> {
+    let mut tables: Vec<PivotTable> = Vec::new();
+    for result in results.iter().filter(|r| r.status == BenchmarkStatus::Completed) {
+        let (Some(row), Some(column)) = (result.params.get(rows), result.params.get(columns)) else {
+            continue;
+        };
+        let fixed: BTreeMap<String, String> = result.params.iter()
+            .filter(|(key, _)| *key != rows && *key != columns)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let unit = &result.stats.unit;
+        let index = match tables.iter().position(|table| table.fixed == fixed && table.unit == *unit) {
+            Some(index) => index,
+            None => {
+                tables.push(PivotTable::new(fixed, unit.clone()));
+                tables.len() - 1
+            }
+        };
+        tables[index].insert(row, column, result.stats.mean_ns);
+    }
+    tables
+}
+
+pub fn print(tables: &[PivotTable], rows: &str, columns: &str) {
+    println!("\n=== Pivot: {} x {} (mean) ===", rows, columns);
+    if tables.is_empty() {
+        println!("No results have both a '{}' and a '{}' parameter.", rows, columns);
+        return;
+    }
+    for table in tables {
+        println!("\n{}", table.title());
+        let width = table.row_keys.iter().map(|key| key.len()).chain([rows.len()]).max().unwrap_or(0);
+        print!("{:<width$}", rows, width = width);
+        for column in &table.column_keys {
+            print!(" {:>15}", column);
+        }
+        println!();
+        for row in &table.row_keys {
+            print!("{:<width$}", row, width = width);
+            for column in &table.column_keys {
+                let cell = table.cell(row, column).map_or("-".to_string(), |mean| format_value(mean, &table.unit));
+                print!(" {:>15}", cell);
+            }
+            println!();
+        }
+    }
+}
+
+// One header for all tables: the fixed parameters, the row parameter, then
+// every column value seen in any table. Cells hold the mean in the table's
+// unit, empty where a combination was not measured.
+pub fn to_csv(tables: &[PivotTable], rows: &str) -> String {
+    let mut fixed_keys: Vec<&String> = Vec::new();
+    let mut column_keys: Vec<&String> = Vec::new();
+    for table in tables {
+        for key in table.fixed.keys() {
+            if !fixed_keys.contains(&key) {
+                fixed_keys.push(key);
+            }
+        }
+        for key in &table.column_keys {
+            if !column_keys.contains(&key) {
+                column_keys.push(key);
+            }
+        }
+    }
+
+    let mut header: Vec<String> = fixed_keys.iter().map(|key| csv_field(key)).collect();
+    header.push(csv_field(rows));
+    header.push("unit".to_string());
+    header.extend(column_keys.iter().map(|key| csv_field(key)));
+    let mut csv = header.join(",") + "\n";
+    for table in tables {
+        for row in &table.row_keys {
+            let mut fields: Vec<String> = fixed_keys.iter()
+                .map(|key| table.fixed.get(*key).map_or(String::new(), |value| csv_field(value)))
+                .collect();
+            fields.push(csv_field(row));
+            fields.push(csv_field(&table.unit));
+            fields.extend(column_keys.iter().map(|column| table.cell(row, column).map_or(String::new(), |mean| format!("{:.1}", mean))));
+            csv.push_str(&(fields.join(",") + "\n"));
+        }
+    }
+    csv
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// A standalone page with one table per group
+pub fn to_html(tables: &[PivotTable], rows: &str, columns: &str) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{} x {}</title>\n", escape_html(rows), escape_html(columns)));
+    html.push_str("<style>\nbody { font-family: sans-serif; }\ntable { border-collapse: collapse; margin-bottom: 2em; }\n\
+        th, td { border: 1px solid #ccc; padding: 4px 10px; }\ntd { text-align: right; font-variant-numeric: tabular-nums; }\n\
+        th { background: #f0f0f0; }\n</style>\n</head>\n<body>\n");
+    html.push_str(&format!("<h1>Mean by {} x {}</h1>\n", escape_html(rows), escape_html(columns)));
+    for table in tables {
+        html.push_str(&format!("<h2>{}</h2>\n<table>\n<tr><th>{} \\ {}</th>", escape_html(&table.title()), escape_html(rows), escape_html(columns)));
+        for column in &table.column_keys {
+            html.push_str(&format!("<th>{}</th>", escape_html(column)));
+        }
+        html.push_str("</tr>\n");
+        for row in &table.row_keys {
+            html.push_str(&format!("<tr><th>{}</th>", escape_html(row)));
+            for column in &table.column_keys {
+                let cell = table.cell(row, column).map_or("-".to_string(), |mean| format_value_plain(mean, &table.unit));
+                html.push_str(&format!("<td>{}</td>", escape_html(&cell)));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+pub fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,175 @@
+// CPU profiles of benchmarks while they run (--profile, with the profiling
+// feature), to explain where the time goes when a scenario is slower than on
+// FlowCoro. pprof samples every thread's call stack up to 999 times per CPU
+// second from a SIGPROF timer and symbolizes the stacks afterwards; they are
+// written per benchmark as folded stacks (<name>.folded, for inferno,
+// speedscope and flamegraph.pl) and as a flamegraph SVG (<name>.svg) drawn
+// by inferno. Samples landing in libc, libgcc, pthread or the vDSO are left
+// out, as unwinding there from a signal handler can deadlock. Inlined
+// functions do not show up as frames of their own, and the signals cost a
+// few microseconds each, so profile in a run separate from the one whose
+// numbers count.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+// Whether --profile works in this build
+pub const SUPPORTED: bool = cfg!(all(feature = "profiling", unix));
+
+// Stack (thread name, then the frames root first, joined by ';') -> samples
+pub type Folded = HashMap<String, u64>;
+
+pub struct Profiler {
+    // Lowercase name fragments of the benchmarks to profile; empty for all
+    filters: Vec<String>,
+    profiles: Mutex<BTreeMap<String, Folded>>,
+}
+
+// A running capture, from Profiler::start until Profiler::finish
+pub struct Session(Option<sampler::Guard>);
+
+impl Profiler {
+    pub fn new(filters: Vec<String>) -> Self {
+        Self { filters, profiles: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn selects(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.filters.is_empty() || self.filters.iter().any(|filter| name.contains(filter.as_str()))
+    }
+
+    pub fn start(&self) -> Session {
+        match sampler::start() {
+            Ok(guard) => Session(Some(guard)),
+            Err(e) => {
+                tracing::warn!(error = %e, "cannot start the profiler");
+                Session(None)
+            }
+        }
+    }
+
+    // Stops sampling and adds the samples to the benchmark's profile, so the
+    // blocks of an interleaved run end up in one profile
+    pub fn finish(&self, name: &str, session: Session) {
+        let Some(guard) = session.0 else {
+            return;
+        };
+        let stacks = match sampler::stop(guard) {
+            Ok(stacks) => stacks,
+            Err(e) => {
+                tracing::warn!(benchmark = %name, error = %e, "cannot read the profile");
+                return;
+            }
+        };
+        let mut profiles = self.profiles.lock().unwrap();
+        let profile = profiles.entry(name.to_string()).or_default();
+        for (stack, count) in stacks {
+            *profile.entry(stack).or_default() += count;
+        }
+    }
+
+    pub fn take_profiles(&self) -> BTreeMap<String, Folded> {
+        std::mem::take(&mut *self.profiles.lock().unwrap())
+    }
+}
+
+pub fn folded_text(stacks: &Folded) -> String {
+    let mut lines: Vec<String> = stacks.iter().map(|(stack, count)| format!("{} {}", stack, count)).collect();
+    lines.sort_unstable();
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(all(feature = "profiling", unix))]
+pub fn flamegraph_svg(title: &str, stacks: &Folded) -> Result<String, String> {
+    use pprof::flamegraph::{from_lines, Options};
+
+    let lines = folded_text(stacks);
+    let mut options = Options::default();
+    options.title = title.to_string();
+    options.count_name = "samples".to_string();
+    let mut svg = Vec::new();
+    from_lines(&mut options, lines.lines(), &mut svg).map_err(|e| e.to_string())?;
+    String::from_utf8(svg).map_err(|e| e.to_string())
+}
+
+#[cfg(not(all(feature = "profiling", unix)))]
+pub fn flamegraph_svg(_title: &str, _stacks: &Folded) -> Result<String, String> {
+    Err("built without the profiling feature".to_string())
+}
+
+#[cfg(all(feature = "profiling", unix))]
+mod sampler {
+    use std::fmt::Write;
+
+    use super::Folded;
+
+    // Off 1 kHz, so sampling does not run in lock step with periodic work
+    const FREQUENCY_HZ: i32 = 999;
+    // Libraries whose frames the unwinder must not be started from
+    const BLOCKLIST: [&str; 4] = ["libc", "libgcc", "pthread", "vdso"];
+
+    pub struct Guard(pprof::ProfilerGuard<'static>);
+
+    pub fn start() -> pprof::Result<Guard> {
+        pprof::ProfilerGuardBuilder::default().frequency(FREQUENCY_HZ).blocklist(&BLOCKLIST).build().map(Guard)
+    }
+
+    pub fn stop(guard: Guard) -> pprof::Result<Folded> {
+        let report = guard.0.report().build()?;
+        let mut stacks = Folded::new();
+        for (frames, &count) in &report.data {
+            let mut stack = frames.thread_name_or_id();
+            for frame in frames.frames.iter().rev() {
+                for symbol in frame.iter().rev() {
+                    let _ = write!(stack, ";{}", symbol);
+                }
+            }
+            *stacks.entry(stack).or_default() += count.max(0) as u64;
+        }
+        Ok(stacks)
+    }
+}
+
+#[cfg(not(all(feature = "profiling", unix)))]
+mod sampler {
+    use super::Folded;
+
+    pub struct Guard;
+
+    pub fn start() -> Result<Guard, String> {
+        Err("built without the profiling feature".to_string())
+    }
+
+    pub fn stop(_guard: Guard) -> Result<Folded, String> {
+        Ok(Folded::new())
+    }
+}
+
+#[cfg(all(test, feature = "profiling", unix))]
+mod tests {
+    use super::*;
+
+    // A spinning loop shows up under this test's thread, and the flamegraph
+    // carries the title and the frames
+    #[test]
+    fn busy_loop_is_profiled() {
+        let profiler = Profiler::new(vec!["spin".to_string()]);
+        assert!(profiler.selects("Busy Spin") && !profiler.selects("Channel"));
+        let session = profiler.start();
+        let started = std::time::Instant::now();
+        let mut x = 0u64;
+        while started.elapsed() < std::time::Duration::from_millis(300) {
+            x = std::hint::black_box(x.wrapping_mul(6364136223846793005).wrapping_add(1));
+        }
+        profiler.finish("Busy Spin", session);
+        let profiles = profiler.take_profiles();
+        let stacks = &profiles["Busy Spin"];
+        assert!(stacks.values().sum::<u64>() > 0);
+        assert!(stacks.keys().any(|stack| stack.contains("busy_loop_is_profiled")), "{:?}", stacks.keys().collect::<Vec<_>>());
+
+        let svg = flamegraph_svg("Busy Spin", stacks).unwrap();
+        assert!(svg.contains("Busy Spin") && svg.contains("busy_loop_is_profiled"));
+        assert!(folded_text(stacks).lines().all(|line| line.rsplit_once(' ').is_some_and(|(_, count)| count.parse::<u64>().is_ok())));
+    }
+}
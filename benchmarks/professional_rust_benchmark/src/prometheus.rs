@@ -0,0 +1,138 @@
+// Final figures in Prometheus' text exposition format, pushed to a
+// pushgateway (--pushgateway) so benchmark results land next to the rest of
+// our monitoring. Every run is its own group under job
+// professional_rust_benchmark with run_id and commit as the grouping key,
+// which Prometheus attaches to each series; --label annotations become
+// labels too. Times are in seconds, as Prometheus names them; measurements
+// other than time keep their unit in a label.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{BenchmarkResult, BenchmarkStatus, Labels, GIT_COMMIT};
+
+pub const JOB: &str = "professional_rust_benchmark";
+
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    samples: Vec<String>,
+}
+
+impl Family {
+    fn new(name: &'static str, help: &'static str) -> Self {
+        Self { name, help, samples: Vec::new() }
+    }
+
+    fn push(&mut self, labels: &str, value: f64) {
+        if value.is_finite() {
+            self.samples.push(format!("{}{{{}}} {}", self.name, labels, value));
+        }
+    }
+}
+
+pub fn to_exposition(results: &[BenchmarkResult], labels: &Labels) -> String {
+    let mut mean_seconds = Family::new("benchmark_mean_seconds", "Mean time per iteration");
+    let mut p99_seconds = Family::new("benchmark_p99_seconds", "99th percentile time per iteration");
+    let mut throughput = Family::new("benchmark_throughput_per_second", "Iterations per second at the mean time");
+    let mut mean = Family::new("benchmark_mean", "Mean per iteration of a measurement other than time");
+    let mut p99 = Family::new("benchmark_p99", "99th percentile per iteration of a measurement other than time");
+    let mut iterations = Family::new("benchmark_iterations", "Iterations measured");
+    let mut errors = Family::new("benchmark_errors", "Iterations that failed");
+
+    let common: String = labels.iter().map(|(key, value)| format!(",{}=\"{}\"", label_name(key), escape(value))).collect();
+    for result in results.iter().filter(|r| r.status == BenchmarkStatus::Completed && r.iterations > 0) {
+        let stats = &result.stats;
+        let series = format!("benchmark=\"{}\"{}", escape(&result.name), common);
+        if result.is_timed() {
+            mean_seconds.push(&series, stats.mean_ns / 1e9);
+            p99_seconds.push(&series, stats.p99_ns / 1e9);
+            if stats.mean_ns > 0.0 {
+                throughput.push(&series, 1e9 / stats.mean_ns);
+            }
+        } else {
+            let series = format!("{},measurement=\"{}\",unit=\"{}\"", series, escape(&result.measurement), escape(&stats.unit));
+            mean.push(&series, stats.mean_ns);
+            p99.push(&series, stats.p99_ns);
+        }
+        iterations.push(&series, result.iterations as f64);
+        errors.push(&series, result.error_count as f64);
+    }
+
+    let mut text = String::new();
+    for family in [mean_seconds, p99_seconds, throughput, mean, p99, iterations, errors] {
+        if family.samples.is_empty() {
+            continue;
+        }
+        text.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", family.name, family.help, family.name));
+        for sample in family.samples {
+            text.push_str(&sample);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+// Pushgateway path of the group for one run; `run_id` and `commit` are
+// path segments, so they must not contain '/'
+fn group_path(run_id: &str, commit: &str) -> String {
+    format!("/metrics/job/{}/run_id/{}/commit/{}", JOB, run_id, commit)
+}
+
+// Replaces this run's group on the pushgateway (PUT), so pushing again under
+// the same run id does not leave stale series behind. Without --run-id the
+// run is identified by the current Unix time.
+pub async fn push(results: &[BenchmarkResult], labels: &Labels, run_id: Option<&str>, url: &str) {
+    let run_id = run_id.map_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default().to_string()
+    }, str::to_string);
+    let commit = Some(GIT_COMMIT).filter(|commit| !commit.is_empty()).unwrap_or("unknown");
+    let target = format!("{}{}", url.trim_end_matches('/'), group_path(&run_id, commit));
+    let body = to_exposition(results, labels);
+    match crate::send_http(reqwest::Method::PUT, &target, "text/plain; version=0.0.4", None, &body).await {
+        Ok(()) => tracing::info!(target = %target, run_id = %run_id, "metrics pushed"),
+        Err(e) => tracing::error!(target = %target, error = %e, "cannot push metrics"),
+    }
+}
+
+// Label names allow [a-zA-Z_][a-zA-Z0-9_]*
+fn label_name(key: &str) -> String {
+    let mut name: String = key.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::completed;
+
+    #[test]
+    fn families_in_exposition_format() {
+        let mut allocs = completed("Alloc \"1KB\"", 2..=2);
+        allocs.measurement = "allocs".to_string();
+        allocs.stats.unit = "allocs".to_string();
+        let results = [completed("Spawn", 1000..=1000), allocs, BenchmarkResult::skipped("Perf", "no perf".to_string())];
+        let labels = Labels::from([("git-branch".to_string(), "main".to_string())]);
+        let text = to_exposition(&results, &labels);
+        let expected = [
+            "# HELP benchmark_mean_seconds Mean time per iteration",
+            "# TYPE benchmark_mean_seconds gauge",
+            "benchmark_mean_seconds{benchmark=\"Spawn\",git_branch=\"main\"} 0.000001",
+            "benchmark_throughput_per_second{benchmark=\"Spawn\",git_branch=\"main\"} 1000000",
+            "benchmark_mean{benchmark=\"Alloc \\\"1KB\\\"\",git_branch=\"main\",measurement=\"allocs\",unit=\"allocs\"} 2",
+            "benchmark_iterations{benchmark=\"Spawn\",git_branch=\"main\"} 1",
+        ];
+        for line in expected {
+            assert!(text.lines().any(|l| l == line), "missing {}\n{}", line, text);
+        }
+        assert_eq!(text.matches("# TYPE benchmark_iterations gauge").count(), 1);
+        assert!(!text.contains("Perf"));
+        assert_eq!(group_path("42", "abc123"), "/metrics/job/professional_rust_benchmark/run_id/42/commit/abc123");
+    }
+}
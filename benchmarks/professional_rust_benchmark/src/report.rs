@@ -0,0 +1,217 @@
+// The results file in the formats --format offers: the JSON every other tool
+// here reads, and CSV, Markdown and HTML tables of the headline numbers for
+// spreadsheets, pull requests and wikis. CSV keeps raw numbers in each
+// benchmark's unit; the tables are formatted like the terminal report.
+// Anything else is rendered from a user template (--template).
+
+use serde_json::{Map, Value};
+
+use crate::pivot::{csv_field, escape_html};
+use crate::template::Template;
+use crate::{format_labels, format_value_plain, BenchmarkResult, BenchmarkStatus, BenchmarkSuite};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(ReportFormat::Json),
+            "csv" => Some(ReportFormat::Csv),
+            "md" | "markdown" => Some(ReportFormat::Markdown),
+            "html" => Some(ReportFormat::Html),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+
+    pub fn render(self, suite: &BenchmarkSuite) -> serde_json::Result<String> {
+        match self {
+            ReportFormat::Json => serde_json::to_string_pretty(suite),
+            ReportFormat::Csv => Ok(to_csv(suite)),
+            ReportFormat::Markdown => Ok(to_markdown(suite)),
+            ReportFormat::Html => Ok(to_html(suite)),
+        }
+    }
+}
+
+const RAW_HEADER: [&str; 16] = ["benchmark", "status", "runtime", "measurement", "unit", "iterations", "errors",
+    "mean", "median", "stddev", "min", "max", "p95", "p99", "cv", "margin"];
+
+// Unformatted cells of one result, numbers in the benchmark's unit
+fn raw_row(result: &BenchmarkResult) -> Vec<String> {
+    let stats = &result.stats;
+    let status = match &result.status {
+        BenchmarkStatus::Completed => "completed".to_string(),
+        BenchmarkStatus::Skipped(reason) => format!("skipped: {}", reason),
+    };
+    let mut row = vec![result.name.clone(), status, result.runtime.clone(), result.measurement.clone(), stats.unit.clone(),
+        result.iterations.to_string(), result.error_count.to_string()];
+    row.extend([stats.mean_ns, stats.median_ns, stats.stddev_ns, stats.min_ns, stats.max_ns, stats.p95_ns, stats.p99_ns, stats.cv, stats.margin_ns]
+        .iter()
+        .map(f64::to_string));
+    row
+}
+
+fn to_csv(suite: &BenchmarkSuite) -> String {
+    let mut csv = format!("{}\n", RAW_HEADER.join(","));
+    for result in &suite.results {
+        let row: Vec<String> = raw_row(result).iter().map(|cell| csv_field(cell)).collect();
+        csv.push_str(&format!("{}\n", row.join(",")));
+    }
+    csv
+}
+
+// The same columns tab-separated for --stdout-tsv, one line per result and
+// nothing else, for cut, awk, sort and datamash. Tabs and newlines in names
+// or skip reasons become spaces, as TSV cannot quote them.
+pub fn to_tsv(results: &[BenchmarkResult]) -> String {
+    let mut tsv = format!("{}\n", RAW_HEADER.join("\t"));
+    for result in results {
+        let row: Vec<String> = raw_row(result).iter().map(|cell| cell.replace(['\t', '\n', '\r'], " ")).collect();
+        tsv.push_str(&format!("{}\n", row.join("\t")));
+    }
+    tsv
+}
+
+const TABLE_HEADER: [&str; 8] = ["Benchmark", "Iterations", "Mean", "±95%", "Median", "p99", "CV", "Errors"];
+
+// Cells of one table row; skipped benchmarks carry their reason instead
+fn table_row(result: &BenchmarkResult) -> Vec<String> {
+    let stats = &result.stats;
+    match &result.status {
+        BenchmarkStatus::Skipped(reason) => {
+            let mut row = vec![result.name.clone(), format!("skipped: {}", reason)];
+            row.resize(TABLE_HEADER.len(), String::new());
+            row
+        }
+        BenchmarkStatus::Completed => vec![
+            result.name.clone(),
+            result.iterations.to_string(),
+            format_value_plain(stats.mean_ns, &stats.unit),
+            format_value_plain(stats.margin_ns, &stats.unit),
+            format_value_plain(stats.median_ns, &stats.unit),
+            format_value_plain(stats.p99_ns, &stats.unit),
+            format!("{:.1}%", stats.cv * 100.0),
+            result.error_count.to_string(),
+        ],
+    }
+}
+
+// "2026-10-15 12:00:00, commit 1a2b3c4d5e6f on main, linux x86_64, 8 CPUs"
+fn run_description(suite: &BenchmarkSuite) -> String {
+    let info = &suite.system_info;
+    let time = chrono::DateTime::from_timestamp(info.timestamp as i64, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    let mut description = time;
+    if let Some(git) = info.git_description() {
+        description.push_str(&format!(", commit {}", git));
+    }
+    description.push_str(&format!(", {} {}, {} CPU{}", info.os, info.arch, info.num_cpus, if info.num_cpus == 1 { "" } else { "s" }));
+    description
+}
+
+fn to_markdown(suite: &BenchmarkSuite) -> String {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut md = String::from("# Benchmark Results\n\n");
+    md.push_str(&format!("{}  \nLabels: {}\n\n", run_description(suite), escape(&format_labels(&suite.labels))));
+    md.push_str(&format!("| {} |\n", TABLE_HEADER.join(" | ")));
+    md.push_str(&format!("|---|{}\n", "---:|".repeat(TABLE_HEADER.len() - 1)));
+    for result in &suite.results {
+        let row: Vec<String> = table_row(result).iter().map(|cell| escape(cell)).collect();
+        md.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    md
+}
+
+fn to_html(suite: &BenchmarkSuite) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Benchmark Results</title>\n");
+    html.push_str("<style>\nbody { font-family: sans-serif; }\ntable { border-collapse: collapse; margin-bottom: 2em; }\n\
+        th, td { border: 1px solid #ccc; padding: 4px 10px; }\ntd { text-align: right; font-variant-numeric: tabular-nums; }\n\
+        td:first-child { text-align: left; }\nth { background: #f0f0f0; }\n</style>\n</head>\n<body>\n");
+    html.push_str(&format!("<h1>Benchmark Results</h1>\n<p>{}<br>Labels: {}</p>\n<table>\n<tr>",
+        escape_html(&run_description(suite)), escape_html(&format_labels(&suite.labels))));
+    for heading in TABLE_HEADER {
+        html.push_str(&format!("<th>{}</th>", escape_html(heading)));
+    }
+    html.push_str("</tr>\n");
+    for result in &suite.results {
+        html.push_str("<tr>");
+        for cell in table_row(result) {
+            html.push_str(&format!("<td>{}</td>", escape_html(&cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+// A --template: the template file, parsed up front so a mistake in it is
+// reported before the run, and where its output goes (stdout without one)
+#[derive(Debug)]
+pub struct ReportTemplate {
+    pub path: String,
+    pub template: Template,
+    pub output: Option<String>,
+}
+
+impl ReportTemplate {
+    // "<template>" or "<template>=<output>"
+    pub fn load(spec: &str) -> Result<Self, String> {
+        let (path, output) = match spec.split_once('=') {
+            Some((path, output)) => (path, Some(output.to_string())),
+            None => (spec, None),
+        };
+        let source = std::fs::read_to_string(path).map_err(|e| format!("cannot read template {}: {}", path, e))?;
+        let template = Template::parse(&source).map_err(|e| format!("template {}: {}", path, e))?;
+        Ok(Self { path: path.to_string(), template, output })
+    }
+}
+
+// What templates see: the results file's JSON, plus `run` (time, commit and
+// machine in a line) and for every result a `display` object holding the
+// table's cells formatted like the terminal report
+pub fn template_context(suite: &BenchmarkSuite) -> serde_json::Result<Value> {
+    let mut context = serde_json::to_value(suite)?;
+    if let Some(results) = context.get_mut("results").and_then(Value::as_array_mut) {
+        for (value, result) in results.iter_mut().zip(&suite.results) {
+            let mut display = Map::new();
+            match &result.status {
+                BenchmarkStatus::Skipped(reason) => {
+                    display.insert("skipped".to_string(), Value::from(reason.as_str()));
+                }
+                BenchmarkStatus::Completed => {
+                    let keys = ["name", "iterations", "mean", "margin", "median", "p99", "cv", "errors"];
+                    for (key, cell) in keys.into_iter().zip(table_row(result)) {
+                        display.insert(key.to_string(), Value::from(cell));
+                    }
+                    let stats = &result.stats;
+                    for (key, value) in [("p95", stats.p95_ns), ("stddev", stats.stddev_ns), ("min", stats.min_ns), ("max", stats.max_ns)] {
+                        display.insert(key.to_string(), Value::from(format_value_plain(value, &stats.unit)));
+                    }
+                }
+            }
+            if let Value::Object(object) = value {
+                object.insert("display".to_string(), Value::Object(display));
+            }
+        }
+    }
+    if let Value::Object(object) = &mut context {
+        object.insert("run".to_string(), Value::from(run_description(suite)));
+    }
+    Ok(context)
+}
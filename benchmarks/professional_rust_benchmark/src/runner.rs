@@ -0,0 +1,712 @@
+// The runner: warms a benchmark up, samples it until the time and iteration
+// bounds are met and folds the samples into a BenchmarkResult. The builder
+// methods switch on what a run records beyond that (regression batches,
+// memory, timestamps, outliers, profiles, task latencies) and the
+// deterministic executor the benchmarks spawn their tasks through.
+
+use std::hash::Hash;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::task::JoinSet;
+use futures::FutureExt;
+
+use crate::benchmarks::SuiteFixtures;
+use crate::memory::MemorySampler;
+use crate::profile::Profiler;
+use crate::stats;
+use crate::{
+    unix_time_ns, BenchError, BenchRng, BenchmarkResult, BenchmarkStats, Measurement, MetricStats, OutlierLog,
+    RegressionBatch, RegressionEstimate, SampleTimeline, StageAttribution, StatsConfig, WallClock, WorkerBreakdown, DEFAULT_SEED,
+    HEAP_TRACKING,
+};
+
+// Called by the runner while a benchmark is measuring, so embedders can
+// render progress bars or stream status instead of a silent terminal.
+pub trait ProgressObserver: Send + Sync {
+    fn on_progress(&self, benchmark: &str, samples: usize, elapsed: std::time::Duration);
+
+    fn on_complete(&self, _benchmark: &str, _samples: usize, _elapsed: std::time::Duration) {}
+}
+
+// Single status line on stderr, so stdout stays clean for the report
+pub struct StderrProgress;
+
+impl ProgressObserver for StderrProgress {
+    fn on_progress(&self, benchmark: &str, samples: usize, elapsed: std::time::Duration) {
+        use std::io::Write;
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K  {:<30} {:>10} samples {:>8.2}s", benchmark, samples, elapsed.as_secs_f64());
+        let _ = stderr.flush();
+    }
+
+    fn on_complete(&self, _benchmark: &str, _samples: usize, _elapsed: std::time::Duration) {
+        use std::io::Write;
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K");
+        let _ = stderr.flush();
+    }
+}
+
+#[derive(Clone)]
+pub struct BenchmarkRunner {
+    pub warmup_time_ns: u128,
+    pub min_iterations: usize,
+    pub max_iterations: usize,
+    pub min_benchmark_time_ns: u128,
+    seed: u64,
+    pub deterministic_seed: Option<u64>,
+    progress: Option<Arc<dyn ProgressObserver>>,
+    pub measurement: Arc<dyn Measurement>,
+    // Recorded alongside the primary measurement, outside its readings
+    extra_measurements: Vec<Arc<dyn Measurement>>,
+    pub stats_config: StatsConfig,
+    pub fixtures: Arc<SuiteFixtures>,
+    // Batches timed for the regression estimate; 0 disables it
+    regression_batches: u64,
+    // Interval of the background memory sampler; None disables it
+    memory_interval: Option<std::time::Duration>,
+    // Keep a timestamp with every sample (result.timeline)
+    timestamps: bool,
+    // Keep at most this many of those, a uniform random subset
+    reservoir: Option<usize>,
+    // Log samples above this multiple of the median (result.outlier_log)
+    outlier_multiple: Option<f64>,
+    // CPU profile of the benchmarks it selects while they run
+    pub profiler: Option<Arc<Profiler>>,
+    // Record per-task latencies in the benchmarks using a TaskRecorder
+    task_latency: bool,
+}
+
+impl Default for BenchmarkRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BenchmarkRunner {
+    pub fn new() -> Self {
+        Self {
+            warmup_time_ns: 100_000_000, // long enough for page faults, frequency ramp-up and allocator caches
+            min_iterations: 100,
+            max_iterations: 10000,
+            min_benchmark_time_ns: 100_000_000, // 100ms minimum
+            seed: DEFAULT_SEED,
+            deterministic_seed: None,
+            progress: None,
+            measurement: Arc::new(WallClock::new()),
+            extra_measurements: Vec::new(),
+            stats_config: StatsConfig::default(),
+            fixtures: Arc::new(SuiteFixtures::default()),
+            regression_batches: 0,
+            memory_interval: None,
+            timestamps: false,
+            reservoir: None,
+            outlier_multiple: None,
+            profiler: None,
+            task_latency: false,
+        }
+    }
+
+    // After the regular samples, time `batches` batches of 1, 2, 3, ...
+    // times a step iterations each and fit a line through them
+    pub fn with_regression(mut self, batches: u64) -> Self {
+        self.regression_batches = batches;
+        self
+    }
+
+    // Iterations per step, sized so all batches together take about as long
+    // as the regular measurement did
+    fn regression_step(&self, result: &BenchmarkResult) -> u64 {
+        let attempts = (result.iterations + result.error_count).max(1) as f64;
+        let per_iteration_ns = (result.total_time_ns / attempts).max(1.0);
+        let batches = self.regression_batches as f64;
+        let step = self.min_benchmark_time_ns as f64 / (per_iteration_ns * batches * (batches + 1.0) / 2.0);
+        (step as u64).max(1)
+    }
+
+    // Sample RSS and heap bytes in use every `interval` while the regular
+    // samples are taken, reported in result.memory
+    pub fn with_memory_sampling(mut self, interval: std::time::Duration) -> Self {
+        HEAP_TRACKING.store(true, std::sync::atomic::Ordering::Relaxed);
+        self.memory_interval = Some(interval);
+        self
+    }
+
+    // Sample the call stacks of the benchmarks `profiler` selects from
+    // warmup to the last sample
+    pub fn with_profiler(mut self, profiler: Arc<Profiler>) -> Self {
+        self.profiler = Some(profiler);
+        self
+    }
+
+    // Record when each sample finished alongside its value, for correlating
+    // spikes with external events. Costs a clock read and 16 bytes per sample.
+    pub fn with_timestamps(mut self) -> Self {
+        self.timestamps = true;
+        self
+    }
+
+    // Bound the timeline to a reservoir sample of `capacity` samples
+    pub fn with_reservoir(mut self, capacity: usize) -> Self {
+        self.reservoir = Some(capacity);
+        self
+    }
+
+    // Log samples above `multiple` times the median together with RSS, heap
+    // and runtime queue state at the time, in result.outlier_log
+    pub fn with_outlier_log(mut self, multiple: f64) -> Self {
+        self.outlier_multiple = Some(multiple);
+        self
+    }
+
+    // Record the latency of every task the concurrent benchmarks spawn, per
+    // worker thread (result.workers). Adds two clock reads and a locked push
+    // per task to the timed iterations.
+    pub fn with_task_latency(mut self) -> Self {
+        self.task_latency = true;
+        self
+    }
+
+    pub fn task_recorder(&self) -> TaskRecorder {
+        TaskRecorder::new(self.task_latency, self.stats_config.precision)
+    }
+
+    pub fn with_measurement(mut self, measurement: Arc<dyn Measurement>) -> Self {
+        self.measurement = measurement;
+        self
+    }
+
+    // Also record `measurement` for every iteration, reported in
+    // result.metrics. Its readings wrap the primary measurement's, so the
+    // primary one stays unaffected.
+    pub fn with_extra_measurement(mut self, measurement: Arc<dyn Measurement>) -> Self {
+        self.extra_measurements.push(measurement);
+        self
+    }
+
+    pub fn with_fixtures(mut self, fixtures: Arc<SuiteFixtures>) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    pub fn with_stats_config(mut self, config: StatsConfig) -> Self {
+        self.stats_config = config;
+        self
+    }
+
+    fn primary_stats(&self) -> BenchmarkStats {
+        BenchmarkStats { unit: self.measurement.unit().to_string(), ..BenchmarkStats::with_precision(self.stats_config.precision) }
+    }
+
+    fn start_result(&self, name: &str, warmup_iterations: usize, warmup: BenchmarkStats) -> BenchmarkResult {
+        let mut result = BenchmarkResult::new(name.to_string());
+        result.warmup_iterations = warmup_iterations;
+        if let Some(multiple) = self.outlier_multiple {
+            let reference = if warmup.histogram.is_empty() { 0.0 } else { warmup.histogram.quantile(0.5) };
+            result.outlier_log = Some(OutlierLog::new(multiple, reference));
+        }
+        result.warmup = Some(warmup);
+        result.measurement = self.measurement.name().to_string();
+        result.stats = self.primary_stats();
+        result.metrics = self.extra_measurements.iter().map(|measurement| MetricStats {
+            name: measurement.name().to_string(),
+            stats: BenchmarkStats { unit: measurement.unit().to_string(), ..BenchmarkStats::with_precision(self.stats_config.precision) },
+        }).collect();
+        if let Some(compression) = self.stats_config.digest_compression {
+            result.stats.keep_digest(compression);
+            for metric in &mut result.metrics {
+                metric.stats.keep_digest(compression);
+            }
+        }
+        if let Some(batch_size) = self.stats_config.batch_size {
+            result.stats.keep_batch_minima(batch_size);
+        }
+        if self.stats_config.trim > 0.0 {
+            result.stats.keep_order();
+            for metric in &mut result.metrics {
+                metric.stats.keep_order();
+            }
+        }
+        result.start_time_ns = unix_time_ns();
+        if self.timestamps {
+            let rng = BenchRng::for_benchmark(self.seed, &format!("{} reservoir", name));
+            result.timeline = Some(SampleTimeline::start(self.reservoir, rng));
+        }
+        result
+    }
+
+    fn read_extra_measurements(&self, readings: &mut [u64]) {
+        for (reading, measurement) in readings.iter_mut().zip(&self.extra_measurements) {
+            *reading = measurement.read();
+        }
+    }
+
+    fn record_sample(&self, result: &mut BenchmarkResult, start: u64, end: u64, extra_start: &[u64], extra_end: &[u64], total_start: Instant) {
+        let value = self.measurement.delta(start, end);
+        if let Some(log) = &mut result.outlier_log {
+            log.observe(result.stats.histogram.len(), value, result.start_time_ns + total_start.elapsed().as_nanos() as u64);
+        }
+        result.stats.record(value);
+        if let Some(timeline) = &mut result.timeline {
+            timeline.record(total_start.elapsed().as_nanos() as u64, value);
+        }
+        for (i, measurement) in self.extra_measurements.iter().enumerate() {
+            result.metrics[i].stats.record(measurement.delta(extra_start[i], extra_end[i]));
+        }
+    }
+
+    // Every benchmark runs on a single-threaded runtime with a paused clock
+    // and seeded task polling order, for reproducible correctness runs.
+    pub fn with_deterministic_executor(mut self, schedule_seed: u64) -> Self {
+        self.deterministic_seed = Some(schedule_seed);
+        self
+    }
+
+    pub fn with_progress(mut self, observer: Arc<dyn ProgressObserver>) -> Self {
+        self.progress = Some(observer);
+        self
+    }
+
+    fn report_progress(&self, name: &str, samples: usize, elapsed_ns: u128) {
+        if let Some(observer) = &self.progress {
+            observer.on_progress(name, samples, std::time::Duration::from_nanos(elapsed_ns as u64));
+        }
+    }
+
+    fn report_complete(&self, name: &str, samples: usize, elapsed_ns: u128) {
+        if let Some(observer) = &self.progress {
+            observer.on_complete(name, samples, std::time::Duration::from_nanos(elapsed_ns as u64));
+        }
+    }
+
+    pub fn with_warmup(mut self, warmup: std::time::Duration) -> Self {
+        self.warmup_time_ns = warmup.as_nanos();
+        self
+    }
+
+    pub fn with_min_time_ns(mut self, min_time_ns: u128) -> Self {
+        self.min_benchmark_time_ns = min_time_ns;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // Runner for one of `rounds` interleaved blocks: each block gets an equal
+    // share of the measurement time and iteration limits.
+    pub fn for_block(&self, rounds: usize) -> Self {
+        let rounds = rounds.max(1);
+        Self {
+            min_iterations: (self.min_iterations / rounds).max(1),
+            max_iterations: (self.max_iterations / rounds).max(1),
+            min_benchmark_time_ns: self.min_benchmark_time_ns / rounds as u128,
+            warmup_time_ns: self.warmup_time_ns / rounds as u128,
+            ..self.clone()
+        }
+    }
+
+    // Whatever the benchmark returns is folded into result.checksum, so a
+    // body can prove it computed real work (and the optimizer cannot drop it).
+    pub async fn run<F, Fut, T>(&self, name: &str, mut benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+        T: Hash,
+    {
+        self.run_with_rng(name, |_| benchmark_func()).await
+    }
+
+    // Same as run(), but every call receives the benchmark's seeded RNG so
+    // randomized workloads generate the same data on every machine and run.
+    pub async fn run_with_rng<F, Fut, T>(&self, name: &str, mut benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut(&mut BenchRng) -> Fut,
+        Fut: Future<Output = T>,
+        T: Hash,
+    {
+        self.try_run_with_rng(name, |rng| {
+            let fut = benchmark_func(rng);
+            async move { Ok(fut.await) }
+        }).await
+    }
+
+    pub async fn try_run<F, Fut, T>(&self, name: &str, mut benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, BenchError>>,
+        T: Hash,
+    {
+        self.try_run_with_rng(name, |_| benchmark_func()).await
+    }
+
+    // Fallible variant: failed iterations are counted in error_count/error_rate
+    // instead of aborting the whole suite, and are excluded from the timings.
+    pub async fn try_run_with_rng<F, Fut, T>(&self, name: &str, benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut(&mut BenchRng) -> Fut,
+        Fut: Future<Output = Result<T, BenchError>>,
+        T: Hash,
+    {
+        self.try_run_hooked(name, None, benchmark_func).await
+    }
+
+    // Same as try_run(), with `hook` called around every iteration
+    pub async fn try_run_tracked<F, Fut, T>(&self, name: &str, hook: &dyn IterationHook, mut benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, BenchError>>,
+        T: Hash,
+    {
+        self.try_run_hooked(name, Some(hook), |_| benchmark_func()).await
+    }
+
+    // The measurement loop behind every run method: warmup, the doubling
+    // sample batches and the regression batches, each iteration one call of
+    // `benchmark_func` between two readings of the measurement
+    async fn try_run_hooked<F, Fut, T>(&self, name: &str, hook: Option<&dyn IterationHook>, mut benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut(&mut BenchRng) -> Fut,
+        Fut: Future<Output = Result<T, BenchError>>,
+        T: Hash,
+    {
+        let mut rng = BenchRng::for_benchmark(self.seed, name);
+
+        // Warmup phase, bounded by time rather than a fixed count
+        let warmup_start = Instant::now();
+        let mut warmup_iterations = 0;
+        let mut warmup = self.primary_stats();
+        while warmup_start.elapsed().as_nanos() < self.warmup_time_ns {
+            if let Some(hook) = hook {
+                hook.begin();
+            }
+            let start = self.measurement.read();
+            let outcome = benchmark_func(&mut rng).await;
+            let end = self.measurement.read();
+            if let Some(hook) = hook {
+                hook.end(false);
+            }
+            if outcome.is_ok() {
+                warmup.record(self.measurement.delta(start, end));
+            }
+            warmup_iterations += 1;
+        }
+
+        let mut result = self.start_result(name, warmup_iterations, warmup);
+        let mut extra_start = vec![0u64; self.extra_measurements.len()];
+        let mut extra_end = extra_start.clone();
+        let sampler = self.memory_interval.map(MemorySampler::start);
+        let total_start = Instant::now();
+        let mut iterations = self.min_iterations;
+        let mut elapsed = 0u128;
+
+        while elapsed < self.min_benchmark_time_ns && iterations <= self.max_iterations {
+            let (samples_before, sum_before) = (result.stats.histogram.len(), result.stats.histogram.sum());
+            let errors_before = result.error_count;
+            for _ in 0..iterations {
+                if let Some(hook) = hook {
+                    hook.begin();
+                }
+                self.read_extra_measurements(&mut extra_start);
+                let start = self.measurement.read();
+                let outcome = benchmark_func(&mut rng).await;
+                let end = self.measurement.read();
+                self.read_extra_measurements(&mut extra_end);
+                if let Some(hook) = hook {
+                    hook.end(outcome.is_ok());
+                }
+                match outcome {
+                    Ok(output) => {
+                        self.record_sample(&mut result, start, end, &extra_start, &extra_end, total_start);
+                        result.record_output(&output);
+                    }
+                    Err(error) => result.record_error(error),
+                }
+            }
+
+            elapsed = total_start.elapsed().as_nanos();
+            result.close_window(samples_before, sum_before, errors_before, elapsed);
+            self.report_progress(name, result.stats.histogram.len() as usize, elapsed);
+            if elapsed < self.min_benchmark_time_ns {
+                iterations = std::cmp::min(iterations * 2, self.max_iterations);
+            }
+        }
+
+        result.memory = sampler.map(MemorySampler::stop);
+        self.report_complete(name, result.stats.histogram.len() as usize, elapsed);
+        result.total_time_ns = elapsed as f64;
+        result.finish(&self.stats_config);
+        if self.regression_batches > 0 {
+            let step = self.regression_step(&result);
+            let mut batches = Vec::new();
+            for k in 1..=self.regression_batches {
+                if let Some(hook) = hook {
+                    hook.begin();
+                }
+                let start = self.measurement.read();
+                for _ in 0..k * step {
+                    let _ = std::hint::black_box(benchmark_func(&mut rng).await);
+                }
+                let end = self.measurement.read();
+                if let Some(hook) = hook {
+                    hook.end(false);
+                }
+                batches.push(RegressionBatch { iterations: k * step, total: self.measurement.delta(start, end) });
+            }
+            result.regression = Some(RegressionEstimate::fit(batches));
+        }
+        result
+    }
+
+    pub fn run_sync<F, T>(&self, name: &str, mut benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut() -> T,
+        T: Hash,
+    {
+        self.try_run_sync(name, || Ok(benchmark_func()))
+    }
+
+    // Sync bodies go through the same loop as async ones; a ready future
+    // never suspends, so the loop completes within a single poll.
+    pub fn try_run_sync<F, T>(&self, name: &str, mut benchmark_func: F) -> BenchmarkResult
+    where
+        F: FnMut() -> Result<T, BenchError>,
+        T: Hash,
+    {
+        self.try_run_hooked(name, None, |_| std::future::ready(benchmark_func()))
+            .now_or_never()
+            .expect("a synchronous benchmark body suspended")
+    }
+}
+
+// Deterministic executor support
+
+thread_local! {
+    // Set only while a benchmark runs in deterministic executor mode. The
+    // runtime is current-thread, so every task is polled on this thread.
+    pub static SCHEDULE_RNG: std::cell::RefCell<Option<BenchRng>> = const { std::cell::RefCell::new(None) };
+}
+
+// Yields back to the scheduler before a poll whenever the schedule RNG says
+// so, which permutes the interleaving of tasks in a reproducible way.
+struct Perturbed<F> {
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Perturbed<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<F::Output> {
+        let yield_now = SCHEDULE_RNG.with(|rng| {
+            rng.borrow_mut().as_mut().is_some_and(|rng| rng.gen_range(2) == 0)
+        });
+        if yield_now {
+            cx.waker().wake_by_ref();
+            return std::task::Poll::Pending;
+        }
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+// Benchmarks spawn their tasks through this so the deterministic executor
+// mode can control polling order; otherwise it is a plain JoinSet::spawn.
+pub fn spawn_task<T, F>(join_set: &mut JoinSet<T>, task: F)
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    if SCHEDULE_RNG.with(|rng| rng.borrow().is_some()) {
+        join_set.spawn(Perturbed { inner: Box::pin(task) });
+    } else {
+        join_set.spawn(task);
+    }
+}
+
+// Runs `f` on a thread of its own and waits for it, for benchmarks that bring
+// their own runtime: nothing of the suite's runtime is entered or queued
+// there, and the schedule RNG starts out unset. A panic in `f` is passed on.
+pub fn on_own_thread<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("benchmark-runtime".to_string())
+            .spawn_scoped(scope, f)
+            .expect("failed to spawn benchmark thread")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+// Called by BenchmarkRunner::try_run_tracked around every iteration, outside
+// the timed region. `keep` is false for warmup and failed iterations.
+pub trait IterationHook {
+    fn begin(&self);
+    fn end(&self, keep: bool);
+}
+
+// Wall-clock sub-measurements of the stages of a composite benchmark. The
+// body calls begin() at the start of an iteration, mark() at the end of each
+// stage and commit() once the iteration succeeded. Warmup commits come first,
+// so the last `iterations` commits are exactly the measured iterations.
+pub struct StageRecorder {
+    names: &'static [&'static str],
+    last_mark: Cell<Option<Instant>>,
+    current: RefCell<Vec<f64>>,
+    committed: RefCell<Vec<f64>>,
+}
+
+impl StageRecorder {
+    pub fn new(names: &'static [&'static str]) -> Self {
+        Self {
+            names,
+            last_mark: Cell::new(None),
+            current: RefCell::new(Vec::with_capacity(names.len())),
+            committed: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn begin(&self) {
+        self.current.borrow_mut().clear();
+        self.last_mark.set(Some(Instant::now()));
+    }
+
+    pub fn mark(&self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_mark.replace(Some(now)) {
+            self.current.borrow_mut().push((now - last).as_nanos() as f64);
+        }
+    }
+
+    pub fn commit(&self) {
+        let current = self.current.borrow();
+        if current.len() == self.names.len() {
+            self.committed.borrow_mut().extend_from_slice(&current);
+        }
+    }
+
+    // Share of the mean and of the p99 tail taken by each stage
+    pub fn attribution(&self, iterations: usize) -> Vec<StageAttribution> {
+        let stages = self.names.len();
+        let committed = self.committed.borrow();
+        let measured = &committed[committed.len().saturating_sub(iterations * stages)..];
+        let rows: Vec<&[f64]> = measured.chunks_exact(stages).collect();
+        if rows.is_empty() {
+            return Vec::new();
+        }
+
+        let mut totals: Vec<f64> = rows.iter().map(|row| row.iter().sum()).collect();
+        totals.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let p99 = stats::quantile(&totals, 0.99);
+        let tail: Vec<&[f64]> = rows.iter().copied().filter(|row| row.iter().sum::<f64>() >= p99).collect();
+
+        let stage_mean = |rows: &[&[f64]], stage: usize| rows.iter().map(|row| row[stage]).sum::<stats::CompensatedSum>().value() / rows.len() as f64;
+        let means: Vec<f64> = (0..stages).map(|stage| stage_mean(&rows, stage)).collect();
+        let tail_means: Vec<f64> = (0..stages).map(|stage| stage_mean(&tail, stage)).collect();
+        let mean_total: f64 = means.iter().sum();
+        let tail_total: f64 = tail_means.iter().sum();
+
+        self.names.iter().enumerate().map(|(stage, name)| StageAttribution {
+            name: name.to_string(),
+            mean_ns: means[stage],
+            tail_mean_ns: tail_means[stage],
+            mean_share: means[stage] / mean_total,
+            tail_share: tail_means[stage] / tail_total,
+        }).collect()
+    }
+}
+
+// Per-task latencies of a concurrent benchmark, recorded only with
+// --task-latency: the clock reads and the shared push in track() are part of
+// the timed iteration, which FlowCoro's and Go's harnesses do not pay. The
+// body wraps each task it spawns in track() and runs through
+// BenchmarkRunner::try_run_tracked, which starts and ends each iteration's
+// batch outside the timed region; only succeeded measured iterations are
+// kept, folded straight into the histograms.
+pub struct TaskRecorder {
+    // Tasks of the current iteration; None when disabled
+    current: Option<Arc<Mutex<Vec<TaskLatency>>>>,
+    // Threads in the order they first completed a task, and what they did
+    threads: RefCell<Vec<std::thread::ThreadId>>,
+    breakdown: RefCell<WorkerBreakdown>,
+    precision: u32,
+}
+
+// Thread the task finished on, and its latency
+type TaskLatency = (std::thread::ThreadId, f64);
+
+impl TaskRecorder {
+    pub fn new(enabled: bool, precision: u32) -> Self {
+        let empty = BenchmarkStats::with_precision(precision);
+        Self {
+            current: enabled.then(|| Arc::new(Mutex::new(Vec::new()))),
+            threads: RefCell::new(Vec::new()),
+            breakdown: RefCell::new(WorkerBreakdown { tasks: empty, workers: Vec::new(), imbalance: 0.0 }),
+            precision,
+        }
+    }
+
+    // Call right before spawning the task
+    pub fn track<F: Future>(&self, task: F) -> impl Future<Output = F::Output> + use<F> {
+        let tracking = self.current.as_ref().map(|current| (Instant::now(), Arc::clone(current)));
+        async move {
+            let output = task.await;
+            if let Some((spawned, current)) = tracking {
+                let latency = spawned.elapsed().as_nanos() as f64;
+                current.lock().unwrap().push((std::thread::current().id(), latency));
+            }
+            output
+        }
+    }
+
+    pub fn breakdown(&self, config: &StatsConfig) -> Option<WorkerBreakdown> {
+        let mut breakdown = self.breakdown.borrow().clone();
+        if breakdown.tasks.histogram.is_empty() {
+            return None;
+        }
+        breakdown.calculate(config);
+        Some(breakdown)
+    }
+}
+
+impl IterationHook for TaskRecorder {
+    fn begin(&self) {
+        let Some(current) = &self.current else {
+            return;
+        };
+        current.lock().unwrap().clear();
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let workers = &mut self.breakdown.borrow_mut().workers;
+            let count = handle.metrics().num_workers().max(workers.len());
+            workers.resize_with(count, || BenchmarkStats::with_precision(self.precision));
+        }
+    }
+
+    fn end(&self, keep: bool) {
+        let Some(current) = &self.current else {
+            return;
+        };
+        let tasks = std::mem::take(&mut *current.lock().unwrap());
+        if !keep {
+            return;
+        }
+        let mut threads = self.threads.borrow_mut();
+        let mut breakdown = self.breakdown.borrow_mut();
+        for (thread, latency) in tasks {
+            let worker = match threads.iter().position(|&seen| seen == thread) {
+                Some(worker) => worker,
+                None => {
+                    threads.push(thread);
+                    threads.len() - 1
+                }
+            };
+            if worker >= breakdown.workers.len() {
+                breakdown.workers.resize_with(worker + 1, || BenchmarkStats::with_precision(self.precision));
+            }
+            breakdown.workers[worker].record(latency);
+            breakdown.tasks.record(latency);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+// `selftest`: runs spin loops of known duration through the normal runner and
+// checks that what comes out matches, to catch clock, calibration and
+// accounting bugs in the measurement pipeline itself.
+
+use std::time::Instant;
+
+use crate::{BenchmarkResult, BenchmarkRunner, BenchmarkStatus};
+
+// Target duration in ns, label and allowed relative error of the median. The
+// harness adds two clock reads per sample, which matters most for the
+// shortest loop. The bounds leave room for frequency drift on shared
+// machines; the bugs this is after are off by factors, not percents.
+const WORKLOADS: [(f64, &str, f64); 3] = [
+    (1_000.0, "1µs", 0.25),
+    (100_000.0, "100µs", 0.10),
+    (10_000_000.0, "10ms", 0.10),
+];
+
+const REGRESSION_TOLERANCE: f64 = 0.20;
+
+fn spin(iterations: u64) -> u64 {
+    let mut x = 0u64;
+    for i in 0..iterations {
+        x = std::hint::black_box(x.wrapping_mul(6364136223846793005).wrapping_add(i));
+    }
+    x
+}
+
+// Nanoseconds per spin iteration: the median of 5 rounds of at least 20ms
+// each after 200ms of warmup, timed with Instant directly rather than
+// through the runner
+fn calibrate() -> f64 {
+    let warmup = Instant::now();
+    while warmup.elapsed().as_millis() < 200 {
+        spin(10_000);
+    }
+    let mut iterations = 1_000u64;
+    loop {
+        let start = Instant::now();
+        spin(iterations);
+        if start.elapsed().as_nanos() >= 20_000_000 {
+            break;
+        }
+        iterations *= 2;
+    }
+    let mut rounds: Vec<f64> = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            spin(iterations);
+            start.elapsed().as_nanos() as f64 / iterations as f64
+        })
+        .collect();
+    rounds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    rounds[2]
+}
+
+struct Check {
+    name: String,
+    expected: f64,
+    measured: f64,
+    tolerance: f64,
+    problems: Vec<String>,
+}
+
+impl Check {
+    fn error(&self) -> f64 {
+        (self.measured - self.expected) / self.expected
+    }
+
+    fn passed(&self) -> bool {
+        self.error().abs() <= self.tolerance && self.problems.is_empty()
+    }
+}
+
+// Bookkeeping that has to hold for any result, whatever the timings
+fn accounting_problems(result: &BenchmarkResult, wall_clock: bool) -> Vec<String> {
+    let mut problems = Vec::new();
+    if result.status != BenchmarkStatus::Completed {
+        problems.push("not completed".to_string());
+    }
+    if result.error_count > 0 {
+        problems.push(format!("{} errors", result.error_count));
+    }
+    if result.iterations as u64 != result.stats.histogram.len() {
+        problems.push(format!("{} iterations but {} samples", result.iterations, result.stats.histogram.len()));
+    }
+    let window_samples: usize = result.windows.iter().map(|w| w.samples).sum();
+    let recorded = result.iterations + result.trimmed_start + result.trimmed_end;
+    if window_samples != recorded {
+        problems.push(format!("windows hold {} of {} samples", window_samples, recorded));
+    }
+    if result.checksum.is_none() {
+        problems.push("no checksum".to_string());
+    }
+    // Samples are disjoint slices of the measured wall time
+    if wall_clock && result.stats.histogram.sum() > result.total_time_ns {
+        problems.push(format!("samples add up to {:.0} ns of {:.0} ns total", result.stats.histogram.sum(), result.total_time_ns));
+    }
+    problems
+}
+
+pub async fn run(runner: &BenchmarkRunner) -> bool {
+    if runner.measurement.unit() != "ns" {
+        tracing::error!(measurement = runner.measurement.name(), "selftest needs a clock measurement");
+        return false;
+    }
+    let wall_clock = runner.measurement.name() == "wall";
+    let ns_per_iteration = calibrate();
+    println!("\n=== Self-Test ({} clock) ===", runner.measurement.name());
+    println!("Spin loop calibrated at {:.3} ns/iteration", ns_per_iteration);
+
+    let mut checks = Vec::new();
+    for (expected, label, tolerance) in WORKLOADS {
+        let iterations = (expected / ns_per_iteration).round() as u64;
+        let result = runner.run_sync(&format!("Spin {}", label), || spin(iterations));
+        checks.push(Check {
+            name: format!("Spin {} (sync median)", label),
+            expected,
+            measured: result.stats.median_ns,
+            tolerance,
+            problems: accounting_problems(&result, wall_clock),
+        });
+    }
+
+    // The async path, with a spin short enough not to matter to the runtime
+    let (expected, label, tolerance) = WORKLOADS[1];
+    let iterations = (expected / ns_per_iteration).round() as u64;
+    let result = runner.run(&format!("Spin {} async", label), || async move { spin(iterations) }).await;
+    checks.push(Check {
+        name: format!("Spin {} (async median)", label),
+        expected,
+        measured: result.stats.median_ns,
+        tolerance,
+        problems: accounting_problems(&result, wall_clock),
+    });
+
+    // The regression slope leaves the per-sample clock reads out, so it is
+    // held to a tighter bound than the 1µs median
+    let (expected, label, _) = WORKLOADS[0];
+    let iterations = (expected / ns_per_iteration).round() as u64;
+    let result = runner.clone().with_regression(30).run_sync(&format!("Spin {}", label), || spin(iterations));
+    let mut problems = accounting_problems(&result, wall_clock);
+    let slope = result.regression.as_ref().map_or(0.0, |regression| regression.slope);
+    if result.regression.is_none() {
+        problems.push("no regression estimate".to_string());
+    }
+    checks.push(Check {
+        name: format!("Spin {} (regression slope)", label),
+        expected,
+        measured: slope,
+        tolerance: REGRESSION_TOLERANCE,
+        problems,
+    });
+
+    println!("{:<32} {:>12} {:>12} {:>8} {:>10}  Result", "Check", "Expected", "Measured", "Error", "Tolerance");
+    println!("----------------------------------------------------------------------------------------------");
+    for check in &checks {
+        println!("{:<32} {:>9.0} ns {:>9.0} ns {:>7.1}% {:>9.0}%  {}",
+            check.name, check.expected, check.measured, check.error() * 100.0, check.tolerance * 100.0,
+            if check.passed() { "PASS" } else { "FAIL" });
+        for problem in &check.problems {
+            println!("    {}", problem);
+        }
+    }
+    let failed = checks.iter().filter(|check| !check.passed()).count();
+    if failed == 0 {
+        println!("\nAll {} checks passed.", checks.len());
+    } else {
+        println!("\n{} of {} checks failed.", failed, checks.len());
+    }
+    failed == 0
+}
@@ -0,0 +1,180 @@
+// Results in an SQLite database (--sqlite, with the sqlite feature), so a
+// long history can be queried with SQL instead of by parsing a pile of
+// results files: one row per run in `runs`, one per benchmark of a run in
+// `benchmarks`, and each benchmark's sample distribution in `distribution`
+// as (value, count) pairs, from the histogram buckets or, with --tdigest,
+// the digest's centroids. The tables are created on first use and every run
+// is added in one transaction.
+
+use rusqlite::{params, Connection, TransactionBehavior};
+
+use crate::{BenchmarkStatus, BenchmarkSuite};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    git_commit TEXT,
+    rust_version TEXT NOT NULL,
+    os TEXT NOT NULL,
+    arch TEXT NOT NULL,
+    num_cpus INTEGER NOT NULL,
+    cpu_model TEXT,
+    seed INTEGER NOT NULL,
+    labels TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS benchmarks (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    name TEXT NOT NULL,
+    status TEXT NOT NULL,
+    skip_reason TEXT,
+    runtime TEXT NOT NULL,
+    measurement TEXT NOT NULL,
+    unit TEXT NOT NULL,
+    iterations INTEGER NOT NULL,
+    errors INTEGER NOT NULL,
+    mean REAL,
+    median REAL,
+    stddev REAL,
+    min REAL,
+    max REAL,
+    p95 REAL,
+    p99 REAL,
+    cv REAL,
+    margin REAL,
+    PRIMARY KEY (run_id, name)
+);
+CREATE TABLE IF NOT EXISTS distribution (
+    run_id INTEGER NOT NULL,
+    benchmark TEXT NOT NULL,
+    value REAL NOT NULL,
+    count INTEGER NOT NULL,
+    FOREIGN KEY (run_id, benchmark) REFERENCES benchmarks(run_id, name)
+);
+CREATE INDEX IF NOT EXISTS benchmarks_by_name ON benchmarks(name, run_id);
+CREATE INDEX IF NOT EXISTS distribution_by_benchmark ON distribution(run_id, benchmark);
+";
+
+// Adds the run, returning its id in `runs`
+pub fn add_run(database: &str, suite: &BenchmarkSuite) -> rusqlite::Result<i64> {
+    let mut connection = Connection::open(database)?;
+    insert(&mut connection, suite)
+}
+
+fn insert(connection: &mut Connection, suite: &BenchmarkSuite) -> rusqlite::Result<i64> {
+    connection.execute_batch(SCHEMA)?;
+    let info = &suite.system_info;
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    transaction.execute(
+        "INSERT INTO runs (timestamp, git_commit, rust_version, os, arch, num_cpus, cpu_model, seed, labels) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            info.timestamp as i64,
+            info.git_commit,
+            info.rust_version,
+            info.os,
+            info.arch,
+            info.num_cpus as i64,
+            info.fingerprint.as_ref().map(|fingerprint| fingerprint.cpu_model.as_str()),
+            // SQLite integers are signed 64-bit
+            suite.seed as i64,
+            serde_json::to_string(&suite.labels).unwrap_or_default(),
+        ],
+    )?;
+    let run_id = transaction.last_insert_rowid();
+    {
+        let mut benchmark = transaction.prepare("INSERT INTO benchmarks VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)")?;
+        let mut bucket = transaction.prepare("INSERT INTO distribution VALUES (?1, ?2, ?3, ?4)")?;
+        for result in &suite.results {
+            let stats = &result.stats;
+            let (status, skip_reason) = match &result.status {
+                BenchmarkStatus::Completed => ("completed", None),
+                BenchmarkStatus::Skipped(reason) => ("skipped", Some(reason.as_str())),
+            };
+            benchmark.execute(params![
+                run_id,
+                result.name,
+                status,
+                skip_reason,
+                result.runtime,
+                result.measurement,
+                stats.unit,
+                result.iterations as i64,
+                result.error_count as i64,
+                real(stats.mean_ns),
+                real(stats.median_ns),
+                real(stats.stddev_ns),
+                real(stats.min_ns),
+                real(stats.max_ns),
+                real(stats.p95_ns),
+                real(stats.p99_ns),
+                real(stats.cv),
+                real(stats.margin_ns),
+            ])?;
+            let distribution: Vec<(f64, u64)> = match &stats.digest {
+                Some(digest) => digest.centroids().collect(),
+                None => stats.histogram.midpoints().collect(),
+            };
+            for (value, count) in distribution {
+                bucket.execute(params![run_id, result.name, value, count as i64])?;
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(run_id)
+}
+
+// NaN (no samples) is stored as NULL
+fn real(value: f64) -> Option<f64> {
+    value.is_finite().then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BenchmarkResult, BenchmarkStats, StatsConfig, SystemInfo};
+
+    fn suite(seed: u64) -> BenchmarkSuite {
+        let mut result = BenchmarkResult::new("it's spawn".to_string());
+        result.stats = BenchmarkStats::with_precision(3);
+        for value in 1..=100 {
+            result.stats.record(value as f64);
+        }
+        result.stats.calculate(&StatsConfig::default());
+        result.iterations = 100;
+        BenchmarkSuite {
+            schema_version: 0,
+            system_info: SystemInfo::current(None),
+            labels: Default::default(),
+            seed,
+            shuffle_seed: None,
+            deterministic_seed: None,
+            results: vec![result, BenchmarkResult::skipped("skipped", "no network".to_string())],
+        }
+    }
+
+    // Two runs in one database: every benchmark and distribution row belongs
+    // to its own run, and quotes in names are stored as they are
+    #[test]
+    fn runs_are_added_to_the_database() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        assert_eq!(insert(&mut connection, &suite(1)).unwrap(), 1);
+        assert_eq!(insert(&mut connection, &suite(2)).unwrap(), 2);
+        let mut query = connection.prepare(
+            "SELECT r.seed, b.name, b.status, b.mean, (SELECT sum(count) FROM distribution d WHERE d.run_id = r.id AND d.benchmark = b.name) \
+             FROM runs r JOIN benchmarks b ON b.run_id = r.id ORDER BY r.id, b.name").unwrap();
+        // seed, name, status, mean, samples
+        type Row = (i64, String, String, Option<f64>, Option<i64>);
+        let rows: Vec<Row> = query
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let spawn = |seed| (seed, "it's spawn".to_string(), "completed".to_string(), Some(50.5), Some(100));
+        let skipped = |seed| (seed, "skipped".to_string(), "skipped".to_string(), Some(0.0), None);
+        assert_eq!(rows, [spawn(1), skipped(1), spawn(2), skipped(2)]);
+        let index: String = connection
+            .query_row("SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'distribution'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(index, "distribution_by_benchmark");
+    }
+}
@@ -0,0 +1,1194 @@
+// Statistics helpers shared by the runner and the result tooling.
+
+use serde::{Deserialize, Serialize};
+
+// Log-linear sample histogram using HdrHistogram's bucket layout with a unit
+// of 1: values are kept to the configured number of significant digits (3 by
+// default, i.e. within 0.1%), so memory stays bounded however many samples
+// are recorded, and two histograms of the same precision line up bucket for
+// bucket. Results from separate workers or processes can therefore be merged
+// (or a cumulative snapshot subtracted from a later one) without going back to
+// the raw samples. Count, min, max, sum, mean and variance are tracked
+// exactly alongside the buckets: the sum with Neumaier compensation and the
+// mean and variance with Welford's updates, so runs of many millions of
+// samples don't lose precision to rounding or cancellation.
+//
+// Serialized sparsely as (bucket index, count) pairs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SparseHistogram", into = "SparseHistogram")]
+pub struct Histogram {
+    significant_digits: u32,
+    sub_bucket_count_magnitude: u32,
+    counts: Vec<u64>,
+    total_count: u64,
+    min: f64,
+    max: f64,
+    sum: CompensatedSum,
+    mean: f64,
+    // Sum of squared deviations from the mean
+    m2: f64,
+}
+
+pub const DEFAULT_SIGNIFICANT_DIGITS: u32 = 3;
+pub const MAX_SIGNIFICANT_DIGITS: u32 = 5;
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::with_precision(DEFAULT_SIGNIFICANT_DIGITS)
+    }
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 1 to 5 significant digits; each extra digit costs about 8x the memory
+    pub fn with_precision(significant_digits: u32) -> Self {
+        assert!((1..=MAX_SIGNIFICANT_DIGITS).contains(&significant_digits), "histogram precision must be 1-5 digits");
+        // 2 * 10^digits rounded up to a power of two
+        let largest_single_unit_value = 2 * 10u64.pow(significant_digits);
+        Self {
+            significant_digits,
+            sub_bucket_count_magnitude: 64 - (largest_single_unit_value - 1).leading_zeros(),
+            counts: Vec::new(),
+            total_count: 0,
+            min: 0.0,
+            max: 0.0,
+            sum: CompensatedSum::default(),
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    pub fn significant_digits(&self) -> u32 {
+        self.significant_digits
+    }
+
+    fn index_of(&self, value: u64) -> usize {
+        let magnitude = self.sub_bucket_count_magnitude;
+        let half_count_magnitude = magnitude - 1;
+        let pow2_ceiling = 64 - (value | ((1 << magnitude) - 1)).leading_zeros();
+        let bucket_index = pow2_ceiling - magnitude;
+        let sub_bucket_index = (value >> bucket_index) as usize;
+        ((bucket_index as usize + 1) << half_count_magnitude) + sub_bucket_index - (1 << half_count_magnitude)
+    }
+
+    // Lowest value that lands in bucket `index`, and the width of the bucket
+    fn bucket_range(&self, index: usize) -> (u64, u64) {
+        let half_count_magnitude = self.sub_bucket_count_magnitude - 1;
+        let half_count = 1 << half_count_magnitude;
+        let bucket_index = (index >> half_count_magnitude) as i64 - 1;
+        let mut sub_bucket_index = (index & (half_count - 1)) + half_count;
+        let bucket_index = if bucket_index < 0 {
+            sub_bucket_index -= half_count;
+            0
+        } else {
+            bucket_index as u32
+        };
+        ((sub_bucket_index as u64) << bucket_index, 1 << bucket_index)
+    }
+
+    pub fn record(&mut self, value: f64) {
+        self.record_n(value, 1);
+    }
+
+    // `count` samples of the same value, e.g. a t-digest centroid
+    pub fn record_n(&mut self, value: f64, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let value = value.max(0.0);
+        self.add_to_bucket(value.round() as u64, count);
+        if self.total_count == count {
+            (self.min, self.max) = (value, value);
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum.add(value * count as f64);
+        // Welford's update for `count` copies of the value
+        let delta = value - self.mean;
+        self.mean += delta * count as f64 / self.total_count as f64;
+        self.m2 += delta * (value - self.mean) * count as f64;
+    }
+
+    fn add_to_bucket(&mut self, value: u64, count: u64) {
+        let index = self.index_of(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += count;
+        self.total_count += count;
+    }
+
+    // Histograms of different precision are merged by re-bucketing the other
+    // side's bucket midpoints
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.is_empty() {
+            return;
+        }
+        let was_empty = self.is_empty();
+        let own_count = self.total_count;
+        if other.significant_digits == self.significant_digits {
+            if other.counts.len() > self.counts.len() {
+                self.counts.resize(other.counts.len(), 0);
+            }
+            for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+                *count += other_count;
+            }
+            self.total_count += other.total_count;
+        } else {
+            for (value, count) in other.midpoints() {
+                self.add_to_bucket(value.round() as u64, count);
+            }
+        }
+        if was_empty {
+            (self.min, self.max) = (other.min, other.max);
+        } else {
+            self.min = self.min.min(other.min);
+            self.max = self.max.max(other.max);
+        }
+        self.sum.add_sum(&other.sum);
+        // Chan et al.'s pairwise combination of the two means and variances
+        let total = self.total_count as f64;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.total_count as f64 / total;
+        self.m2 += other.m2 + delta * delta * own_count as f64 * other.total_count as f64 / total;
+    }
+
+    // Removes an earlier snapshot of the same recording. Fails without
+    // modifying `self` if `other` holds samples that `self` does not. The
+    // exact min and max are lost; they fall back to the bucket bounds.
+    pub fn subtract(&mut self, other: &Histogram) -> Result<(), String> {
+        if other.significant_digits != self.significant_digits {
+            return Err(format!("cannot subtract a {}-digit histogram from a {}-digit one",
+                other.significant_digits, self.significant_digits));
+        }
+        for (index, &other_count) in other.counts.iter().enumerate() {
+            if other_count > self.counts.get(index).copied().unwrap_or(0) {
+                let (value, _) = self.bucket_range(index);
+                return Err(format!("cannot subtract {} samples around {} from a histogram holding fewer", other_count, value));
+            }
+        }
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count -= other_count;
+        }
+        let combined_count = self.total_count as f64;
+        self.total_count -= other.total_count;
+        if self.is_empty() {
+            (self.min, self.max, self.sum, self.mean, self.m2) = (0.0, 0.0, CompensatedSum::default(), 0.0, 0.0);
+        } else {
+            // The pairwise combination in merge() run backwards
+            let remaining = self.total_count as f64;
+            self.sum.subtract_sum(&other.sum);
+            if self.sum.value() < 0.0 {
+                self.sum = CompensatedSum::default();
+            }
+            let mean = (self.mean * combined_count - other.mean * other.total_count as f64) / remaining;
+            let delta = other.mean - mean;
+            self.m2 = (self.m2 - other.m2 - delta * delta * remaining * other.total_count as f64 / combined_count).max(0.0);
+            self.mean = mean.max(0.0);
+            self.reset_bounds_from_buckets();
+        }
+        Ok(())
+    }
+
+    fn reset_bounds_from_buckets(&mut self) {
+        let min = self.buckets().next().map_or(0.0, |(lowest, _, _)| lowest as f64);
+        let max = self.buckets().last().map_or(0.0, |(lowest, width, _)| (lowest + width - 1) as f64);
+        (self.min, self.max) = (min, max);
+    }
+
+    pub fn len(&self) -> u64 {
+        self.total_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_count == 0
+    }
+
+    fn buckets(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.counts.iter().enumerate().filter(|(_, &count)| count > 0).map(|(index, &count)| {
+            let (lowest, width) = self.bucket_range(index);
+            (lowest, width, count)
+        })
+    }
+
+    // Width of the bucket `value` falls in: the smallest difference the
+    // histogram resolves around it, at least one unit
+    pub fn bucket_width(&self, value: f64) -> f64 {
+        self.bucket_range(self.index_of(value.max(0.0).round() as u64)).1 as f64
+    }
+
+    // Non-empty buckets as (midpoint, count), lowest first. Buckets of width
+    // 1 hold a single integer value, which is returned as is.
+    pub fn midpoints(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.buckets().map(|(lowest, width, count)| (lowest as f64 + (width - 1) as f64 / 2.0, count))
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum.value()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    // Population standard deviation
+    pub fn stddev(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        (self.m2 / self.total_count as f64).max(0.0).sqrt()
+    }
+
+    // Quantile `q` (0-1) with the same closest-ranks interpolation as
+    // quantile(), locating the rank inside its bucket; exact for values
+    // below 2 * 10^digits
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let rank = q.clamp(0.0, 1.0) * (self.total_count - 1) as f64;
+        let mut seen = 0u64;
+        for (lowest, width, count) in self.buckets() {
+            if (seen + count) as f64 > rank {
+                let value = if width == 1 {
+                    lowest as f64
+                } else {
+                    lowest as f64 + width as f64 * (rank - seen as f64 + 0.5) / count as f64
+                };
+                return value.clamp(self.min, self.max);
+            }
+            seen += count;
+        }
+        self.max
+    }
+
+    // Median of |x - median| over the samples, taking each bucket at its
+    // midpoint: a spread that, unlike the standard deviation, a few extreme
+    // samples can't move
+    pub fn median_absolute_deviation(&self, median: f64) -> f64 {
+        let mut deviations: Vec<(f64, u64)> = self.midpoints().map(|(x, count)| ((x - median).abs(), count)).collect();
+        deviations.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let half = self.total_count.div_ceil(2);
+        let mut seen = 0;
+        deviations.iter().find(|(_, count)| {
+            seen += count;
+            seen >= half
+        }).map_or(0.0, |(deviation, _)| *deviation)
+    }
+
+    // Share of the samples at or below `value`, assuming the integers in the
+    // bucket holding it are equally likely; exact below 2 * 10^digits
+    pub fn fraction_at_or_below(&self, value: f64) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let mut below = 0.0;
+        for (lowest, width, count) in self.buckets() {
+            if lowest as f64 > value {
+                break;
+            }
+            let covered = ((value.floor() - lowest as f64 + 1.0) / width as f64).min(1.0);
+            below += count as f64 * covered;
+        }
+        below / self.total_count as f64
+    }
+
+    // The classic HdrHistogram percentile distribution text (.hgrm), as
+    // written by outputPercentileDistribution() in the Java implementation
+    // with 5 reporting ticks per half distance. Values are divided by
+    // `value_scale` on output.
+    pub fn percentile_spectrum(&self, value_scale: f64) -> String {
+        const TICKS_PER_HALF_DISTANCE: f64 = 5.0;
+        let digits = self.significant_digits as usize;
+        let mut out = format!("{:>12} {:>14} {:>10} {:>14}\n\n", "Value", "Percentile", "TotalCount", "1/(1-Percentile)");
+        let mut level = 0.0f64;
+        let mut seen = 0u64;
+        let mut highest = 0.0;
+        for (lowest, width, count) in self.buckets() {
+            seen += count;
+            highest = (lowest + width - 1) as f64 / value_scale;
+            let reached = 100.0 * seen as f64 / self.total_count as f64;
+            while level <= reached {
+                out.push_str(&format!("{:>12.digits$} {:2.12} {:>10} {:>14.2}\n",
+                    highest, level / 100.0, seen, 1.0 / (1.0 - level / 100.0)));
+                // The last bucket reports once, then the final 100% line
+                if seen == self.total_count {
+                    break;
+                }
+                let half_distances = (100.0 / (100.0 - level)).log2().floor() + 1.0;
+                level += 100.0 / (TICKS_PER_HALF_DISTANCE * 2f64.powf(half_distances));
+            }
+        }
+        if !self.is_empty() {
+            out.push_str(&format!("{:>12.digits$} {:2.12} {:>10}\n", highest, 1.0, self.total_count));
+        }
+        let sub_bucket_count = 1u64 << self.sub_bucket_count_magnitude;
+        let bucket_count = self.counts.len().div_ceil(1 << (self.sub_bucket_count_magnitude - 1)).max(1);
+        out.push_str(&format!("#[Mean    = {:>12.digits$}, StdDeviation   = {:>12.digits$}]\n",
+            self.mean() / value_scale, self.stddev() / value_scale));
+        out.push_str(&format!("#[Max     = {:>12.digits$}, Total count    = {:>12}]\n", self.max / value_scale, self.total_count));
+        out.push_str(&format!("#[Buckets = {:>12}, SubBuckets     = {:>12}]\n", bucket_count, sub_bucket_count));
+        out
+    }
+
+    // Counts regrouped into log-scaled buckets, `per_octave` of them per
+    // doubling, from the bucket holding the minimum to the one holding the
+    // maximum with empty ones included, so the list plots as is. Bucket i
+    // spans [2^(i/per_octave), 2^((i+1)/per_octave)); values below 1 share a
+    // [0, 1) bucket. Samples are placed by the midpoint of their bucket here.
+    pub fn log_buckets(&self, per_octave: u32) -> Vec<LogBucket> {
+        let per_octave = per_octave.max(1) as f64;
+        let index = |value: f64| if value < 1.0 { -1 } else { (value.log2() * per_octave).floor() as i64 };
+        let bound = |i: i64| if i < 0 { 0.0 } else { 2f64.powf(i as f64 / per_octave) };
+        let mut counts: Vec<(i64, u64)> = Vec::new();
+        for (value, count) in self.midpoints() {
+            let i = index(value);
+            match counts.last_mut() {
+                Some((last, total)) if *last == i => *total += count,
+                _ => counts.push((i, count)),
+            }
+        }
+        let (Some(&(first, _)), Some(&(last, _))) = (counts.first(), counts.last()) else {
+            return Vec::new();
+        };
+        (first..=last)
+            .map(|i| LogBucket {
+                lower: bound(i),
+                upper: bound(i + 1),
+                count: counts.iter().find(|(j, _)| *j == i).map_or(0, |&(_, count)| count),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SparseHistogram {
+    #[serde(default = "default_significant_digits")]
+    significant_digits: u32,
+    counts: Vec<(usize, u64)>,
+    // Absent in files written before these were tracked exactly
+    #[serde(default)]
+    min: Option<f64>,
+    #[serde(default)]
+    max: Option<f64>,
+    #[serde(default)]
+    sum: Option<f64>,
+    #[serde(default)]
+    sum_squares: Option<f64>,
+    // Absent in files written before the variance was tracked with Welford's
+    // method; derived from the sum of squares then
+    #[serde(default)]
+    mean: Option<f64>,
+    #[serde(default)]
+    m2: Option<f64>,
+}
+
+fn default_significant_digits() -> u32 {
+    DEFAULT_SIGNIFICANT_DIGITS
+}
+
+impl From<Histogram> for SparseHistogram {
+    fn from(histogram: Histogram) -> Self {
+        let counts = histogram.counts.iter().enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(index, &count)| (index, count))
+            .collect();
+        Self {
+            significant_digits: histogram.significant_digits,
+            counts,
+            min: Some(histogram.min),
+            max: Some(histogram.max),
+            sum: Some(histogram.sum.value()),
+            sum_squares: Some(histogram.m2 + histogram.mean * histogram.mean * histogram.total_count as f64),
+            mean: Some(histogram.mean),
+            m2: Some(histogram.m2),
+        }
+    }
+}
+
+impl From<SparseHistogram> for Histogram {
+    fn from(sparse: SparseHistogram) -> Self {
+        let digits = sparse.significant_digits.clamp(1, MAX_SIGNIFICANT_DIGITS);
+        let mut histogram = Histogram::with_precision(digits);
+        for (index, count) in sparse.counts {
+            let (value, _) = histogram.bucket_range(index);
+            histogram.add_to_bucket(value, count);
+        }
+        histogram.reset_bounds_from_buckets();
+        let midpoints: Vec<(f64, u64)> = histogram.midpoints().collect();
+        let (bucket_mean, bucket_stddev) = mean_and_stddev(&midpoints);
+        let n = histogram.total_count as f64;
+        histogram.min = sparse.min.unwrap_or(histogram.min);
+        histogram.max = sparse.max.unwrap_or(histogram.max);
+        let sum = sparse.sum.unwrap_or(bucket_mean * n);
+        histogram.sum = CompensatedSum::from(sum);
+        histogram.mean = sparse.mean.unwrap_or(if n > 0.0 { sum / n } else { 0.0 });
+        histogram.m2 = match (sparse.m2, sparse.sum_squares) {
+            (Some(m2), _) => m2,
+            (None, Some(sum_squares)) => (sum_squares - histogram.mean * sum).max(0.0),
+            (None, None) => bucket_stddev * bucket_stddev * n,
+        };
+        histogram
+    }
+}
+
+// Neumaier's variant of Kahan summation: the low-order bits lost by each
+// addition are carried in a separate term, so the error stays at a few ulps
+// of the total instead of growing with the number of terms
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl From<f64> for CompensatedSum {
+    fn from(value: f64) -> Self {
+        Self { sum: value, compensation: 0.0 }
+    }
+}
+
+impl CompensatedSum {
+    pub fn add(&mut self, value: f64) {
+        let total = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - total) + value;
+        } else {
+            self.compensation += (value - total) + self.sum;
+        }
+        self.sum = total;
+    }
+
+    pub fn add_sum(&mut self, other: &CompensatedSum) {
+        self.add(other.sum);
+        self.add(other.compensation);
+    }
+
+    pub fn subtract_sum(&mut self, other: &CompensatedSum) {
+        self.add(-other.sum);
+        self.add(-other.compensation);
+    }
+
+    pub fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+impl std::iter::Sum<f64> for CompensatedSum {
+    fn sum<I: Iterator<Item = f64>>(iter: I) -> Self {
+        let mut total = CompensatedSum::default();
+        for value in iter {
+            total.add(value);
+        }
+        total
+    }
+}
+
+// Minimum of every `batch_size` consecutive samples: a low-noise estimator
+// for CPU-bound code, since interference only ever adds time and each batch
+// is likely to hold at least one undisturbed sample. A trailing partial batch
+// is left out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatchMinima {
+    pub batch_size: usize,
+    pub batches: u64,
+    pub mean: f64,
+    pub median: f64,
+    // The batch minima, mergeable across workers like the samples
+    pub histogram: Histogram,
+    // Minimum and size of the batch being filled
+    #[serde(skip)]
+    current: Option<(f64, usize)>,
+}
+
+impl BatchMinima {
+    pub fn new(batch_size: usize, significant_digits: u32) -> Self {
+        Self {
+            batch_size,
+            batches: 0,
+            mean: 0.0,
+            median: 0.0,
+            histogram: Histogram::with_precision(significant_digits),
+            current: None,
+        }
+    }
+
+    pub fn record(&mut self, value: f64) {
+        let (min, filled) = match self.current {
+            Some((min, filled)) => (min.min(value), filled + 1),
+            None => (value, 1),
+        };
+        if filled >= self.batch_size {
+            self.histogram.record(min);
+            self.current = None;
+        } else {
+            self.current = Some((min, filled));
+        }
+    }
+
+    // Minima of batches of different sizes don't estimate the same thing, so
+    // those are refused
+    pub fn merge(&mut self, other: &BatchMinima) -> bool {
+        if other.batch_size != self.batch_size {
+            return false;
+        }
+        self.histogram.merge(&other.histogram);
+        true
+    }
+
+    pub fn calculate(&mut self) {
+        self.batches = self.histogram.len();
+        self.mean = self.histogram.mean();
+        self.median = self.histogram.quantile(0.5);
+    }
+}
+
+// Quantile `q` (0-1) of already sorted samples, linearly interpolated between
+// the two closest ranks (Hyndman & Fan type 7, as in R and NumPy).
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        1 => sorted[0],
+        n => {
+            let rank = q.clamp(0.0, 1.0) * (n - 1) as f64;
+            let lower = rank.floor() as usize;
+            let upper = (lower + 1).min(n - 1);
+            sorted[lower] + (rank - lower as f64) * (sorted[upper] - sorted[lower])
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutlierMethod {
+    // Beyond 1.5 (mild) or 3 (severe) interquartile ranges outside the quartiles
+    #[default]
+    Tukey,
+    // Modified z-score 0.6745 * |x - median| / MAD above 3.5 (mild) or 5 (severe)
+    Mad,
+}
+
+impl OutlierMethod {
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "tukey" => Some(Self::Tukey),
+            "mad" => Some(Self::Mad),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Tukey => "tukey",
+            Self::Mad => "mad",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierClass {
+    Normal,
+    LowMild,
+    LowSevere,
+    HighMild,
+    HighSevere,
+}
+
+// Thresholds derived from one set of samples
+#[derive(Debug, Clone, Copy)]
+pub struct OutlierFences {
+    low_severe: f64,
+    low_mild: f64,
+    high_mild: f64,
+    high_severe: f64,
+}
+
+impl OutlierFences {
+    pub fn new(histogram: &Histogram, method: OutlierMethod) -> Self {
+        let (center_low, center_high, spread, mild, severe) = match method {
+            OutlierMethod::Tukey => {
+                let q1 = histogram.quantile(0.25);
+                let q3 = histogram.quantile(0.75);
+                (q1, q3, q3 - q1, 1.5, 3.0)
+            }
+            OutlierMethod::Mad => {
+                let median = histogram.quantile(0.5);
+                let mad = histogram.median_absolute_deviation(median);
+                // A zero MAD (over half the samples identical) flags nothing
+                let scale = if mad > 0.0 { mad / 0.6745 } else { f64::INFINITY };
+                (median, median, scale, 3.5, 5.0)
+            }
+        };
+        Self {
+            low_severe: center_low - severe * spread,
+            low_mild: center_low - mild * spread,
+            high_mild: center_high + mild * spread,
+            high_severe: center_high + severe * spread,
+        }
+    }
+
+    // [low severe, low mild, high mild, high severe]
+    pub fn bounds(&self) -> [f64; 4] {
+        [self.low_severe, self.low_mild, self.high_mild, self.high_severe]
+    }
+
+    pub fn classify(&self, value: f64) -> OutlierClass {
+        if value < self.low_severe {
+            OutlierClass::LowSevere
+        } else if value < self.low_mild {
+            OutlierClass::LowMild
+        } else if value > self.high_severe {
+            OutlierClass::HighSevere
+        } else if value > self.high_mild {
+            OutlierClass::HighMild
+        } else {
+            OutlierClass::Normal
+        }
+    }
+}
+
+// Mean and population standard deviation of (value, count) pairs
+pub fn mean_and_stddev(values: &[(f64, u64)]) -> (f64, f64) {
+    let n: u64 = values.iter().map(|(_, count)| count).sum();
+    if n == 0 {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().map(|(x, count)| x * *count as f64).sum::<CompensatedSum>().value() / n as f64;
+    let variance = values.iter().map(|(x, count)| (x - mean).powi(2) * *count as f64).sum::<CompensatedSum>().value() / n as f64;
+    (mean, variance.sqrt())
+}
+
+// Skewness and excess kurtosis (population moments) of (value, count)
+// pairs: 0 and 0 for a normal distribution, both well above for latencies
+// with a long right tail
+pub fn skewness_and_kurtosis(values: &[(f64, u64)]) -> (f64, f64) {
+    let (mean, stddev) = mean_and_stddev(values);
+    let n: u64 = values.iter().map(|(_, count)| count).sum();
+    if n == 0 || stddev == 0.0 {
+        return (0.0, 0.0);
+    }
+    let moment = |power: i32| values.iter()
+        .map(|(x, count)| ((x - mean) / stddev).powi(power) * *count as f64)
+        .sum::<CompensatedSum>()
+        .value() / n as f64;
+    (moment(3), moment(4) - 3.0)
+}
+
+// Two-sided 95% critical value of Student's t with `df` degrees of freedom:
+// tabulated up to 4, then a Cornish-Fisher expansion around the normal 1.96
+// (within 0.2% from 5 on)
+pub fn t_critical_95(df: u64) -> f64 {
+    const TABLE: [f64; 4] = [12.706, 4.303, 3.182, 2.776];
+    if df == 0 {
+        return f64::INFINITY;
+    }
+    if let Some(&t) = TABLE.get(df as usize - 1) {
+        return t;
+    }
+    let (z, df) = (1.959964_f64, df as f64);
+    z + (z.powi(3) + z) / (4.0 * df)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * df.powi(2))
+        + (3.0 * z.powi(7) + 19.0 * z.powi(5) + 17.0 * z.powi(3) - 15.0 * z) / (384.0 * df.powi(3))
+}
+
+// Welch's unequal-variances t-test of b's mean against a's, each given as
+// (mean, population standard deviation, samples). None with fewer than two
+// samples on either side.
+#[derive(Debug, Clone, Copy)]
+pub struct WelchTest {
+    pub t: f64,
+    // Welch-Satterthwaite degrees of freedom
+    pub df: f64,
+    // Standard error of the difference of the means
+    pub se: f64,
+}
+
+pub fn welch_t_test(a: (f64, f64, u64), b: (f64, f64, u64)) -> Option<WelchTest> {
+    let ((mean_a, stddev_a, n_a), (mean_b, stddev_b, n_b)) = (a, b);
+    if n_a < 2 || n_b < 2 {
+        return None;
+    }
+    // Variance of each mean, from the sample variance n/(n-1) stddev²
+    let var_a = stddev_a.powi(2) / (n_a - 1) as f64;
+    let var_b = stddev_b.powi(2) / (n_b - 1) as f64;
+    let se = (var_a + var_b).sqrt();
+    let difference = mean_b - mean_a;
+    if se == 0.0 {
+        let t = if difference == 0.0 { 0.0 } else { difference.signum() * f64::INFINITY };
+        return Some(WelchTest { t, df: f64::INFINITY, se });
+    }
+    let df = (var_a + var_b).powi(2) / (var_a.powi(2) / (n_a - 1) as f64 + var_b.powi(2) / (n_b - 1) as f64);
+    Some(WelchTest { t: difference / se, df, se })
+}
+
+impl WelchTest {
+    pub fn p_value(&self) -> f64 {
+        t_two_sided_p(self.t, self.df)
+    }
+}
+
+// Two-sided p-value of Student's t with `df` (possibly fractional) degrees
+// of freedom: I_{df/(df+t²)}(df/2, 1/2)
+pub fn t_two_sided_p(t: f64, df: f64) -> f64 {
+    if t.is_infinite() {
+        return 0.0;
+    }
+    if t.is_nan() || df <= 0.0 {
+        return 1.0;
+    }
+    // Beyond this the distribution is the normal to within double precision
+    let df = df.min(1e10);
+    regularized_incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+// I_x(a, b) by the continued fraction of Numerical Recipes' betacf, on the
+// side of the symmetry relation where it converges quickly
+fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Modified Lentz's method
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    d = 1.0 / if d.abs() < TINY { TINY } else { d };
+    let mut fraction = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            d = 1.0 / if d.abs() < TINY { TINY } else { d };
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            fraction *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    fraction
+}
+
+// ln Γ(x) for x > 0, Lanczos approximation (g = 7, 9 terms)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8, 771.323_428_777_653_1,
+        -176.615_029_162_140_6, 12.507_343_278_686_905, -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection formula
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut series = COEFFICIENTS[0];
+    for (i, &coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        series += coefficient / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+// Samples per run for the 95% confidence interval of the difference between
+// two runs' means to be within `difference` (a fraction of the mean), both
+// runs having coefficient of variation `cv`: n = 2 (1.96 cv / difference)²
+pub fn samples_to_resolve(cv: f64, difference: f64) -> u64 {
+    let n = 2.0 * (1.959964 * cv / difference).powi(2);
+    (n.ceil() as u64).max(2)
+}
+
+// Smallest relative difference between two such runs of `n` samples each
+// that the 95% confidence interval excludes zero for
+pub fn resolvable_difference(cv: f64, n: u64) -> f64 {
+    if n == 0 {
+        return f64::INFINITY;
+    }
+    1.959964 * cv * (2.0 / n as f64).sqrt()
+}
+
+// Ordinary least squares fit of y = intercept + slope * x, returning
+// (slope, intercept, r_squared)
+pub fn linear_fit(points: &[(f64, f64)]) -> (f64, f64, f64) {
+    if points.len() < 2 {
+        return (0.0, 0.0, 0.0);
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    if sxx == 0.0 {
+        return (0.0, mean_y, 0.0);
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let total: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    let residual: f64 = points.iter().map(|(x, y)| (y - intercept - slope * x).powi(2)).sum();
+    let r_squared = if total > 0.0 { 1.0 - residual / total } else { 1.0 };
+    (slope, intercept, r_squared)
+}
+
+// Modes of the distribution, lowest first, from a Gaussian kernel density
+// estimate over log2 of the values (Silverman's bandwidth, floored so bucket
+// quantization can't create bumps). Neighbouring bumps separated by a valley
+// above MAX_VALLEY_RATIO of the lower peak count as one; bumps holding less
+// than MIN_MODE_MASS of the samples are dropped. Several modes mean the mean
+// lies between the typical values rather than on one of them.
+pub fn modes(histogram: &Histogram) -> Vec<f64> {
+    const GRID: usize = 512;
+    const MIN_BANDWIDTH: f64 = 0.05;
+    const MAX_VALLEY_RATIO: f64 = 0.7;
+    const MIN_MODE_MASS: f64 = 0.05;
+
+    let points: Vec<(f64, u64)> = histogram.midpoints().map(|(value, count)| (value.max(1.0).log2(), count)).collect();
+    let (mean, stddev) = mean_and_stddev(&points);
+    if points.len() < 2 || stddev == 0.0 {
+        return if histogram.is_empty() { Vec::new() } else { vec![2f64.powf(mean)] };
+    }
+    let bandwidth = (1.06 * stddev * (histogram.len() as f64).powf(-0.2)).max(MIN_BANDWIDTH);
+    let low = points[0].0 - 3.0 * bandwidth;
+    let step = (points[points.len() - 1].0 + 3.0 * bandwidth - low) / (GRID - 1) as f64;
+    let grid = |i: usize| low + i as f64 * step;
+    let density: Vec<f64> = (0..GRID)
+        .map(|i| {
+            points
+                .iter()
+                .map(|&(x, count)| count as f64 * (-0.5 * ((grid(i) - x) / bandwidth).powi(2)).exp())
+                .sum()
+        })
+        .collect();
+
+    // Bumps as (peak index, first index, last index), split at local minima
+    let mut bumps: Vec<(usize, usize, usize)> = Vec::new();
+    let mut start = 0;
+    for i in 1..GRID {
+        let is_valley = i + 1 < GRID && density[i] < density[i - 1] && density[i] <= density[i + 1];
+        if is_valley || i + 1 == GRID {
+            let end = if is_valley { i - 1 } else { i };
+            let peak = (start..=end).max_by(|&a, &b| density[a].total_cmp(&density[b])).unwrap_or(start);
+            bumps.push((peak, start, end));
+            start = i;
+        }
+    }
+
+    // Merge across shallow valleys, shallowest first
+    loop {
+        let shallowest = (1..bumps.len())
+            .map(|j| {
+                let valley = density[bumps[j].1];
+                (j, valley / density[bumps[j - 1].0].min(density[bumps[j].0]))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((j, _)) = shallowest.filter(|&(_, ratio)| ratio > MAX_VALLEY_RATIO) else {
+            break;
+        };
+        let (left, right) = (bumps[j - 1], bumps.remove(j));
+        let peak = if density[right.0] > density[left.0] { right.0 } else { left.0 };
+        bumps[j - 1] = (peak, left.1, right.2);
+    }
+
+    let total: f64 = density.iter().sum();
+    bumps
+        .iter()
+        .filter(|&&(_, first, last)| density[first..=last].iter().sum::<f64>() >= MIN_MODE_MASS * total)
+        .map(|&(peak, _, _)| 2f64.powf(grid(peak)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BenchRng, BenchmarkStats, SampleTimeline, StatsConfig};
+
+    fn histogram_of(values: impl IntoIterator<Item = u64>, digits: u32) -> Histogram {
+        let mut histogram = Histogram::with_precision(digits);
+        for value in values {
+            histogram.record(value as f64);
+        }
+        histogram
+    }
+
+    #[test]
+    fn bucket_layout_matches_hdr_histogram() {
+        // 3 digits: 2 * 10^3 rounds up to 2048 sub-buckets, so values below
+        // 2048 get a bucket each and the next octave buckets of width 2
+        let histogram = Histogram::with_precision(3);
+        assert_eq!(histogram.bucket_range(histogram.index_of(2047)), (2047, 1));
+        assert_eq!(histogram.bucket_range(histogram.index_of(2048)), (2048, 2));
+        assert_eq!(histogram.bucket_range(histogram.index_of(2049)), (2048, 2));
+        assert_eq!(histogram.bucket_range(histogram.index_of(4096)), (4096, 4));
+        for digits in 1..=MAX_SIGNIFICANT_DIGITS {
+            let histogram = Histogram::with_precision(digits);
+            for value in (0..40).map(|shift| (1u64 << shift) + 12_345 % (1u64 << shift).max(1)) {
+                let (lowest, width) = histogram.bucket_range(histogram.index_of(value));
+                assert!(lowest <= value && value < lowest + width, "{} digits: {} outside [{}, +{})", digits, value, lowest, width);
+                assert!(width == 1 || (width as f64) / (lowest as f64) <= 10f64.powi(-(digits as i32)), "{} digits: width {} at {}", digits, width, lowest);
+            }
+        }
+    }
+
+    #[test]
+    fn percentiles_of_a_sequence_stay_within_the_precision() {
+        let n = 100_000u64;
+        for digits in 2..=4 {
+            let histogram = histogram_of(1..=n, digits);
+            for q in [0.0, 0.01, 0.25, 0.5, 0.9, 0.99, 0.999, 1.0] {
+                // Type 7 over 1..=n
+                let exact = 1.0 + q * (n - 1) as f64;
+                let estimate = histogram.quantile(q);
+                let tolerance = (exact * 10f64.powi(-(digits as i32))).max(1.0);
+                assert!((estimate - exact).abs() <= tolerance, "{} digits, q {}: {} vs {}", digits, q, estimate, exact);
+            }
+        }
+    }
+
+    #[test]
+    fn moments_are_exact_whatever_the_buckets() {
+        let n = 100_000u64;
+        let histogram = histogram_of(1..=n, 2);
+        assert_eq!(histogram.len(), n);
+        assert_eq!((histogram.min(), histogram.max()), (1.0, n as f64));
+        assert_eq!(histogram.sum(), (n * (n + 1) / 2) as f64);
+        assert!((histogram.mean() - (n + 1) as f64 / 2.0).abs() < 1e-9);
+        let variance = ((n * n - 1) as f64) / 12.0;
+        assert!((histogram.stddev() - variance.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn merge_and_subtract_invert_each_other() {
+        let mut merged = histogram_of(1..=5_000, 3);
+        merged.merge(&histogram_of(5_001..=10_000, 3));
+        let whole = histogram_of(1..=10_000, 3);
+        assert_eq!(merged.counts, whole.counts);
+        assert!((merged.mean() - whole.mean()).abs() < 1e-9);
+        assert!((merged.stddev() - whole.stddev()).abs() < 1e-9);
+
+        merged.subtract(&histogram_of(1..=5_000, 3)).unwrap();
+        let second_half = histogram_of(5_001..=10_000, 3);
+        assert_eq!(merged.counts, second_half.counts);
+        assert!((merged.mean() - second_half.mean()).abs() < 1e-6);
+        assert!((merged.stddev() - second_half.stddev()).abs() < 1e-6);
+        assert!(merged.subtract(&histogram_of([1], 3)).is_err());
+    }
+
+    #[test]
+    fn median_absolute_deviation_of_a_small_set() {
+        // |x - 5| over 1..=9 is 0, 1, 1, 2, 2, 3, 3, 4, 4
+        let histogram = histogram_of(1..=9, 3);
+        assert_eq!(histogram.quantile(0.5), 5.0);
+        assert_eq!(histogram.median_absolute_deviation(5.0), 2.0);
+    }
+
+    #[test]
+    fn tukey_fences_sit_at_one_and_a_half_and_three_iqrs() {
+        // Quartiles of 1..=100 at bucket resolution: 25 and 75, IQR 50
+        let histogram = histogram_of(1..=100, 3);
+        let fences = OutlierFences::new(&histogram, OutlierMethod::Tukey);
+        assert_eq!(fences.bounds(), [-125.0, -50.0, 150.0, 225.0]);
+        assert_eq!(fences.classify(150.0), OutlierClass::Normal);
+        assert_eq!(fences.classify(151.0), OutlierClass::HighMild);
+        assert_eq!(fences.classify(226.0), OutlierClass::HighSevere);
+    }
+
+    #[test]
+    fn mad_fences_use_the_modified_z_score() {
+        // Median 5, MAD 2: z = 0.6745 |x - 5| / 2
+        let histogram = histogram_of(1..=9, 3);
+        let fences = OutlierFences::new(&histogram, OutlierMethod::Mad);
+        let [_, _, high_mild, high_severe] = fences.bounds();
+        assert!((high_mild - (5.0 + 3.5 * 2.0 / 0.6745)).abs() < 1e-9);
+        assert!((high_severe - (5.0 + 5.0 * 2.0 / 0.6745)).abs() < 1e-9);
+        // Mostly identical samples have no spread to judge by
+        let flat = histogram_of([7, 7, 7, 7, 100], 3);
+        assert_eq!(OutlierFences::new(&flat, OutlierMethod::Mad).classify(100.0), OutlierClass::Normal);
+    }
+
+    #[test]
+    fn trim_drops_the_same_count_at_each_end() {
+        let mut stats = BenchmarkStats::with_precision(3);
+        stats.keep_order();
+        for value in 1..=100 {
+            stats.record(value as f64);
+        }
+        assert_eq!(stats.trim(0.1), (10, 10));
+        assert_eq!(stats.histogram.len(), 80);
+        assert_eq!((stats.histogram.min(), stats.histogram.max()), (11.0, 90.0));
+        // Under one sample per end is nothing to trim
+        let mut short = BenchmarkStats::with_precision(3);
+        short.keep_order();
+        for value in 1..=9 {
+            short.record(value as f64);
+        }
+        assert_eq!(short.trim(0.1), (0, 0));
+        assert_eq!(short.histogram.len(), 9);
+    }
+
+    #[test]
+    fn winsorizing_clamps_the_tails_into_the_quantiles() {
+        let mut stats = BenchmarkStats::with_precision(3);
+        for value in 1..=100 {
+            stats.record(value as f64);
+        }
+        stats.record(10_000.0);
+        let config = StatsConfig { winsorize: Some(0.1), ..StatsConfig::default() };
+        stats.calculate(&config);
+        let winsorized = stats.winsorized.clone().unwrap();
+        assert_eq!((winsorized.low_ns, winsorized.high_ns), (11.0, 91.0));
+        // 1..=10 become 11, 92..=100 and the outlier become 91
+        let clamped: f64 = 10.0 * 11.0 + (11..=91).sum::<u64>() as f64 + 10.0 * 91.0;
+        assert!((winsorized.mean_ns - clamped / 101.0).abs() < 1e-9);
+        assert_eq!(stats.mean_ns, winsorized.mean_ns);
+        assert!(winsorized.raw_mean_ns > 100.0);
+    }
+
+    fn stats_of(values: impl IntoIterator<Item = u64>, config: &StatsConfig) -> BenchmarkStats {
+        let mut stats = BenchmarkStats::with_precision(3);
+        for value in values {
+            stats.record(value as f64);
+        }
+        stats.calculate(config);
+        stats
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9 * expected.abs().max(1.0), "{} vs {}", actual, expected);
+    }
+
+    #[test]
+    fn standard_error_of_all_samples() {
+        let stats = stats_of(1..=100, &StatsConfig::default());
+        assert_eq!(stats.samples_used, 100);
+        // Sample standard deviation of 1..=100 over sqrt(100)
+        let sem = (100.0 * 101.0 / 12.0f64).sqrt() / 10.0;
+        assert_close(stats.sem_ns, sem);
+        assert_close(stats.margin_ns, sem * t_critical_95(99));
+    }
+
+    #[test]
+    fn excluded_outliers_leave_the_kept_count() {
+        let config = StatsConfig { exclude_outliers: true, ..StatsConfig::default() };
+        let stats = stats_of((1..=100).chain([10_000]), &config);
+        assert_eq!(stats.outliers.count(), 1);
+        assert_eq!(stats.samples_used, 100);
+        assert_close(stats.mean_ns, 50.5);
+        let sem = (100.0 * 101.0 / 12.0f64).sqrt() / 10.0;
+        assert_close(stats.sem_ns, sem);
+        assert_close(stats.margin_ns, sem * t_critical_95(99));
+        assert_close(stats.resolvable, resolvable_difference(stats.cv, 100));
+    }
+
+    // Yuen: the winsorized variance over h (h - 1), h = n - 2k, with h - 1
+    // degrees of freedom
+    #[test]
+    fn winsorized_error_counts_only_unclamped_samples() {
+        let config = StatsConfig { winsorize: Some(0.1), ..StatsConfig::default() };
+        let stats = stats_of((1..=100).chain([10_000]), &config);
+        let winsorized = stats.winsorized.clone().unwrap();
+        assert_eq!((winsorized.low_clamped, winsorized.high_clamped), (10, 10));
+        assert_eq!(stats.samples_used, 81);
+
+        let clamped: Vec<f64> = (1..=100).chain([10_000]).map(|value| (value as f64).clamp(11.0, 91.0)).collect();
+        let mean = clamped.iter().sum::<f64>() / 101.0;
+        let squares: f64 = clamped.iter().map(|value| (value - mean).powi(2)).sum();
+        let sem = (squares / (81.0 * 80.0)).sqrt();
+        assert_close(stats.sem_ns, sem);
+        assert_close(stats.margin_ns, sem * t_critical_95(80));
+        // Wider than treating all 101 samples as independent
+        assert!(stats.sem_ns > winsorized.stddev_ns / 100f64.sqrt());
+    }
+
+    #[test]
+    fn reservoir_keeps_a_uniform_subset() {
+        let mut timeline = SampleTimeline::start(Some(1_000), BenchRng::new(3));
+        for i in 0..100_000u64 {
+            timeline.record(i, i as f64);
+        }
+        assert_eq!(timeline.samples.len(), 1_000);
+        assert_eq!(timeline.seen, 100_000);
+        let mut per_decile = [0u32; 10];
+        for &(offset, value) in &timeline.samples {
+            assert_eq!(offset as f64, value);
+            per_decile[(offset / 10_000) as usize] += 1;
+        }
+        // 100 expected per decile; 4 standard deviations either way
+        assert!(per_decile.iter().all(|&count| (60..=140).contains(&count)), "{:?}", per_decile);
+    }
+
+    #[test]
+    fn t_critical_values_match_the_published_table() {
+        for (df, table) in [(1, 12.706), (2, 4.303), (4, 2.776), (5, 2.571), (10, 2.228), (20, 2.086), (30, 2.042), (60, 2.000), (120, 1.980)] {
+            let t = t_critical_95(df);
+            assert!((t - table).abs() / table < 0.002, "df {}: {} vs {}", df, t, table);
+        }
+        assert!((t_critical_95(u64::MAX) - 1.960).abs() < 0.001);
+    }
+
+    #[test]
+    fn t_p_values_match_closed_forms_and_tables() {
+        // df 1 is the Cauchy distribution: p = 1 - 2 atan(t) / pi
+        assert!((t_two_sided_p(1.0, 1.0) - 0.5).abs() < 1e-12);
+        // df 2: p = 1 - t / sqrt(2 + t²)
+        assert!((t_two_sided_p(2.0, 2.0) - (1.0 - 2.0 / 6f64.sqrt())).abs() < 1e-12);
+        for (t, df, p) in [(2.0, 5.0, 0.101_939), (3.0, 20.0, 0.007_076), (2.228, 10.0, 0.050_012), (1.5, 30.0, 0.144_066), (0.5, 3.0, 0.651_448)] {
+            assert!((t_two_sided_p(t, df) - p).abs() < 1e-6, "t {} df {}: {}", t, df, t_two_sided_p(t, df));
+        }
+        // The 95% critical values give p = 0.05; a huge df is the normal
+        assert!((t_two_sided_p(2.086, 20.0) - 0.05).abs() < 1e-4);
+        assert!((t_two_sided_p(1.959_964, f64::INFINITY) - 0.05).abs() < 1e-6);
+        assert_eq!(t_two_sided_p(0.0, 10.0), 1.0);
+    }
+
+    #[test]
+    fn welch_test_matches_the_textbook_example() {
+        // Welch's t-test example 1 on Wikipedia: t 2.46, df 25.0, p 0.021
+        let a = [27.5, 21.0, 19.0, 23.6, 17.0, 17.9, 16.9, 20.1, 21.9, 22.6, 23.1, 19.6, 19.0, 21.7, 21.4];
+        let b = [27.1, 22.0, 20.8, 23.4, 23.4, 23.5, 25.8, 22.0, 24.8, 20.2, 21.9, 22.1, 22.9, 20.5, 24.4];
+        let summary = |values: &[f64]| {
+            let (mean, stddev) = mean_and_stddev(&values.iter().map(|&value| (value, 1)).collect::<Vec<_>>());
+            (mean, stddev, values.len() as u64)
+        };
+        let test = welch_t_test(summary(&a), summary(&b)).unwrap();
+        assert!((test.t - 2.455_356).abs() < 1e-5, "t {}", test.t);
+        assert!((test.df - 24.988_53).abs() < 1e-4, "df {}", test.df);
+        assert!((test.p_value() - 0.021_378).abs() < 1e-5, "p {}", test.p_value());
+        assert!(welch_t_test((1.0, 0.0, 1), (2.0, 0.0, 10)).is_none());
+        assert_eq!(welch_t_test((5.0, 0.0, 10), (5.0, 0.0, 10)).unwrap().p_value(), 1.0);
+    }
+}
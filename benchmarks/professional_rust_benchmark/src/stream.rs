@@ -0,0 +1,62 @@
+// Results written out one JSON line each as benchmarks complete (--stream),
+// so a crash or Ctrl-C halfway through a long run loses only the benchmark
+// in progress. The lines are the entries of the results file's "results".
+
+use crate::BenchmarkResult;
+
+pub struct ResultStream {
+    path: String,
+}
+
+impl ResultStream {
+    pub async fn create(path: &str) -> Option<Self> {
+        match tokio::fs::write(path, b"").await {
+            Ok(()) => Some(Self { path: path.to_string() }),
+            Err(e) => {
+                tracing::error!(path = %path, error = %e, "cannot create result stream");
+                None
+            }
+        }
+    }
+
+    pub async fn emit(&self, result: &BenchmarkResult) {
+        let written = match serde_json::to_string(result) {
+            Ok(mut line) => {
+                line.push('\n');
+                crate::append_file(&self.path, line.as_bytes()).await
+            }
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = written {
+            tracing::error!(path = %self.path, benchmark = %result.name, error = %e, "cannot stream result");
+        }
+    }
+}
+
+pub async fn emit(stream: Option<&ResultStream>, result: &BenchmarkResult) {
+    if let Some(stream) = stream {
+        stream.emit(result).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::completed;
+
+    // Every emitted result is one line that reads back as a result
+    #[tokio::test]
+    async fn one_json_line_per_result() {
+        let path = std::env::temp_dir().join(format!("stream-test-{}.jsonl", std::process::id()));
+        let path = path.display().to_string();
+        std::fs::write(&path, "left over from an earlier run\n").unwrap();
+        let stream = ResultStream::create(&path).await.unwrap();
+        emit(Some(&stream), &completed("Spawn", 1..=10)).await;
+        emit(None, &completed("Ignored", 1..=10)).await;
+        emit(Some(&stream), &BenchmarkResult::skipped("Perf", "no perf".to_string())).await;
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let names: Vec<String> = text.lines().map(|line| serde_json::from_str::<BenchmarkResult>(line).unwrap().name).collect();
+        assert_eq!(names, ["Spawn", "Perf"]);
+    }
+}
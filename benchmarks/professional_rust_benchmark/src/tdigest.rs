@@ -0,0 +1,281 @@
+// Merging t-digest (Dunning & Ertl): the distribution as at most about
+// `compression` weighted centroids, small near the tails and large around the
+// median, so extreme quantiles stay accurate in a few KB however many samples
+// are added. Digests from separate workers merge into the digest of all their
+// samples. Count, sum, min and max are tracked exactly.
+//
+// Serialized as [mean, weight] pairs after folding in the unmerged buffer.
+
+use serde::{Deserialize, Serialize};
+
+// Unmerged samples held back before a compression pass, as a multiple of the
+// compression
+const BUFFER_FACTOR: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Centroid(f64, u64);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SerializedDigest", into = "SerializedDigest")]
+pub struct TDigest {
+    compression: f64,
+    // Sorted by mean
+    centroids: Vec<Centroid>,
+    buffer: Vec<Centroid>,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedDigest {
+    compression: f64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    centroids: Vec<Centroid>,
+}
+
+impl From<TDigest> for SerializedDigest {
+    fn from(mut digest: TDigest) -> Self {
+        digest.compress();
+        Self {
+            compression: digest.compression,
+            count: digest.count,
+            sum: digest.sum,
+            min: digest.min,
+            max: digest.max,
+            centroids: digest.centroids,
+        }
+    }
+}
+
+impl From<SerializedDigest> for TDigest {
+    fn from(serialized: SerializedDigest) -> Self {
+        Self {
+            compression: serialized.compression,
+            centroids: serialized.centroids,
+            buffer: Vec::new(),
+            count: serialized.count,
+            sum: serialized.sum,
+            min: serialized.min,
+            max: serialized.max,
+        }
+    }
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        Self { compression, centroids: Vec::new(), buffer: Vec::new(), count: 0, sum: 0.0, min: 0.0, max: 0.0 }
+    }
+
+    pub fn compression(&self) -> f64 {
+        self.compression
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn record(&mut self, value: f64) {
+        self.add(Centroid(value, 1));
+        self.sum += value;
+    }
+
+    fn add(&mut self, centroid: Centroid) {
+        if self.count == 0 {
+            (self.min, self.max) = (centroid.0, centroid.0);
+        } else {
+            self.min = self.min.min(centroid.0);
+            self.max = self.max.max(centroid.0);
+        }
+        self.count += centroid.1;
+        self.buffer.push(centroid);
+        if self.buffer.len() as f64 >= BUFFER_FACTOR * self.compression {
+            self.compress();
+        }
+    }
+
+    pub fn merge(&mut self, other: &TDigest) {
+        if other.is_empty() {
+            return;
+        }
+        let (min, max) = (other.min, other.max);
+        for &centroid in other.centroids.iter().chain(&other.buffer) {
+            self.add(centroid);
+        }
+        self.min = self.min.min(min);
+        self.max = self.max.max(max);
+        self.sum += other.sum;
+        self.compress();
+    }
+
+    // Scale function k2: centroid sizes shrink in proportion to q(1 - q)
+    // toward both ends, which keeps p99.9 and beyond from being averaged
+    // with the extreme outliers of heavy-tailed latencies
+    fn k(&self, q: f64, normalizer: f64) -> f64 {
+        let q = q.clamp(1e-15, 1.0 - 1e-15);
+        self.compression / normalizer * (q / (1.0 - q)).ln()
+    }
+
+    // Folds the buffer into the centroids, merging neighbours while the
+    // merged centroid spans at most one unit of k
+    pub fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut items = std::mem::take(&mut self.centroids);
+        items.append(&mut self.buffer);
+        items.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let total = self.count as f64;
+        let normalizer = 4.0 * (total / self.compression).max(1.0).ln() + 24.0;
+        let mut merged = Vec::with_capacity(items.len().min(2 * self.compression as usize));
+        let mut current = items[0];
+        let mut weight_before = 0.0;
+        for &next in &items[1..] {
+            let proposed = (weight_before + (current.1 + next.1) as f64) / total;
+            if self.k(proposed, normalizer) - self.k(weight_before / total, normalizer) <= 1.0 {
+                let weight = current.1 + next.1;
+                current.0 += (next.0 - current.0) * next.1 as f64 / weight as f64;
+                current.1 = weight;
+            } else {
+                weight_before += current.1 as f64;
+                merged.push(current);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    // Quantile `q` (0-1), interpolating between centroid centres and toward
+    // the exact min and max at the ends
+    pub fn quantile(&mut self, q: f64) -> f64 {
+        self.compress();
+        if self.centroids.is_empty() {
+            return 0.0;
+        }
+        let target = q.clamp(0.0, 1.0) * self.count as f64;
+        let first = self.centroids[0];
+        if target < first.1 as f64 / 2.0 {
+            return self.min + (first.0 - self.min) * target / (first.1 as f64 / 2.0);
+        }
+        let mut seen = 0.0;
+        for pair in self.centroids.windows(2) {
+            let (left, right) = (pair[0], pair[1]);
+            let left_centre = seen + left.1 as f64 / 2.0;
+            let right_centre = seen + left.1 as f64 + right.1 as f64 / 2.0;
+            if target < right_centre {
+                let fraction = (target - left_centre) / (right_centre - left_centre);
+                return left.0 + (right.0 - left.0) * fraction;
+            }
+            seen += left.1 as f64;
+        }
+        let last = self.centroids[self.centroids.len() - 1];
+        let last_centre = seen + last.1 as f64 / 2.0;
+        let fraction = ((target - last_centre) / (last.1 as f64 / 2.0)).min(1.0);
+        last.0 + (self.max - last.0) * fraction
+    }
+
+    // (mean, weight) of every centroid, not in order if not compressed
+    pub fn centroids(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.centroids.iter().chain(&self.buffer).map(|centroid| (centroid.0, centroid.1))
+    }
+
+    pub fn centroid_count(&self) -> usize {
+        self.centroids.len() + self.buffer.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BenchRng;
+
+    // 1..=n in a fixed random order, so compression sees unsorted input
+    fn shuffled(n: u64) -> Vec<f64> {
+        let mut values: Vec<f64> = (1..=n).map(|value| value as f64).collect();
+        let mut rng = BenchRng::new(7);
+        for i in (1..values.len()).rev() {
+            values.swap(i, rng.gen_range(i as u64 + 1) as usize);
+        }
+        values
+    }
+
+    fn digest_of(values: &[f64]) -> TDigest {
+        let mut digest = TDigest::new(100.0);
+        for &value in values {
+            digest.record(value);
+        }
+        digest
+    }
+
+    #[test]
+    fn quantiles_of_uniform_values_are_within_a_fraction_of_a_percent_in_rank() {
+        let n = 100_000;
+        let mut digest = digest_of(&shuffled(n));
+        for q in [0.001, 0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.99, 0.999] {
+            let exact = q * n as f64;
+            let estimate = digest.quantile(q);
+            assert!((estimate - exact).abs() <= 0.005 * n as f64, "q {}: {} vs {}", q, estimate, exact);
+        }
+        assert_eq!(digest.quantile(0.0), 1.0);
+        assert_eq!(digest.quantile(1.0), n as f64);
+    }
+
+    #[test]
+    fn tails_are_resolved_more_finely_than_the_middle() {
+        let n = 100_000;
+        let mut digest = digest_of(&shuffled(n));
+        assert!((digest.quantile(0.9999) - 0.9999 * n as f64).abs() <= 10.0);
+        assert!((digest.quantile(0.0001) - 0.0001 * n as f64).abs() <= 10.0);
+    }
+
+    #[test]
+    fn compression_bounds_the_centroid_count() {
+        let mut digest = digest_of(&shuffled(100_000));
+        digest.compress();
+        assert!(digest.centroid_count() <= 200, "{} centroids", digest.centroid_count());
+        assert_eq!(digest.centroids().map(|(_, weight)| weight).sum::<u64>(), 100_000);
+    }
+
+    #[test]
+    fn merged_digests_match_one_built_from_all_samples() {
+        let values = shuffled(40_000);
+        let mut whole = digest_of(&values);
+        let mut merged = TDigest::new(100.0);
+        for part in values.chunks(10_000) {
+            merged.merge(&digest_of(part));
+        }
+        assert_eq!(merged.count, whole.count);
+        assert_eq!(merged.sum, whole.sum);
+        assert_eq!((merged.min, merged.max), (1.0, 40_000.0));
+        for q in [0.001, 0.01, 0.5, 0.99, 0.999] {
+            let (a, b) = (merged.quantile(q), whole.quantile(q));
+            assert!((a - b).abs() <= 0.002 * 40_000.0, "q {}: merged {} vs whole {}", q, a, b);
+        }
+    }
+
+    #[test]
+    fn merging_an_empty_digest_changes_nothing() {
+        let mut digest = digest_of(&[3.0, 1.0, 2.0]);
+        digest.merge(&TDigest::new(100.0));
+        assert_eq!(digest.count, 3);
+        assert_eq!(digest.quantile(0.5), 2.0);
+        assert_eq!(TDigest::new(100.0).quantile(0.5), 0.0);
+    }
+
+    #[test]
+    fn serialization_round_trips() {
+        let mut digest = digest_of(&shuffled(10_000));
+        let mut restored: TDigest = serde_json::from_str(&serde_json::to_string(&digest).unwrap()).unwrap();
+        assert_eq!((restored.count, restored.min, restored.max), (10_000, 1.0, 10_000.0));
+        for q in [0.01, 0.5, 0.99] {
+            // JSON keeps the centroid means to within an ulp or so
+            assert!((restored.quantile(q) - digest.quantile(q)).abs() < 1e-6);
+        }
+    }
+}
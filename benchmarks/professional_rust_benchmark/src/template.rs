@@ -0,0 +1,94 @@
+// User-supplied report templates (--template), so a team can render results
+// into its own wiki markup, Confluence pages or chat messages without
+// touching the reporter. Templates are Handlebars, rendered by the
+// handlebars crate. Values are written as they are rather than HTML-escaped,
+// as most targets are not HTML; the html, json and csv helpers escape a value
+// for a target that needs it ({{html name}}, {{csv display.mean}}).
+
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use serde_json::Value;
+
+use crate::pivot::{csv_field, escape_html};
+
+const NAME: &str = "template";
+
+// Strings without their quotes, anything else as JSON
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+handlebars_helper!(html: |value: Json| escape_html(&to_text(value)));
+handlebars_helper!(json: |value: Json| value.to_string());
+handlebars_helper!(csv: |value: Json| csv_field(&to_text(value)));
+
+#[derive(Debug)]
+pub struct Template {
+    registry: Handlebars<'static>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(no_escape);
+        registry.register_helper("html", Box::new(html));
+        registry.register_helper("json", Box::new(json));
+        registry.register_helper("csv", Box::new(csv));
+        registry.register_template_string(NAME, source).map_err(|e| e.to_string())?;
+        Ok(Self { registry })
+    }
+
+    pub fn render(&self, context: &Value) -> Result<String, String> {
+        self.registry.render(NAME, context).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(source: &str, context: Value) -> String {
+        Template::parse(source).unwrap_or_else(|e| panic!("{}: {}", source, e)).render(&context).unwrap()
+    }
+
+    #[test]
+    fn values_and_helpers() {
+        let context = json!({"name": "a<b>,\"c\"", "run": {"commit": "abc"}, "list": [1, 2], "n": 1.5, "none": null});
+        assert_eq!(render("{{name}}", context.clone()), "a<b>,\"c\"");
+        assert_eq!(render("{{html name}}", context.clone()), "a&lt;b&gt;,&quot;c&quot;");
+        assert_eq!(render("{{csv name}}", context.clone()), "\"a<b>,\"\"c\"\"\"");
+        assert_eq!(render("{{json name}} {{json list}}", context.clone()), "\"a<b>,\\\"c\\\"\" [1,2]");
+        assert_eq!(render("{{run.commit}} {{list.[1]}} {{n}}", context.clone()), "abc 2 1.5");
+        assert_eq!(render("[{{missing}}{{none}}{{run.missing.deeper}}]", context), "[]");
+    }
+
+    #[test]
+    fn each_and_conditions() {
+        let context = json!({"unit": "ns", "results": [{"name": "a", "mean": 1}, {"name": "b", "mean": 2}], "map": {"x": 1, "y": 2}, "empty": []});
+        assert_eq!(render("{{#each results}}{{@index}}:{{name}}={{this.mean}}{{../unit}}{{#unless @last}}, {{/unless}}{{/each}}", context.clone()),
+            "0:a=1ns, 1:b=2ns");
+        assert_eq!(render("{{#each results}}{{#if @first}}first {{/if}}{{name}} {{/each}}", context.clone()), "first a b ");
+        assert_eq!(render("{{#each map}}{{@key}}={{this}};{{/each}}", context.clone()), "x=1;y=2;");
+        assert_eq!(render("{{#each empty}}x{{else}}none{{/each}}", context.clone()), "none");
+        assert_eq!(render("{{#if missing}}y{{else}}n{{/if}}", context), "n");
+    }
+
+    #[test]
+    fn standalone_tags_leave_no_blank_lines() {
+        let source = "# Results\n{{! one line per benchmark }}\n{{#each results}}\n  - {{name}}\n{{/each}}\ndone {{#if ok}}yes{{/if}}\n";
+        assert_eq!(render(source, json!({"results": [{"name": "a"}, {"name": "b"}], "ok": true})), "# Results\n  - a\n  - b\ndone yes\n");
+    }
+
+    // Mistakes are reported when the template is loaded, with their line
+    #[test]
+    fn malformed_templates_are_errors() {
+        let error = Template::parse("a\n{{#each list}}\n{{/if}}").unwrap_err();
+        assert!(error.contains(":3:1"), "{}", error);
+        assert!(Template::parse("{{#if x}}").is_err());
+        assert!(Template::parse("{{name").is_err());
+    }
+}
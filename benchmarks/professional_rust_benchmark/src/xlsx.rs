@@ -0,0 +1,207 @@
+// An Excel workbook of the results (--xlsx) for people who share performance
+// comparisons as spreadsheets: a Summary sheet with the CSV's columns, a
+// Samples sheet with each benchmark's distribution as (value, count) rows
+// from the histogram or, with --tdigest, the digest's centroids, and the
+// System Info the run was measured on. The workbook is written by
+// rust_xlsxwriter.
+
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+
+use crate::{format_labels, BenchmarkStatus, BenchmarkSuite};
+
+enum Cell {
+    Text(String),
+    Number(f64),
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::Text(text.to_string())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(value: f64) -> Self {
+        Cell::Number(value)
+    }
+}
+
+struct Sheet {
+    name: &'static str,
+    header: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+pub fn to_xlsx(suite: &BenchmarkSuite) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    for sheet in [summary_sheet(suite), samples_sheet(suite), system_sheet(suite)] {
+        // The header row is bold and stays in view when scrolling
+        let worksheet = workbook.add_worksheet().set_name(sheet.name)?;
+        worksheet.write_row_with_format(0, 0, sheet.header, &bold)?;
+        worksheet.set_freeze_panes(1, 0)?;
+        for (index, row) in sheet.rows.iter().enumerate() {
+            let number = index as u32 + 1;
+            for (column, cell) in row.iter().enumerate() {
+                let column = column as u16;
+                match cell {
+                    Cell::Text(text) => {
+                        worksheet.write_string(number, column, text)?;
+                    }
+                    // Spreadsheets have no NaN or infinity; leave the cell empty
+                    Cell::Number(value) if value.is_finite() => {
+                        worksheet.write_number(number, column, *value)?;
+                    }
+                    Cell::Number(_) => {}
+                }
+            }
+        }
+    }
+    workbook.save_to_buffer()
+}
+
+fn summary_sheet(suite: &BenchmarkSuite) -> Sheet {
+    let header = vec!["benchmark", "status", "runtime", "measurement", "unit", "iterations", "errors",
+        "mean", "median", "stddev", "min", "max", "p95", "p99", "cv", "margin"];
+    let rows = suite.results.iter().map(|result| {
+        let stats = &result.stats;
+        let status = match &result.status {
+            BenchmarkStatus::Completed => "completed".to_string(),
+            BenchmarkStatus::Skipped(reason) => format!("skipped: {}", reason),
+        };
+        let mut row: Vec<Cell> = vec![result.name.as_str().into(), status.into(), result.runtime.as_str().into(),
+            result.measurement.as_str().into(), stats.unit.as_str().into(),
+            (result.iterations as f64).into(), (result.error_count as f64).into()];
+        for value in [stats.mean_ns, stats.median_ns, stats.stddev_ns, stats.min_ns, stats.max_ns, stats.p95_ns, stats.p99_ns, stats.cv, stats.margin_ns] {
+            row.push(value.into());
+        }
+        row
+    }).collect();
+    Sheet { name: "Summary", header, rows }
+}
+
+fn samples_sheet(suite: &BenchmarkSuite) -> Sheet {
+    let mut rows = Vec::new();
+    for result in &suite.results {
+        let stats = &result.stats;
+        let distribution: Vec<(f64, u64)> = match &stats.digest {
+            Some(digest) => digest.centroids().collect(),
+            None => stats.histogram.midpoints().collect(),
+        };
+        for (value, count) in distribution {
+            rows.push(vec![result.name.as_str().into(), stats.unit.as_str().into(), value.into(), (count as f64).into()]);
+        }
+    }
+    cap_rows(&mut rows, MAX_ROWS - 1);
+    Sheet { name: "Samples", header: vec!["benchmark", "unit", "value", "count"], rows }
+}
+
+// Past `limit` rows the last one that fits says how many were left out, as
+// Excel refuses to open a sheet longer than MAX_ROWS
+fn cap_rows(rows: &mut Vec<Vec<Cell>>, limit: usize) {
+    if rows.len() <= limit {
+        return;
+    }
+    let omitted = rows.len() - limit + 1;
+    rows.truncate(limit - 1);
+    rows.push(vec![format!("{} more rows omitted: a sheet holds at most {} rows, the results JSON has them all", omitted, MAX_ROWS).into()]);
+}
+
+fn system_sheet(suite: &BenchmarkSuite) -> Sheet {
+    let info = &suite.system_info;
+    let time = chrono::DateTime::from_timestamp(info.timestamp as i64, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    let mut rows: Vec<Vec<Cell>> = vec![
+        vec!["time".into(), time.into()],
+        vec!["git commit".into(), info.git_commit.clone().unwrap_or_default().into()],
+        vec!["git branch".into(), info.git_branch.clone().unwrap_or_default().into()],
+        vec!["git dirty".into(), info.git_dirty.map(|dirty| dirty.to_string()).unwrap_or_default().into()],
+        vec!["version".into(), info.rust_version.as_str().into()],
+        vec!["os".into(), info.os.as_str().into()],
+        vec!["arch".into(), info.arch.as_str().into()],
+        vec!["cpus".into(), (info.num_cpus as f64).into()],
+    ];
+    if let Some(fingerprint) = &info.fingerprint {
+        rows.push(vec!["cpu model".into(), fingerprint.cpu_model.as_str().into()]);
+        rows.push(vec!["rustc".into(), fingerprint.rustc.as_str().into()]);
+        rows.push(vec!["allocator".into(), fingerprint.allocator.as_str().into()]);
+        rows.push(vec!["profile".into(), fingerprint.profile.as_str().into()]);
+    }
+    // Seeds are 64-bit, more than a spreadsheet number holds exactly
+    rows.push(vec!["seed".into(), suite.seed.to_string().into()]);
+    rows.push(vec!["labels".into(), format_labels(&suite.labels).into()]);
+    Sheet { name: "System Info", header: vec!["property", "value"], rows }
+}
+
+// Rows per sheet in Excel, the header included
+const MAX_ROWS: usize = 1_048_576;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BenchmarkResult, BenchmarkStats, StatsConfig, SystemInfo};
+
+    fn suite() -> BenchmarkSuite {
+        let mut result = BenchmarkResult::new("spawn <&> \u{1b}[31mred".to_string());
+        result.stats = BenchmarkStats::with_precision(3);
+        for value in 1..=1000 {
+            result.stats.record(value as f64);
+        }
+        result.stats.calculate(&StatsConfig::default());
+        result.iterations = 1000;
+        BenchmarkSuite {
+            schema_version: 0,
+            system_info: SystemInfo::current(None),
+            labels: Default::default(),
+            seed: 7,
+            shuffle_seed: None,
+            deterministic_seed: None,
+            results: vec![result, BenchmarkResult::skipped("skipped", "no network".to_string())],
+        }
+    }
+
+    #[test]
+    fn rows_past_the_limit_are_summarized() {
+        let mut rows: Vec<Vec<Cell>> = (0..5).map(|i| vec![(i as f64).into()]).collect();
+        cap_rows(&mut rows, 5);
+        assert_eq!(rows.len(), 5);
+        cap_rows(&mut rows, 3);
+        assert_eq!(rows.len(), 3);
+        assert!(matches!(&rows[1][0], Cell::Number(value) if *value == 1.0));
+        assert!(matches!(&rows[2][0], Cell::Text(text) if text.starts_with("3 more rows omitted")));
+    }
+
+    // Info-ZIP's unzip checks every entry's CRC; the sheets hold the rows
+    // and control characters in names are escaped rather than breaking the XML
+    #[test]
+    fn workbook_passes_unzip() {
+        let path = std::env::temp_dir().join(format!("xlsx-test-{}.xlsx", std::process::id()));
+        std::fs::write(&path, to_xlsx(&suite()).unwrap()).unwrap();
+        let test = std::process::Command::new("unzip").arg("-t").arg(&path).output();
+        let part = |name: &str| std::process::Command::new("unzip").arg("-p").arg(&path).arg(name).output();
+        let (summary, strings) = (part("xl/worksheets/sheet1.xml"), part("xl/sharedStrings.xml"));
+        std::fs::remove_file(&path).unwrap();
+        let (test, summary, strings) = match (test, summary, strings) {
+            (Ok(test), Ok(summary), Ok(strings)) => (test, summary, strings),
+            _ => {
+                eprintln!("unzip not found, skipping");
+                return;
+            }
+        };
+        let listing = String::from_utf8_lossy(&test.stdout);
+        assert!(test.status.success(), "{}{}", listing, String::from_utf8_lossy(&test.stderr));
+        assert!(listing.contains("No errors detected"), "{}", listing);
+        assert!(listing.contains("xl/worksheets/sheet3.xml"), "{}", listing);
+        let sheet = String::from_utf8(summary.stdout).unwrap();
+        assert!(sheet.contains("<row r=\"3\""), "{}", sheet);
+        let strings = String::from_utf8(strings.stdout).unwrap();
+        assert!(strings.contains("spawn &lt;&amp;&gt; _x001B_[31mred"), "{}", strings);
+    }
+}
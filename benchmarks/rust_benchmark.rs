@@ -1,5 +1,5 @@
 use std::env;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::task::JoinSet;
@@ -23,7 +23,103 @@ async fn handle_single_request(user_id: usize) -> String {
     format!("用户{} (已处理)", 1000 + user_id)
 }
 
-async fn handle_concurrent_requests_tokio(request_count: usize) {
+// "10s"、"500ms"或秒数
+fn parse_duration(text: &str) -> Option<Duration> {
+    let (value, scale) = if let Some(ms) = text.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = text.strip_suffix('s') {
+        (s, 1.0)
+    } else {
+        (text, 1.0)
+    };
+    Duration::try_from_secs_f64(value.parse::<f64>().ok()? * scale).ok()
+}
+
+// 每个请求从创建任务到完成的耗时(ns)，以及完成时刻相对测试开始的偏移(ns)；
+// spawn_index是启动顺序，completion_rank是完成顺序
+struct RequestSample {
+    completed_at_ns: u64,
+    latency_ns: u64,
+    spawn_index: usize,
+    completion_rank: usize,
+}
+
+// 归并排序统计逆序对数：启动较早却完成较晚的任务对
+fn count_inversions(values: &mut [usize], buffer: &mut Vec<usize>) -> u64 {
+    if values.len() < 2 {
+        return 0;
+    }
+    let middle = values.len() / 2;
+    let mut inversions = count_inversions(&mut values[..middle], buffer) + count_inversions(&mut values[middle..], buffer);
+    buffer.clear();
+    let (mut left, mut right) = (0, middle);
+    while left < middle && right < values.len() {
+        if values[left] <= values[right] {
+            buffer.push(values[left]);
+            left += 1;
+        } else {
+            inversions += (middle - left) as u64;
+            buffer.push(values[right]);
+            right += 1;
+        }
+    }
+    buffer.extend_from_slice(&values[left..middle]);
+    buffer.extend_from_slice(&values[right..]);
+    values.copy_from_slice(buffer);
+    inversions
+}
+
+// 完成顺序与启动顺序的偏离：逆序对比例0表示严格FIFO，1表示严格LIFO；
+// 相邻逆序比例反映刚启动的任务是否抢先执行（如tokio工作线程的LIFO槽）
+// `ranks[i]`是第i个启动的任务的完成名次
+fn print_completion_order(mut ranks: Vec<usize>) {
+    let n = ranks.len();
+    if n < 2 {
+        println!("   完成顺序: 样本不足");
+        return;
+    }
+    let in_place = ranks.iter().enumerate().filter(|&(i, &rank)| i == rank).count();
+    let adjacent_reversed = ranks.windows(2).filter(|pair| pair[1] < pair[0]).count();
+    let mean_displacement = ranks.iter().enumerate().map(|(i, &rank)| i.abs_diff(rank) as f64).sum::<f64>() / n as f64;
+    let max_displacement = ranks.iter().enumerate().map(|(i, &rank)| i.abs_diff(rank)).max().unwrap_or(0);
+    let inversions = count_inversions(&mut ranks, &mut Vec::with_capacity(n));
+    let inversion_ratio = inversions as f64 / (n as f64 * (n - 1) as f64 / 2.0);
+    let verdict = if inversion_ratio < 0.25 {
+        "接近FIFO"
+    } else if inversion_ratio > 0.75 {
+        "接近LIFO"
+    } else {
+        "混合"
+    };
+    println!("   完成顺序 vs 启动顺序:");
+    println!("     逆序对比例: {:.6} ({}，{} 对)", inversion_ratio, verdict, inversions);
+    println!("     相邻逆序比例: {:.2}% (后启动的任务先完成)", adjacent_reversed as f64 * 100.0 / (n - 1) as f64);
+    println!("     位置不变: {:.2}%", in_place as f64 * 100.0 / n as f64);
+    println!("     平均位移: {:.1} 个位置 (最大 {})", mean_displacement, max_displacement);
+}
+
+// 已排序延迟(ns)的分位数，单位ms
+fn latency_percentile(sorted: &[u64], p: f64) -> f64 {
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize] as f64 / 1_000_000.0
+}
+
+fn print_sample_stats(label: &str, samples: &[RequestSample], window: Duration) {
+    if samples.is_empty() {
+        println!("   {}: 无样本", label);
+        return;
+    }
+    let mut latencies: Vec<u64> = samples.iter().map(|s| s.latency_ns).collect();
+    latencies.sort_unstable();
+    let percentile = |p: f64| latency_percentile(&latencies, p);
+    let mean = latencies.iter().sum::<u64>() as f64 / latencies.len() as f64 / 1_000_000.0;
+    println!("   {}: {} 个请求, 平均延迟 {:.4} ms, p50 {:.4} ms, p99 {:.4} ms",
+        label, samples.len(), mean, percentile(0.50), percentile(0.99));
+    if !window.is_zero() {
+        println!("   {}吞吐量: {:.0} 请求/秒 (窗口 {} ms)", label, samples.len() as f64 / window.as_secs_f64(), window.as_millis());
+    }
+}
+
+async fn handle_concurrent_requests_tokio(request_count: usize, warmup: Duration, completion_order: bool) {
     let start_time = Instant::now();
     let initial_memory = get_memory_usage_kb();
     
@@ -41,77 +137,317 @@ async fn handle_concurrent_requests_tokio(request_count: usize) {
     // 创建与协程数量相同的async任务
     for i in 0..request_count {
         let completed_clone = Arc::clone(&completed);
+        let spawned_at = Instant::now();
         join_set.spawn(async move {
             let result = handle_single_request(i).await;
             
             let current_completed = completed_clone.fetch_add(1, Ordering::Relaxed) + 1;
+            #[allow(clippy::manual_is_multiple_of)]
             if current_completed % (request_count / 10).max(1) == 0 || current_completed == request_count {
                 println!("已完成 {}/{} 个任务 ({}%)", 
                     current_completed, request_count, (current_completed * 100) / request_count);
             }
             
-            result
+            let sample = RequestSample {
+                completed_at_ns: start_time.elapsed().as_nanos() as u64,
+                latency_ns: spawned_at.elapsed().as_nanos() as u64,
+                spawn_index: i,
+                completion_rank: current_completed - 1,
+            };
+            (result, sample)
         });
     }
     
     // 等待所有任务完成
     let mut results = Vec::new();
+    let mut samples = Vec::new();
     while let Some(result) = join_set.join_next().await {
         match result {
-            Ok(res) => results.push(res),
+            Ok((res, sample)) => {
+                results.push(res);
+                samples.push(sample);
+            }
             Err(e) => eprintln!("任务错误: {}", e),
         }
     }
     
     let end_time = Instant::now();
     let duration = end_time.duration_since(start_time);
+    
+    // 完成顺序按全部请求统计，包括预热窗口内的；有任务出错时排名不连续，不做统计
+    let completion_ranks = (completion_order && samples.len() == request_count).then(|| {
+        let mut ranks = vec![0usize; samples.len()];
+        for sample in &samples {
+            ranks[sample.spawn_index] = sample.completion_rank;
+        }
+        ranks
+    });
+    
+    // 预热窗口内完成的请求单独统计，不计入最终结果（运行时和分配器的冷启动开销）
+    let warmup_ns = warmup.as_nanos() as u64;
+    let (warmup_samples, measured_samples): (Vec<RequestSample>, Vec<RequestSample>) =
+        samples.into_iter().partition(|s| s.completed_at_ns < warmup_ns);
     let final_memory = get_memory_usage_kb();
     let memory_delta = final_memory.saturating_sub(initial_memory);
     
     println!("{}", "-".repeat(50));
     println!("Rust Tokio方式完成！");
-    println!("   总请求数: {} 个", request_count);
-    println!("   总耗时: {} ms", duration.as_millis());
+    // 总计只包含预热窗口之后完成的请求
+    let (counted, window) = if warmup.is_zero() {
+        print_sample_stats("请求延迟", &measured_samples, Duration::ZERO);
+        (request_count, duration)
+    } else {
+        println!("   预热窗口: 前 {} ms", warmup.as_millis());
+        print_sample_stats("预热", &warmup_samples, warmup.min(duration));
+        let measured_window = duration.saturating_sub(warmup);
+        print_sample_stats("稳态", &measured_samples, measured_window);
+        if measured_samples.is_empty() {
+            println!("   警告: 所有请求都在预热窗口内完成，没有可统计的样本，请增加请求数或缩短 --warmup-duration");
+        }
+        println!("   以下总计不含预热窗口:");
+        (measured_samples.len(), measured_window)
+    };
+    println!("   总请求数: {} 个", counted);
+    println!("   总耗时: {} ms", window.as_millis());
     
-    if request_count > 0 {
+    if counted > 0 {
         println!("   平均耗时: {:.4} ms/请求", 
-            duration.as_nanos() as f64 / request_count as f64 / 1_000_000.0);
+            window.as_nanos() as f64 / counted as f64 / 1_000_000.0);
     }
     
-    if duration.as_millis() > 0 {
+    if window.as_millis() > 0 {
         println!("   吞吐量: {} 请求/秒", 
-            (request_count as u128 * 1000) / duration.as_millis());
+            (counted as u128 * 1000) / window.as_millis());
     }
     
     println!("   内存变化: {} KB → {} KB (增加 {} KB)", 
         initial_memory, final_memory, memory_delta);
     
-    if request_count > 0 {
-        println!("   单请求内存: {} bytes/请求", (memory_delta * 1024) / request_count);
+    if let Some(per_request) = (memory_delta * 1024).checked_div(request_count) {
+        println!("   单请求内存: {} bytes/请求", per_request);
     }
     
     println!("   Task总数: {} 个", request_count);
     println!("   并发策略: Tokio异步运行时");
+    if let Some(ranks) = completion_ranks {
+        print_completion_order(ranks);
+    } else if completion_order {
+        println!("   完成顺序: 有任务失败，跳过统计");
+    }
+    println!("   程序结束: [{}]", get_current_time());
+}
+
+// 容量验收：实际完成速率不低于目标的99%即视为维持住了目标速率
+const SUSTAIN_RPS_TOLERANCE: f64 = 0.01;
+
+struct SustainTarget {
+    rps: f64,
+    duration: Duration,
+    p99_max: Duration,
+}
+
+// 开环负载：第i个请求计划在 i/rps 时刻发出，延迟从计划时刻算起，所以生成端
+// 或调度器跟不上时积压的等待也计入延迟，不会因为放慢发送而掩盖排队。
+// 预热窗口内完成的请求不参与判定。返回是否通过。
+async fn verify_sustained_throughput(target: &SustainTarget, warmup: Duration) -> bool {
+    let start_time = Instant::now();
+    let total = (target.rps * target.duration.as_secs_f64()).round() as usize;
+    let interval_ns = 1e9 / target.rps;
+    
+    println!("Rust Tokio容量验收：{:.0} 请求/秒，持续 {} ms，要求 p99 ≤ {:.3} ms",
+        target.rps, target.duration.as_millis(), target.p99_max.as_secs_f64() * 1000.0);
+    println!("计划请求数: {} 个", total);
+    println!("开始时间: [{}]", get_current_time());
+    println!("{}", "-".repeat(50));
+    
+    let mut join_set = JoinSet::new();
+    let mut samples = Vec::with_capacity(total);
+    let mut failures = 0usize;
+    let mut collect = |result: Result<(String, RequestSample), tokio::task::JoinError>, samples: &mut Vec<RequestSample>| match result {
+        Ok((_, sample)) => samples.push(sample),
+        Err(e) => {
+            eprintln!("任务错误: {}", e);
+            failures += 1;
+        }
+    };
+    let mut ticker = tokio::time::interval(Duration::from_millis(1));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut sent = 0;
+    while sent < total {
+        ticker.tick().await;
+        // 补发到当前时刻为止应已发出的全部请求
+        let due = ((start_time.elapsed().as_nanos() as f64 / interval_ns) as usize + 1).min(total);
+        for i in sent..due {
+            let scheduled_ns = (i as f64 * interval_ns) as u64;
+            join_set.spawn(async move {
+                let result = handle_single_request(i).await;
+                let completed_at_ns = start_time.elapsed().as_nanos() as u64;
+                // 此模式不统计完成顺序
+                let sample = RequestSample {
+                    completed_at_ns,
+                    latency_ns: completed_at_ns.saturating_sub(scheduled_ns),
+                    spawn_index: i,
+                    completion_rank: 0,
+                };
+                (result, sample)
+            });
+        }
+        sent = due;
+        while let Some(result) = join_set.try_join_next() {
+            collect(result, &mut samples);
+        }
+    }
+    while let Some(result) = join_set.join_next().await {
+        collect(result, &mut samples);
+    }
+    
+    // 全部请求完成的时刻；比计划时长更早结束时按计划时长算
+    let end = start_time.elapsed().max(target.duration);
+    let warmup_ns = warmup.as_nanos() as u64;
+    let measured: Vec<RequestSample> = samples.into_iter().filter(|s| s.completed_at_ns >= warmup_ns).collect();
+    let window = end.saturating_sub(warmup);
+    
+    println!("Rust Tokio容量验收完成！");
+    if !warmup.is_zero() {
+        println!("   预热窗口: 前 {} ms (不参与判定)", warmup.as_millis());
+    }
+    print_sample_stats("稳态", &measured, window);
+    if measured.is_empty() || window.is_zero() {
+        println!("   结论: 未通过 (预热窗口之后没有完成的请求)");
+        return false;
+    }
+    
+    let achieved = measured.len() as f64 / window.as_secs_f64();
+    let rps_margin = achieved / target.rps - 1.0;
+    let rps_ok = rps_margin >= -SUSTAIN_RPS_TOLERANCE;
+    let mut latencies: Vec<u64> = measured.iter().map(|s| s.latency_ns).collect();
+    latencies.sort_unstable();
+    let p99 = latency_percentile(&latencies, 0.99);
+    let p99_max = target.p99_max.as_secs_f64() * 1000.0;
+    let p99_margin = (p99_max - p99) / p99_max;
+    let p99_ok = p99 <= p99_max;
+    let verdict = |ok: bool| if ok { "通过" } else { "未通过" };
+    
+    println!("   吞吐量: {:.0} / {:.0} 请求/秒, 余量 {:+.2}% (容差 -{:.0}%) [{}]",
+        achieved, target.rps, rps_margin * 100.0, SUSTAIN_RPS_TOLERANCE * 100.0, verdict(rps_ok));
+    println!("   p99延迟: {:.4} / {:.4} ms, 余量 {:+.2}% [{}]", p99, p99_max, p99_margin * 100.0, verdict(p99_ok));
+    if failures > 0 {
+        println!("   失败请求: {} 个 [未通过]", failures);
+    }
+    let passed = rps_ok && p99_ok && failures == 0;
+    println!("   结论: {}", verdict(passed));
     println!("   程序结束: [{}]", get_current_time());
+    passed
 }
 
-#[tokio::main]
-async fn main() {
+fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("用法: {} <request_count>", args[0]);
+    let usage = format!("用法: {} <request_count> [--warmup-duration <时长，如10s或500ms>] [--completion-order] \
+        [--runtime <multi-thread|current-thread>] [--workers <n>]\n\
+        容量验收: {} --sustain <rps> --duration <时长> --p99-max <时长> [--warmup-duration <时长>] [--runtime ...] [--workers <n>]",
+        args[0], args[0]);
+    let mut request_count = None;
+    let mut warmup = Duration::ZERO;
+    let mut completion_order = false;
+    let mut current_thread = false;
+    let mut workers = None;
+    let mut sustain_rps = None;
+    let mut sustain_duration = None;
+    let mut p99_max = None;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        let valid = match arg.as_str() {
+            "--warmup-duration" => rest.next().and_then(|text| parse_duration(text)).map(|duration| warmup = duration).is_some(),
+            "--completion-order" => {
+                completion_order = true;
+                true
+            }
+            "--runtime" => match rest.next().map(String::as_str) {
+                Some("multi-thread") => {
+                    current_thread = false;
+                    true
+                }
+                Some("current-thread") => {
+                    current_thread = true;
+                    true
+                }
+                _ => false,
+            },
+            "--workers" => rest.next().and_then(|text| text.parse::<usize>().ok()).filter(|&n| n > 0).map(|n| workers = Some(n)).is_some(),
+            "--sustain" => rest.next().and_then(|text| text.parse::<f64>().ok()).filter(|&rps| rps > 0.0).map(|rps| sustain_rps = Some(rps)).is_some(),
+            "--duration" => rest.next().and_then(|text| parse_duration(text)).filter(|d| !d.is_zero()).map(|d| sustain_duration = Some(d)).is_some(),
+            "--p99-max" => rest.next().and_then(|text| parse_duration(text)).filter(|d| !d.is_zero()).map(|d| p99_max = Some(d)).is_some(),
+            count if request_count.is_none() => {
+                request_count = Some(count.parse().unwrap_or(0));
+                true
+            }
+            _ => false,
+        };
+        if !valid {
+            println!("{}", usage);
+            return;
+        }
+    }
+    let sustain = match (sustain_rps, sustain_duration, p99_max) {
+        (Some(rps), Some(duration), Some(p99_max)) if request_count.is_none() && !completion_order => {
+            Some(SustainTarget { rps, duration, p99_max })
+        }
+        (None, None, None) => None,
+        _ => {
+            println!("{}", usage);
+            println!("--sustain 需要同时给出 --duration 和 --p99-max，且不能与 <request_count> 或 --completion-order 一起使用");
+            return;
+        }
+    };
+    let request_count = match (request_count, &sustain) {
+        (Some(count), _) => count,
+        (None, Some(_)) => 0,
+        (None, None) => {
+            println!("{}", usage);
+            return;
+        }
+    };
+    if current_thread && workers.is_some() {
+        println!("--workers 只适用于 multi-thread 运行时");
         return;
     }
     
-    let request_count: usize = args[1].parse().unwrap_or(0);
+    // 调度器的排队方式随运行时配置而变，--completion-order 可对比不同配置下的完成顺序
+    let mut builder = if current_thread {
+        tokio::runtime::Builder::new_current_thread()
+    } else {
+        tokio::runtime::Builder::new_multi_thread()
+    };
+    if let Some(workers) = workers {
+        builder.worker_threads(workers);
+    }
+    let runtime = builder.enable_all().build().expect("无法创建Tokio运行时");
+    let runtime_label = if current_thread {
+        "current-thread".to_string()
+    } else {
+        format!("multi-thread ({} 个工作线程)", workers.unwrap_or_else(num_cpus::get))
+    };
     
     println!("========================================");
     println!("Rust Tokio 高并发性能测试");
     println!("========================================");
-    println!("请求数量: {} 个", request_count);
+    if sustain.is_none() {
+        println!("请求数量: {} 个", request_count);
+    }
     println!("每个请求模拟0ms处理时间 (纯调度测试)");
+    println!("运行时: {}", runtime_label);
+    if !warmup.is_zero() {
+        println!("预热窗口: {} ms (窗口内完成的请求单独报告，不计入最终统计)", warmup.as_millis());
+    }
     println!("========================================");
     println!();
     
-    handle_concurrent_requests_tokio(request_count).await;
+    if let Some(target) = sustain {
+        // 未通过时以非零状态退出，便于在发布流水线中作为验收步骤
+        if !runtime.block_on(verify_sustained_throughput(&target, warmup)) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    runtime.block_on(handle_concurrent_requests_tokio(request_count, warmup, completion_order));
 }